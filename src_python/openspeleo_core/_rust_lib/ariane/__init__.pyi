@@ -2,27 +2,770 @@
 # ruff: noqa: E501, F401, F403, F405
 
 import builtins
+from openspeleo_core._rust_lib import mapping
 import typing
 __all__ = [
+    "Diagnostic",
+    "GeomagneticModel",
+    "NumberFormat",
+    "Shot",
+    "ShotTable",
+    "Survey",
+    "SurveyDiff",
+    "SurveyMerge",
+    "SurveyStatistics",
+    "TmlEntry",
+    "UtmCoordinate",
+    "XmlWriterOptions",
+    "convert_units",
+    "correct_declination",
+    "dict_to_ariane_tml_file",
+    "dict_to_ariane_tmlu_file",
+    "dict_to_gpx_file",
+    "dict_to_gpx_str",
+    "dict_to_xml_file",
     "dict_to_xml_str",
+    "dict_to_xml_stream",
+    "diff_surveys",
+    "export_shots_csv",
+    "list_ariane_tml_entries",
+    "load_ariane_file_to_dict",
+    "load_ariane_tml_file_to_arrays",
+    "load_ariane_tml_file_to_arrow",
     "load_ariane_tml_file_to_dict",
+    "load_pockettopo_txt_file_to_dict",
+    "load_therion_th_file_to_dict",
+    "load_topodroid_csv_file_to_dict",
+    "local_to_wgs84",
+    "magnetic_declination",
+    "merge_surveys",
+    "read_ariane_tml_entry",
+    "shot_arrays",
+    "shots_to_arrow",
+    "station_coordinates",
+    "survey_statistics",
+    "utm_to_wgs84",
+    "validate",
+    "wgs84_to_local",
+    "wgs84_to_utm",
+    "xml_bytes_to_dict",
     "xml_str_to_dict",
 ]
 
-def dict_to_xml_str(data: dict, root_name: builtins.str) -> builtins.str: ...
+@typing.final
+class Diagnostic:
+    r"""
+    A problem found in a survey.
+    """
+    @property
+    def code(self) -> builtins.str:
+        r"""
+        Stable identifier of the check (`duplicate-id`, `unknown-from-station`, ...).
+        """
+    @property
+    def message(self) -> builtins.str: ...
+    @property
+    def shot_id(self) -> typing.Optional[builtins.str]:
+        r"""
+        `ID` of the shot concerned, `None` when it has none.
+        """
+    @property
+    def field(self) -> typing.Optional[builtins.str]:
+        r"""
+        Field of the shot concerned, `None` when the whole shot is.
+        """
+    @property
+    def severity(self) -> builtins.str: ...
+    @property
+    def is_error(self) -> builtins.bool: ...
+    def to_dict(self) -> typing.Any: ...
+    def __str__(self) -> builtins.str: ...
+    def __repr__(self) -> builtins.str: ...
+
+@typing.final
+class GeomagneticModel:
+    r"""
+    Spherical harmonic model of the main geomagnetic field, in the WMM/IGRF `.COF` format.
+    """
+    @property
+    def name(self) -> builtins.str: ...
+    @property
+    def epoch(self) -> builtins.float:
+        r"""
+        Decimal year the coefficients refer to.
+        """
+    @property
+    def valid_until(self) -> builtins.float:
+        r"""
+        Decimal year the validity window of the model closes at, five years after its epoch.
+        """
+    @staticmethod
+    def from_file(path: builtins.str) -> GeomagneticModel:
+        r"""
+        Loads a coefficient file in the WMM/IGRF `.COF` format, e.g. a newer World Magnetic
+        Model release.
+        """
+    @staticmethod
+    def embedded() -> builtins.list[GeomagneticModel]:
+        r"""
+        The embedded World Magnetic Model releases, oldest first.
+        """
+    def declination(self, latitude: builtins.float, longitude: builtins.float, date: typing.Any, altitude: builtins.float = 0.0) -> builtins.float:
+        r"""
+        Declination in degrees (east positive) at `date`, a `datetime.date`, an ISO 8601 string
+        or a decimal year. `altitude` is in meters above the WGS84 ellipsoid. Raises a
+        `ValueError` when `date` is outside the five years following the model epoch.
+        """
+    def __repr__(self) -> builtins.str: ...
+
+@typing.final
+class NumberFormat:
+    r"""
+    Formatting policy applied to numeric values when writing XML.
+    
+    Styles are given per element name, either as `"auto"`, `"float"`, `"int"` or as a number
+    of decimals. Exponent notation is never used.
+    """
+    @property
+    def default(self) -> typing.Any:
+        r"""
+        Style used for elements without a specific rule.
+        """
+    def __new__(cls, default: typing.Optional[typing.Any] = None, fields: typing.Optional[dict] = None) -> NumberFormat: ...
+    @staticmethod
+    def ariane() -> NumberFormat:
+        r"""
+        Number formatting reproducing the files written by Ariane.
+        """
+    def style_for(self, name: builtins.str) -> typing.Any:
+        r"""
+        Style of `name`, falling back on the default style.
+        """
+    def __repr__(self) -> builtins.str: ...
+
+@typing.final
+class Shot:
+    r"""
+    A shot of a `Survey`, with its measurements as numbers in the survey unit.
+    """
+    @property
+    def id(self) -> typing.Optional[builtins.str]: ...
+    @property
+    def from_id(self) -> typing.Optional[builtins.str]: ...
+    @property
+    def length(self) -> typing.Optional[builtins.float]: ...
+    @property
+    def azimuth(self) -> typing.Optional[builtins.float]:
+        r"""
+        Azimuth in degrees, see `Survey.magnetic`.
+        """
+    @property
+    def inclination(self) -> typing.Optional[builtins.float]: ...
+    @property
+    def depth(self) -> typing.Optional[builtins.float]: ...
+    @property
+    def depth_in(self) -> typing.Optional[builtins.float]:
+        r"""
+        Depth at the start of the shot, `-1.0` when not recorded.
+        """
+    @property
+    def left(self) -> typing.Optional[builtins.float]: ...
+    @property
+    def right(self) -> typing.Optional[builtins.float]: ...
+    @property
+    def up(self) -> typing.Optional[builtins.float]: ...
+    @property
+    def down(self) -> typing.Optional[builtins.float]: ...
+    @property
+    def date(self) -> typing.Optional[builtins.str]: ...
+    @property
+    def comment(self) -> typing.Optional[builtins.str]: ...
+    def __repr__(self) -> builtins.str: ...
+
+@typing.final
+class ShotTable:
+    r"""
+    Shots of a survey as an Arrow record batch.
+    
+    Implements the Arrow PyCapsule interface, so `pyarrow.record_batch(table)` or
+    `polars.DataFrame(table)` take it without copying. For pandas, use
+    `pyarrow.table(table).to_pandas()`.
+    """
+    @property
+    def num_rows(self) -> builtins.int: ...
+    @property
+    def column_names(self) -> builtins.list[builtins.str]: ...
+    def __len__(self) -> builtins.int: ...
+    def __arrow_c_schema__(self) -> object:
+        r"""
+        Exports the schema as an `arrow_schema` PyCapsule.
+        """
+    def __arrow_c_array__(self, requested_schema: typing.Optional[typing.Any] = None) -> tuple[object, object]:
+        r"""
+        Exports the shots as a struct array, in `arrow_schema` and `arrow_array` PyCapsules.
+        `requested_schema` is ignored.
+        """
+    def __arrow_c_stream__(self, requested_schema: typing.Optional[typing.Any] = None) -> object:
+        r"""
+        Exports the shots as a stream of one record batch, in an `arrow_array_stream`
+        PyCapsule. `requested_schema` is ignored.
+        """
+    def to_parquet(self, path: builtins.str, compression: builtins.str = 'snappy') -> None:
+        r"""
+        Writes the shots to a Parquet file at `path`, `snappy` compressed or `uncompressed`.
+        """
+    def __repr__(self) -> builtins.str: ...
+
+@typing.final
+class Survey:
+    r"""
+    Typed view of a loaded survey.
+    
+    Built from the dict returned by the loaders, which it keeps: `to_dict` gives it back, with
+    the changes made through the typed model (e.g. `convert_units`).
+    """
+    @property
+    def name(self) -> typing.Optional[builtins.str]:
+        r"""
+        Name of the cave, from `caveName`.
+        """
+    @property
+    def unit(self) -> builtins.str:
+        r"""
+        Length unit of the measurements: `m` or `ft`.
+        """
+    @property
+    def magnetic(self) -> builtins.bool:
+        r"""
+        Whether the azimuths are relative to magnetic north, from `useMagneticAzimuth`.
+        """
+    @property
+    def shots(self) -> builtins.list[Shot]:
+        r"""
+        Shots in document order.
+        """
+    def __new__(cls, data: dict, unit: typing.Optional[builtins.str] = None) -> Survey:
+        r"""
+        Reads a loaded survey, converted to `unit` (`m` or `ft`) if given.
+        """
+    def __len__(self) -> builtins.int: ...
+    def convert_units(self, target: builtins.str) -> Survey:
+        r"""
+        Returns a copy of the survey with its lengths and depths in `target` (`m` or `ft`), as
+        `convert_units` does for the dict representation.
+        """
+    def to_dict(self) -> dict:
+        r"""
+        Returns the survey as the dict the loaders return.
+        """
+    def __repr__(self) -> builtins.str: ...
+
+@typing.final
+class SurveyDiff:
+    r"""
+    Domain-level differences between two surveys.
+    
+    Shots are matched by `ID`, layers by `name`. Numbers are compared numerically, so `5` and
+    `5.0` are equal. A `Section` renamed on every shot it contains is reported once as a
+    renamed section instead of a change on each shot.
+    """
+    @property
+    def header(self) -> typing.Any:
+        r"""
+        Changed fields of `CaveFile` outside `Data` and `Layers` (`caveName`, `unit`, ...).
+        """
+    @property
+    def sections_renamed(self) -> typing.Any: ...
+    @property
+    def shots_added(self) -> typing.Any: ...
+    @property
+    def shots_removed(self) -> typing.Any: ...
+    @property
+    def shots_modified(self) -> typing.Any: ...
+    @property
+    def layers_added(self) -> builtins.list[builtins.str]: ...
+    @property
+    def layers_removed(self) -> builtins.list[builtins.str]: ...
+    @property
+    def layers_modified(self) -> typing.Any: ...
+    def to_dict(self) -> typing.Any:
+        r"""
+        The whole diff as a dict.
+        """
+    def report(self) -> builtins.str: ...
+    def __bool__(self) -> builtins.bool: ...
+    def __repr__(self) -> builtins.str: ...
+
+@typing.final
+class SurveyMerge:
+    r"""
+    Result of a three-way merge: the merged survey, the conflicts and the renumbered shots.
+    """
+    @property
+    def merged(self) -> typing.Any:
+        r"""
+        The merged survey, writable with `dict_to_ariane_tml_file`.
+        """
+    @property
+    def conflicts(self) -> typing.Any: ...
+    @property
+    def renumbered(self) -> typing.Any: ...
+    @property
+    def has_conflicts(self) -> builtins.bool: ...
+    def __repr__(self) -> builtins.str: ...
+
+@typing.final
+class SurveyStatistics:
+    r"""
+    Summary statistics of a survey. Lengths and depths are in the survey `unit`.
+    """
+    @property
+    def cave_name(self) -> typing.Optional[builtins.str]: ...
+    @property
+    def unit(self) -> typing.Optional[builtins.str]: ...
+    @property
+    def shot_count(self) -> builtins.int: ...
+    @property
+    def station_count(self) -> builtins.int: ...
+    @property
+    def loop_count(self) -> builtins.int:
+        r"""
+        Independent loops of the survey network, closed by closure shots or shared stations.
+        """
+    @property
+    def total_length(self) -> builtins.float:
+        r"""
+        Length of every shot.
+        """
+    @property
+    def surveyed_length(self) -> builtins.float:
+        r"""
+        Length of the shots which are neither `Excluded` nor of type `CLOSURE`.
+        """
+    @property
+    def horizontal_extent(self) -> builtins.float:
+        r"""
+        Largest horizontal distance between two stations.
+        """
+    @property
+    def vertical_range(self) -> builtins.float:
+        r"""
+        Difference between the deepest and the shallowest station.
+        """
+    @property
+    def max_depth(self) -> builtins.float: ...
+    @property
+    def length_by_section(self) -> typing.Any: ...
+    @property
+    def length_by_explorer(self) -> typing.Any: ...
+    @property
+    def length_by_date(self) -> typing.Any: ...
+    def to_dict(self) -> typing.Any: ...
+    def report(self, format: builtins.str = 'markdown') -> builtins.str:
+        r"""
+        Renders the statistics as a `markdown` document or as `json`.
+        """
+    def __repr__(self) -> builtins.str: ...
+
+@typing.final
+class TmlEntry:
+    r"""
+    An entry of a TML archive.
+    """
+    @property
+    def name(self) -> builtins.str: ...
+    @property
+    def size(self) -> builtins.int:
+        r"""
+        Uncompressed size, in bytes.
+        """
+    @property
+    def compressed_size(self) -> builtins.int:
+        r"""
+        Size in the archive, in bytes.
+        """
+    @property
+    def compression(self) -> builtins.str:
+        r"""
+        Compression method: `stored`, `deflated`, ...
+        """
+    def __repr__(self) -> builtins.str: ...
+
+@typing.final
+class UtmCoordinate:
+    r"""
+    Position projected on a UTM zone.
+    """
+    @property
+    def zone(self) -> builtins.int:
+        r"""
+        Zone number, from 1 to 60.
+        """
+    @property
+    def hemisphere(self) -> builtins.str:
+        r"""
+        `N` or `S`.
+        """
+    @property
+    def easting(self) -> builtins.float: ...
+    @property
+    def northing(self) -> builtins.float: ...
+    @property
+    def convergence(self) -> builtins.float:
+        r"""
+        Angle from true north to grid north, clockwise in degrees. A true azimuth minus the
+        convergence gives the grid azimuth.
+        """
+    @property
+    def scale_factor(self) -> builtins.float:
+        r"""
+        Ratio of a grid distance to the distance on the ellipsoid.
+        """
+    def to_wgs84(self) -> tuple[builtins.float, builtins.float]:
+        r"""
+        Latitude and longitude of the position.
+        """
+    def to_dict(self) -> typing.Any: ...
+    def __repr__(self) -> builtins.str: ...
+
+@typing.final
+class XmlWriterOptions:
+    r"""
+    Formatting options used when writing a dict back to XML.
+    
+    The default options produce a compact single-line document, while
+    `XmlWriterOptions.ariane()` reproduces the layout of files saved by Ariane.
+    """
+    @property
+    def indent_char(self) -> builtins.str:
+        r"""
+        Character used to indent nested elements (a space or a tab).
+        """
+    @property
+    def indent_size(self) -> builtins.int:
+        r"""
+        Number of `indent_char` per nesting level. `0` disables pretty-printing.
+        """
+    @property
+    def version(self) -> builtins.str:
+        r"""
+        Value of the `version` field of the XML declaration.
+        """
+    @property
+    def encoding(self) -> typing.Optional[builtins.str]:
+        r"""
+        Value of the `encoding` field of the XML declaration, omitted if `None`.
+        """
+    @property
+    def standalone(self) -> typing.Optional[builtins.str]:
+        r"""
+        Value of the `standalone` field of the XML declaration, omitted if `None`.
+        """
+    @property
+    def trailing_newline(self) -> builtins.bool:
+        r"""
+        Terminate the document with a line ending.
+        """
+    @property
+    def number_format(self) -> NumberFormat:
+        r"""
+        Formatting policy applied to numeric values.
+        """
+    @property
+    def line_ending(self) -> builtins.str:
+        r"""
+        Line ending used in the document: `"lf"` or `"crlf"`.
+        """
+    def __new__(cls, indent_char: builtins.str = ' ', indent_size: builtins.int = 0, version: builtins.str = '1.0', encoding: typing.Optional[builtins.str] = 'utf-8', standalone: typing.Optional[builtins.str] = None, trailing_newline: builtins.bool = False, line_ending: builtins.str = 'lf', number_format: typing.Optional[NumberFormat] = None) -> XmlWriterOptions: ...
+    @staticmethod
+    def ariane() -> XmlWriterOptions:
+        r"""
+        Options reproducing the files written by Ariane.
+        """
+    def __repr__(self) -> builtins.str: ...
+
+def convert_units(data: dict, target: builtins.str) -> typing.Any:
+    r"""
+    Returns a copy of a loaded survey with its lengths and depths converted to `target`.
+    
+    The `unit` element is updated along with `Length`, `Depth`, `DepthIn`, the LRUD, the radius
+    vectors of the shot shapes and `firstStartAbsoluteElevation`. Values loaded as strings stay
+    strings. `target` is `m` (meters) or `ft` (feet). See `Survey.convert_units` for the typed
+    model.
+    """
+
+def correct_declination(data: dict, north: builtins.str, latitude: typing.Optional[builtins.float] = None, longitude: typing.Optional[builtins.float] = None, models: typing.Optional[typing.Sequence[GeomagneticModel]] = None) -> typing.Any:
+    r"""
+    Returns a copy of a loaded survey with its azimuths relative to `north` (`true` or
+    `magnetic`), correcting each shot with the declination at its date and position.
+    
+    Shots without coordinates are placed at `latitude`/`longitude`, or at the first located
+    shot. The `useMagneticAzimuth` flag is updated; a survey already relative to `north` is
+    returned unchanged. Each shot uses the model in effect at its date, from `models` or the
+    embedded releases; a `ValueError` is raised for a shot dated outside all of them.
+    """
+
+def dict_to_ariane_tml_file(data: dict, path: builtins.str, options: typing.Optional[XmlWriterOptions] = None, mapping: typing.Optional[mapping.KeyMapping] = None, north: typing.Optional[builtins.str] = None, source: typing.Optional[builtins.str] = None) -> None:
+    r"""
+    Writes a dict to an Ariane TML file (a zip archive holding a "Data.xml" entry).
+    
+    The XML is streamed straight into the compressed zip entry.
+    
+    # Arguments
+    
+    * `data`: The dict to save, with a single root key (as returned by the loader). Comments and
+      processing instructions kept by the loader are written back around the root element.
+    * `path`: The path of the TML file to create.
+    * `options`: The XML formatting options, defaults to the Ariane layout.
+    * `mapping`: `KeyMapping` whose inverse renames the keys back to Ariane names.
+    * `north`: Write the azimuths relative to this north (`true` or `magnetic`), see
+      `correct_declination`.
+    * `source`: TML file whose other entries (images, backgrounds, ...) are copied unchanged
+      after "Data.xml", usually the file the survey was loaded from. It may be `path` itself.
+      Without it, only "Data.xml" is written.
+    """
+
+def dict_to_ariane_tmlu_file(data: dict, path: builtins.str, options: typing.Optional[XmlWriterOptions] = None, mapping: typing.Optional[mapping.KeyMapping] = None, north: typing.Optional[builtins.str] = None) -> None:
+    r"""
+    Writes a dict to an Ariane TMLU file, the uncompressed XML of a TML file.
+    
+    Arguments are as in `dict_to_ariane_tml_file`.
+    """
+
+def dict_to_gpx_file(data: dict, path: builtins.str, latitude: typing.Optional[builtins.float] = None, longitude: typing.Optional[builtins.float] = None) -> None:
+    r"""
+    Writes a loaded survey to a GPX 1.1 file at `path`. See `dict_to_gpx_str`.
+    """
+
+def dict_to_gpx_str(data: dict, latitude: typing.Optional[builtins.float] = None, longitude: typing.Optional[builtins.float] = None) -> builtins.str:
+    r"""
+    Converts a loaded survey to a GPX 1.1 document.
+    
+    Shots with non-zero `Latitude`/`Longitude` (entrances and surface fixes) become waypoints
+    and each `Section` a track, with elevations from `Depth`. Surveys without a located shot
+    are placed at `latitude`/`longitude`. Magnetic azimuths are corrected to true north, or used
+    as is with a `UserWarning` when no shot is dated.
+    """
+
+def dict_to_xml_file(data: dict, path: builtins.str, root_name: builtins.str, options: typing.Optional[XmlWriterOptions] = None, mapping: typing.Optional[mapping.KeyMapping] = None) -> None:
+    r"""
+    Writes the XML document directly to the file at `path`, without building it in memory.
+    """
+
+def dict_to_xml_str(data: dict, root_name: builtins.str, options: typing.Optional[XmlWriterOptions] = None, mapping: typing.Optional[mapping.KeyMapping] = None) -> builtins.str: ...
+
+def dict_to_xml_stream(data: dict, fileobj: typing.Any, root_name: builtins.str, options: typing.Optional[XmlWriterOptions] = None, mapping: typing.Optional[mapping.KeyMapping] = None) -> None:
+    r"""
+    Writes the XML document to a binary file-like object (anything with a `write(bytes)` method).
+    """
+
+def diff_surveys(old: dict, new: dict) -> SurveyDiff:
+    r"""
+    Compares two loaded surveys at the domain level.
+    """
+
+def export_shots_csv(data: dict, path: builtins.str, columns: typing.Optional[typing.Sequence[builtins.str]] = None, delimiter: builtins.str = ',') -> None:
+    r"""
+    Writes the shots of a loaded survey to a CSV file at `path`, one row per shot.
+    
+    `columns` are shot fields (`ID`, `Length`, `Comment`, ...) or the computed `x`, `y`, `z`
+    (station position from the survey start, in the survey unit) and `cumulative_length`.
+    Measurements are written as numbers and fields holding the delimiter, quotes or line
+    breaks are quoted.
+    """
 
-def load_ariane_tml_file_to_dict(path: builtins.str) -> typing.Any:
+def list_ariane_tml_entries(path: builtins.str) -> builtins.list[TmlEntry]:
+    r"""
+    Lists the entries of a TML archive: "Data.xml" and the attachments (images, backgrounds, ...)
+    stored next to it, in archive order.
+    
+    # Arguments
+    
+    * `path`: The path of the TML file.
+    """
+
+def load_ariane_file_to_dict(path: builtins.str, keep_comments: builtins.bool = False, keep_processing_instructions: builtins.bool = False, lossy: builtins.bool = False, mapping: typing.Optional[mapping.KeyMapping] = None, unit: typing.Optional[builtins.str] = None, north: typing.Optional[builtins.str] = None) -> typing.Any:
+    r"""
+    Loads an Ariane file, either a TML file (a zip archive holding "Data.xml") or a TMLU file
+    (the uncompressed XML). The format is told apart by the first bytes of the file, not by its
+    extension.
+    
+    Arguments are as in `load_ariane_tml_file_to_dict`.
+    """
+
+def load_ariane_tml_file_to_arrays(path: builtins.str, lossy: builtins.bool = False, unit: typing.Optional[builtins.str] = None, north: typing.Optional[builtins.str] = None) -> dict:
+    r"""
+    Reads the shots of a TML file straight into NumPy arrays, without building the dict.
+    
+    See `shot_arrays`; `lossy`, `unit` and `north` are as in `load_ariane_tml_file_to_dict`.
+    """
+
+def load_ariane_tml_file_to_arrow(path: builtins.str, columns: typing.Optional[typing.Sequence[builtins.str]] = None, lossy: builtins.bool = False, unit: typing.Optional[builtins.str] = None, north: typing.Optional[builtins.str] = None) -> ShotTable:
+    r"""
+    Reads the shots of a TML file straight into a `ShotTable`, without building the dict.
+    
+    `columns` are as in `export_shots_csv`; `lossy`, `unit` and `north` as in
+    `load_ariane_tml_file_to_dict`.
+    """
+
+def load_ariane_tml_file_to_dict(path: builtins.str, keep_comments: builtins.bool = False, keep_processing_instructions: builtins.bool = False, lossy: builtins.bool = False, mapping: typing.Optional[mapping.KeyMapping] = None, unit: typing.Optional[builtins.str] = None, north: typing.Optional[builtins.str] = None) -> typing.Any:
     r"""
     Reads the contents of the "Data.xml" file from a zip archive.
     
+    Use `load_ariane_file_to_dict` to read TMLU files (uncompressed XML) as well.
+    
     # Arguments
     
     * `path`: The path to the zip archive.
+    * `keep_comments`: Keep XML comments under the reserved `#comment` key, with their position.
+    * `keep_processing_instructions`: Keep processing instructions under the reserved `#pi` key.
+    * `lossy`: Replace undecodable bytes instead of failing, with a `UnicodeWarning` listing them.
+    * `mapping`: `KeyMapping` applied to element and attribute names while loading.
+    * `unit`: Convert lengths and depths to this unit (`m` or `ft`), see `convert_units`.
+    * `north`: Make the azimuths relative to this north (`true` or `magnetic`), see
+      `correct_declination`.
     
     # Returns
     
     The contents of the "Data.xml" file as a string.
     """
 
-def xml_str_to_dict(xml_str: builtins.str, keep_null: builtins.bool) -> typing.Any: ...
+def load_pockettopo_txt_file_to_dict(path: builtins.str, lossy: builtins.bool = False, unit: typing.Optional[builtins.str] = None, north: typing.Optional[builtins.str] = None) -> typing.Any:
+    r"""
+    Loads a PocketTopo text export (`.txt`) as an Ariane survey.
+    
+    Legs become `SurveyData` shots named after their station, splays give the LRUD and the
+    `RadiusVector`s of their station, and each `TRIP` its `Date`. Azimuths are corrected by the
+    trip `DECLINATION` when one is set, otherwise they stay magnetic; a `UserWarning` is emitted
+    when only some trips set one. Files which are not valid
+    UTF-8 are read as Windows-1252. `lossy`, `unit` and `north` are as in
+    `load_ariane_tml_file_to_dict`.
+    """
+
+def load_therion_th_file_to_dict(path: builtins.str, lossy: builtins.bool = False, unit: typing.Optional[builtins.str] = None, north: typing.Optional[builtins.str] = None) -> typing.Any:
+    r"""
+    Loads a Therion centerline file (`.th`, as exported by TopoDroid) as an Ariane survey.
+    
+    Each `centerline` is a trip giving its `date`, `team` and `declination` to its shots and
+    each `survey` a `Section`. `normal` and `diving` data are supported; `equate`s are not
+    followed, so equated surveys get their own start. The file is decoded as its `encoding`
+    command says. See `load_pockettopo_txt_file_to_dict` for the layout of the shots.
+    """
+
+def load_topodroid_csv_file_to_dict(path: builtins.str, lossy: builtins.bool = False, unit: typing.Optional[builtins.str] = None, north: typing.Optional[builtins.str] = None) -> typing.Any:
+    r"""
+    Loads a TopoDroid CSV export as an Ariane survey.
+    
+    The `#` header gives the survey name, `Date`, `Explorer` team, declination and units. See
+    `load_pockettopo_txt_file_to_dict` for the layout of the shots.
+    """
+
+def local_to_wgs84(east: builtins.float, north: builtins.float, up: builtins.float, origin_latitude: builtins.float, origin_longitude: builtins.float, origin_altitude: builtins.float = 0.0) -> tuple[builtins.float, builtins.float, builtins.float]:
+    r"""
+    Inverse of `wgs84_to_local`: the latitude, longitude and altitude of east, north and up
+    offsets from an origin.
+    """
+
+def magnetic_declination(latitude: builtins.float, longitude: builtins.float, date: typing.Any, altitude: builtins.float = 0.0, models: typing.Optional[typing.Sequence[GeomagneticModel]] = None) -> builtins.float:
+    r"""
+    Magnetic declination in degrees (east positive) from the embedded World Magnetic Model.
+    
+    `date` is a `datetime.date`, an ISO 8601 string or a decimal year and `altitude` is in meters
+    above the WGS84 ellipsoid. The release in effect at `date` is used, taken from `models`
+    instead of the embedded ones if given. A `ValueError` is raised when no model is valid at
+    `date`, e.g. before the oldest or after the newest embedded release.
+    """
+
+def merge_surveys(base: dict, ours: dict, theirs: dict) -> SurveyMerge:
+    r"""
+    Three-way merge of two surveys edited from a common `base`.
+    
+    Shots are matched by `ID` and layers by `name`; each field changed on a single side is
+    taken from that side. Shots added on both sides with the same `ID` are kept, their shots
+    being renumbered after the highest station along with the `FromID`/`ClosureToID`
+    references to them. Changes which cannot be merged are reported as conflicts and resolved
+    in favour of `ours`.
+    """
+
+def read_ariane_tml_entry(path: builtins.str, name: builtins.str) -> bytes:
+    r"""
+    Reads the uncompressed bytes of an entry of a TML archive.
+    
+    # Arguments
+    
+    * `path`: The path of the TML file.
+    * `name`: The name of the entry, as listed by `list_ariane_tml_entries`.
+    """
+
+def shot_arrays(data: dict) -> dict:
+    r"""
+    Returns the measurements and station positions of the shots of a loaded survey as NumPy
+    arrays, one row per shot in document order.
+    
+    The dict holds a `float64` array for `Length`, `Azimut`, `Inclination`, `Depth`, `Left`,
+    `Right`, `Up` and `Down`, missing or invalid values being NaN, and for the `x`, `y` and `z`
+    position of the shot station (see `export_shots_csv`). `ID` is an object array mapping
+    each row back to its shot.
+    """
+
+def shots_to_arrow(data: dict, columns: typing.Optional[typing.Sequence[builtins.str]] = None) -> ShotTable:
+    r"""
+    Builds a `ShotTable` from a loaded survey, with `columns` as in `export_shots_csv`.
+    """
+
+def station_coordinates(data: dict, crs: builtins.str = 'wgs84', zone: typing.Optional[builtins.int] = None, latitude: typing.Optional[builtins.float] = None, longitude: typing.Optional[builtins.float] = None) -> typing.Any:
+    r"""
+    Returns the position of every station of a loaded survey, keyed by station `ID`.
+    
+    `crs` is `wgs84` (`latitude`, `longitude`, `altitude`), `utm` (`zone`, `hemisphere`,
+    `easting`, `northing`, `altitude`, `convergence`) or `local` (`east`, `north`, `up` in meters
+    from the located station). UTM positions share the zone of the located station unless
+    `zone` is given. Stations are placed from the first located shot, or from the survey start
+    at `latitude`/`longitude`. Magnetic azimuths are corrected to true north first, or used as is
+    with a `UserWarning` when no shot is dated.
+    """
+
+def survey_statistics(data: dict, unit: typing.Optional[builtins.str] = None) -> SurveyStatistics:
+    r"""
+    Computes the length, extent, depth and network statistics of a loaded survey, in its own
+    unit or converted to `unit` (`m` or `ft`).
+    """
+
+def utm_to_wgs84(easting: builtins.float, northing: builtins.float, zone: builtins.int, hemisphere: builtins.str) -> tuple[builtins.float, builtins.float]:
+    r"""
+    Returns the WGS84 latitude and longitude of a UTM position. `hemisphere` is `N` or `S`.
+    """
+
+def validate(data: dict) -> builtins.list[Diagnostic]:
+    r"""
+    Checks a loaded survey and returns the problems found, in document order.
+    
+    Errors flag inconsistent data: duplicate or missing `ID`s, `FromID`/`ClosureToID`
+    referencing a missing station, non-numeric values, azimuths outside 0-360, inclinations
+    outside ±90 and negative lengths or LRUD. Warnings flag likely mistakes: depth changes
+    larger than the shot length, future or malformed dates and groups of shots not connected
+    to the survey start.
+    """
+
+def wgs84_to_local(latitude: builtins.float, longitude: builtins.float, origin_latitude: builtins.float, origin_longitude: builtins.float, altitude: builtins.float = 0.0, origin_altitude: builtins.float = 0.0) -> tuple[builtins.float, builtins.float, builtins.float]:
+    r"""
+    Returns the east, north and up offsets in meters of a WGS84 position from an origin, in the
+    plane tangent to the ellipsoid at the origin. Altitudes are above the ellipsoid.
+    """
+
+def wgs84_to_utm(latitude: builtins.float, longitude: builtins.float, zone: typing.Optional[builtins.int] = None) -> UtmCoordinate:
+    r"""
+    Projects a WGS84 position on UTM, in its own zone or in `zone` (1 to 60).
+    """
+
+def xml_bytes_to_dict(xml_bytes: bytes, keep_null: builtins.bool, keep_comments: builtins.bool = False, keep_processing_instructions: builtins.bool = False, lossy: builtins.bool = False, mapping: typing.Optional[mapping.KeyMapping] = None) -> typing.Any:
+    r"""
+    Parses the raw bytes of an XML document, detecting its encoding from the BOM or the XML
+    declaration (UTF-8, UTF-16, ISO-8859-1/Windows-1252 and ISO-8859-15).
+    
+    With `lossy`, undecodable bytes are replaced by U+FFFD and a `UnicodeWarning` lists them.
+    `mapping` renames element and attribute names before the result is converted to Python.
+    """
+
+def xml_str_to_dict(xml_str: builtins.str, keep_null: builtins.bool, keep_comments: builtins.bool = False, keep_processing_instructions: builtins.bool = False, mapping: typing.Optional[mapping.KeyMapping] = None) -> typing.Any: ...
 
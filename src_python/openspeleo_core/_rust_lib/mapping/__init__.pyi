@@ -1,10 +1,84 @@
 # This file is automatically generated by pyo3_stub_gen
 # ruff: noqa: E501, F401, F403, F405
 
+import builtins
 import typing
 __all__ = [
+    "KeyMapping",
+    "Transform",
     "apply_key_mapping",
+    "transform",
 ]
 
-def apply_key_mapping(data: typing.Any, mapping: dict) -> typing.Any: ...
+@typing.final
+class KeyMapping:
+    r"""
+    Compiled, invertible key mapping.
+    
+    Keys are either plain names, renamed wherever they appear, or path patterns restricting the
+    rule to a context: `Data.SurveyData.*.Length` or `**.RadiusVector.length`. `*` matches one
+    key, `**` any number of keys, and list indices may be omitted. Path patterns are written
+    with source names and take precedence over plain names.
+    
+    The mapping is checked at construction: two keys mapping to the same name would make the
+    inverse ambiguous and are rejected. Applying the mapping raises a `ValueError` instead of
+    silently overwriting a value when two keys of the same dict collide after mapping.
+    """
+    def __new__(cls, mapping: dict) -> KeyMapping: ...
+    def forward(self, data: typing.Any, generic_containers: builtins.bool = False) -> typing.Any:
+        r"""
+        Renames the keys of `data` from source to target names, recursing into dicts and lists.
+        
+        `generic_containers` also walks subclasses, tuples and abstract containers, as
+        `apply_key_mapping` does.
+        """
+    def inverse(self, data: typing.Any, generic_containers: builtins.bool = False) -> typing.Any:
+        r"""
+        Renames the keys of `data` from target back to source names.
+        """
+    def inverted(self) -> KeyMapping:
+        r"""
+        Returns the mapping going the other way.
+        
+        Path patterns are rewritten with the target names of the keys they go through.
+        """
+    def to_dict(self) -> dict:
+        r"""
+        Returns the mapping as a `{pattern: target}` dict.
+        """
+    def __len__(self) -> builtins.int: ...
+    def __repr__(self) -> builtins.str: ...
+
+@typing.final
+class Transform:
+    r"""
+    Compiled transform spec: per key or path pattern, rename, cast, scale, drop or set a default.
+    
+    Each spec value is either a new name or a dict with the options `rename`, `cast` (`int`,
+    `float`, `bool`, `str` or `date`), `factor`, `drop` and `default`. Keys accept the path
+    patterns of `KeyMapping`; `default` requires one, since a copy of its value is added to
+    every dict of the scope missing the key. Casts apply to scalar values and to the items of a
+    list value, `None` is left untouched; `nan` and infinite numbers are rejected.
+    """
+    def __new__(cls, spec: dict) -> Transform: ...
+    def apply(self, data: typing.Any) -> typing.Any:
+        r"""
+        Applies the transform to `data` in a single pass over the dict and list tree.
+        """
+    def __len__(self) -> builtins.int: ...
+    def __repr__(self) -> builtins.str: ...
+
+def apply_key_mapping(data: typing.Any, mapping: dict, generic_containers: builtins.bool = False) -> typing.Any:
+    r"""
+    Renames the keys of `data` found in `mapping`, recursing into dicts and lists.
+    
+    Only exact `dict` and `list` objects are walked by default. With `generic_containers`,
+    dict and list subclasses, tuples and `collections.abc.Mapping`/`Sequence` objects are
+    walked too: mappings and sequences come back as `dict` and `list`, tuples as `tuple`.
+    """
+
+def transform(data: typing.Any, spec: dict) -> typing.Any:
+    r"""
+    Applies a transform spec to a dict or list tree.
+    """
 
@@ -1,23 +1,391 @@
 from __future__ import annotations
 
 from pathlib import Path
+from typing import TYPE_CHECKING
 
 from openspeleo_core._rust_lib import ariane as _ariane  # type: ignore  # noqa: PGH003
+from openspeleo_core.mapping import KeyMapping
 
+if TYPE_CHECKING:
+    import datetime
+    from collections.abc import Sequence
+    from typing import BinaryIO
 
-def load_ariane_tml_file_to_dict(path: str | Path) -> dict:
+NumberFormat = _ariane.NumberFormat
+XmlWriterOptions = _ariane.XmlWriterOptions
+SurveyDiff = _ariane.SurveyDiff
+SurveyMerge = _ariane.SurveyMerge
+Diagnostic = _ariane.Diagnostic
+SurveyStatistics = _ariane.SurveyStatistics
+GeomagneticModel = _ariane.GeomagneticModel
+UtmCoordinate = _ariane.UtmCoordinate
+ShotTable = _ariane.ShotTable
+TmlEntry = _ariane.TmlEntry
+Survey = _ariane.Survey
+Shot = _ariane.Shot
+
+
+def _as_key_mapping(mapping: dict[str, str] | KeyMapping | None) -> KeyMapping | None:
+    if mapping is None or isinstance(mapping, KeyMapping):
+        return mapping
+
+    if not isinstance(mapping, dict):
+        raise TypeError(f"Unexpected type received for `mapping`: {type(mapping)}")
+
+    return KeyMapping(mapping)
+
+
+def load_ariane_tml_file_to_dict(
+    path: str | Path,
+    keep_comments: bool = False,
+    keep_processing_instructions: bool = False,
+    lossy: bool = False,
+    mapping: dict[str, str] | KeyMapping | None = None,
+    unit: str | None = None,
+    north: str | None = None,
+) -> dict:
+    if not Path(path).exists():
+        raise FileNotFoundError(f"Impossible to find {path} ...")
+
+    return _ariane.load_ariane_tml_file_to_dict(
+        str(path),
+        keep_comments,
+        keep_processing_instructions,
+        lossy,
+        _as_key_mapping(mapping),
+        unit,
+        north,
+    )
+
+
+def load_ariane_file_to_dict(
+    path: str | Path,
+    keep_comments: bool = False,
+    keep_processing_instructions: bool = False,
+    lossy: bool = False,
+    mapping: dict[str, str] | KeyMapping | None = None,
+    unit: str | None = None,
+    north: str | None = None,
+) -> dict:
+    if not Path(path).exists():
+        raise FileNotFoundError(f"Impossible to find {path} ...")
+
+    return _ariane.load_ariane_file_to_dict(
+        str(path),
+        keep_comments,
+        keep_processing_instructions,
+        lossy,
+        _as_key_mapping(mapping),
+        unit,
+        north,
+    )
+
+
+def list_ariane_tml_entries(path: str | Path) -> list[TmlEntry]:
+    if not Path(path).exists():
+        raise FileNotFoundError(f"Impossible to find {path} ...")
+
+    return _ariane.list_ariane_tml_entries(str(path))
+
+
+def read_ariane_tml_entry(path: str | Path, name: str) -> bytes:
+    if not Path(path).exists():
+        raise FileNotFoundError(f"Impossible to find {path} ...")
+
+    return _ariane.read_ariane_tml_entry(str(path), name)
+
+
+def xml_str_to_dict(
+    xml_str: str,
+    keep_null: bool = True,
+    keep_comments: bool = False,
+    keep_processing_instructions: bool = False,
+    mapping: dict[str, str] | KeyMapping | None = None,
+) -> dict:
+    return _ariane.xml_str_to_dict(
+        xml_str,
+        keep_null,
+        keep_comments,
+        keep_processing_instructions,
+        _as_key_mapping(mapping),
+    )
+
+
+def xml_bytes_to_dict(
+    xml_bytes: bytes,
+    keep_null: bool = True,
+    keep_comments: bool = False,
+    keep_processing_instructions: bool = False,
+    lossy: bool = False,
+    mapping: dict[str, str] | KeyMapping | None = None,
+) -> dict:
+    return _ariane.xml_bytes_to_dict(
+        xml_bytes,
+        keep_null,
+        keep_comments,
+        keep_processing_instructions,
+        lossy,
+        _as_key_mapping(mapping),
+    )
+
+
+def dict_to_xml_str(
+    data: dict,
+    root_name: str,
+    options: XmlWriterOptions | None = None,
+    mapping: dict[str, str] | KeyMapping | None = None,
+) -> str:
+    return _ariane.dict_to_xml_str(data, root_name, options, _as_key_mapping(mapping))
+
+
+def dict_to_xml_file(
+    data: dict,
+    path: str | Path,
+    root_name: str,
+    options: XmlWriterOptions | None = None,
+    mapping: dict[str, str] | KeyMapping | None = None,
+) -> None:
+    _ariane.dict_to_xml_file(
+        data, str(path), root_name, options, _as_key_mapping(mapping)
+    )
+
+
+def dict_to_xml_stream(
+    data: dict,
+    fileobj: BinaryIO,
+    root_name: str,
+    options: XmlWriterOptions | None = None,
+    mapping: dict[str, str] | KeyMapping | None = None,
+) -> None:
+    _ariane.dict_to_xml_stream(
+        data, fileobj, root_name, options, _as_key_mapping(mapping)
+    )
+
+
+def dict_to_ariane_tml_file(
+    data: dict,
+    path: str | Path,
+    options: XmlWriterOptions | None = None,
+    mapping: dict[str, str] | KeyMapping | None = None,
+    north: str | None = None,
+    source: str | Path | None = None,
+) -> None:
+    if source is not None and not Path(source).exists():
+        raise FileNotFoundError(f"Impossible to find {source} ...")
+
+    _ariane.dict_to_ariane_tml_file(
+        data,
+        str(path),
+        options,
+        _as_key_mapping(mapping),
+        north,
+        None if source is None else str(source),
+    )
+
+
+def dict_to_ariane_tmlu_file(
+    data: dict,
+    path: str | Path,
+    options: XmlWriterOptions | None = None,
+    mapping: dict[str, str] | KeyMapping | None = None,
+    north: str | None = None,
+) -> None:
+    _ariane.dict_to_ariane_tmlu_file(
+        data, str(path), options, _as_key_mapping(mapping), north
+    )
+
+
+def diff_surveys(old: dict, new: dict) -> SurveyDiff:
+    return _ariane.diff_surveys(old, new)
+
+
+def merge_surveys(base: dict, ours: dict, theirs: dict) -> SurveyMerge:
+    return _ariane.merge_surveys(base, ours, theirs)
+
+
+def validate(data: dict) -> list[Diagnostic]:
+    return _ariane.validate(data)
+
+
+def survey_statistics(data: dict, unit: str | None = None) -> SurveyStatistics:
+    return _ariane.survey_statistics(data, unit)
+
+
+def convert_units(data: dict | Survey, target: str) -> dict | Survey:
+    if isinstance(data, Survey):
+        return data.convert_units(target)
+
+    return _ariane.convert_units(data, target)
+
+
+def magnetic_declination(
+    latitude: float,
+    longitude: float,
+    date: datetime.date | str | float,
+    altitude: float = 0.0,
+    models: Sequence[GeomagneticModel] | None = None,
+) -> float:
+    return _ariane.magnetic_declination(
+        latitude, longitude, date, altitude, None if models is None else list(models)
+    )
+
+
+def correct_declination(
+    data: dict,
+    north: str,
+    latitude: float | None = None,
+    longitude: float | None = None,
+    models: Sequence[GeomagneticModel] | None = None,
+) -> dict:
+    return _ariane.correct_declination(
+        data, north, latitude, longitude, None if models is None else list(models)
+    )
+
+
+def wgs84_to_utm(
+    latitude: float, longitude: float, zone: int | None = None
+) -> UtmCoordinate:
+    return _ariane.wgs84_to_utm(latitude, longitude, zone)
+
+
+def utm_to_wgs84(
+    easting: float, northing: float, zone: int, hemisphere: str
+) -> tuple[float, float]:
+    return _ariane.utm_to_wgs84(easting, northing, zone, hemisphere)
+
+
+def wgs84_to_local(
+    latitude: float,
+    longitude: float,
+    origin_latitude: float,
+    origin_longitude: float,
+    altitude: float = 0.0,
+    origin_altitude: float = 0.0,
+) -> tuple[float, float, float]:
+    return _ariane.wgs84_to_local(
+        latitude,
+        longitude,
+        origin_latitude,
+        origin_longitude,
+        altitude,
+        origin_altitude,
+    )
+
+
+def local_to_wgs84(
+    east: float,
+    north: float,
+    up: float,
+    origin_latitude: float,
+    origin_longitude: float,
+    origin_altitude: float = 0.0,
+) -> tuple[float, float, float]:
+    return _ariane.local_to_wgs84(
+        east, north, up, origin_latitude, origin_longitude, origin_altitude
+    )
+
+
+def station_coordinates(
+    data: dict,
+    crs: str = "wgs84",
+    zone: int | None = None,
+    latitude: float | None = None,
+    longitude: float | None = None,
+) -> dict[str, dict]:
+    return _ariane.station_coordinates(data, crs, zone, latitude, longitude)
+
+
+def dict_to_gpx_str(
+    data: dict, latitude: float | None = None, longitude: float | None = None
+) -> str:
+    return _ariane.dict_to_gpx_str(data, latitude, longitude)
+
+
+def dict_to_gpx_file(
+    data: dict,
+    path: str | Path,
+    latitude: float | None = None,
+    longitude: float | None = None,
+) -> None:
+    _ariane.dict_to_gpx_file(data, str(path), latitude, longitude)
+
+
+def export_shots_csv(
+    data: dict,
+    path: str | Path,
+    columns: list[str] | None = None,
+    delimiter: str = ",",
+) -> None:
+    _ariane.export_shots_csv(data, str(path), columns, delimiter)
+
+
+def shots_to_arrow(data: dict, columns: list[str] | None = None) -> ShotTable:
+    return _ariane.shots_to_arrow(data, columns)
+
+
+def load_ariane_tml_file_to_arrow(
+    path: str | Path,
+    columns: list[str] | None = None,
+    lossy: bool = False,
+    unit: str | None = None,
+    north: str | None = None,
+) -> ShotTable:
     if not Path(path).exists():
         raise FileNotFoundError(f"Impossible to find {path} ...")
 
-    return _ariane.load_ariane_tml_file_to_dict(str(path))
+    return _ariane.load_ariane_tml_file_to_arrow(str(path), columns, lossy, unit, north)
+
 
+def shot_arrays(data: dict) -> dict:
+    return _ariane.shot_arrays(data)
 
-def xml_str_to_dict(xml_str: str, keep_null: bool = True) -> dict:
-    return _ariane.xml_str_to_dict(xml_str, keep_null)
 
+def load_ariane_tml_file_to_arrays(
+    path: str | Path,
+    lossy: bool = False,
+    unit: str | None = None,
+    north: str | None = None,
+) -> dict:
+    if not Path(path).exists():
+        raise FileNotFoundError(f"Impossible to find {path} ...")
+
+    return _ariane.load_ariane_tml_file_to_arrays(str(path), lossy, unit, north)
+
+
+def load_pockettopo_txt_file_to_dict(
+    path: str | Path,
+    lossy: bool = False,
+    unit: str | None = None,
+    north: str | None = None,
+) -> dict:
+    if not Path(path).exists():
+        raise FileNotFoundError(f"Impossible to find {path} ...")
+
+    return _ariane.load_pockettopo_txt_file_to_dict(str(path), lossy, unit, north)
+
+
+def load_topodroid_csv_file_to_dict(
+    path: str | Path,
+    lossy: bool = False,
+    unit: str | None = None,
+    north: str | None = None,
+) -> dict:
+    if not Path(path).exists():
+        raise FileNotFoundError(f"Impossible to find {path} ...")
+
+    return _ariane.load_topodroid_csv_file_to_dict(str(path), lossy, unit, north)
+
+
+def load_therion_th_file_to_dict(
+    path: str | Path,
+    lossy: bool = False,
+    unit: str | None = None,
+    north: str | None = None,
+) -> dict:
+    if not Path(path).exists():
+        raise FileNotFoundError(f"Impossible to find {path} ...")
 
-def dict_to_xml_str(data: dict, root_name: str) -> str:
-    return _ariane.dict_to_xml_str(data, root_name)
+    return _ariane.load_therion_th_file_to_dict(str(path), lossy, unit, north)
 
 
 def load_ariane_tml_file_to_json(path: str | Path) -> str:
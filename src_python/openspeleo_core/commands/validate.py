@@ -0,0 +1,61 @@
+from __future__ import annotations
+
+import argparse
+import json
+import pathlib
+import sys
+
+from openspeleo_core import ariane_core
+
+
+def validate(args):
+    parser = argparse.ArgumentParser(
+        prog="validate", description="Validate a Survey File"
+    )
+    parser.add_argument(
+        "-i",
+        "--input_file",
+        type=pathlib.Path,
+        required=True,
+        help="Path to the TML or TMLU file to be validated",
+    )
+
+    parser.add_argument(
+        "-f",
+        "--format",
+        type=str,
+        choices=["text", "json"],
+        default="text",
+        help="Output format of the diagnostics.",
+    )
+
+    parser.add_argument(
+        "-s",
+        "--strict",
+        action="store_true",
+        help="Also fail when only warnings are found.",
+        default=False,
+    )
+
+    parsed_args = parser.parse_args(args)
+
+    input_file: pathlib.Path = parsed_args.input_file
+
+    if not input_file.exists():
+        raise FileNotFoundError(f"File not found: `{input_file}`")
+
+    # The TML or TMLU format is read from the content, whatever the extension
+    data: dict = ariane_core.load_ariane_file_to_dict(input_file)
+
+    diagnostics = ariane_core.validate(data)
+
+    match parsed_args.format:
+        case "json":
+            json.dump([d.to_dict() for d in diagnostics], sys.stdout, indent=2)
+            sys.stdout.write("\n")
+        case _:
+            for diagnostic in diagnostics:
+                sys.stdout.write(f"{diagnostic}\n")
+
+    failed = any(d.is_error or parsed_args.strict for d in diagnostics)
+    return 1 if failed else 0
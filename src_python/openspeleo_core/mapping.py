@@ -1,13 +1,42 @@
 from __future__ import annotations
 
+from collections.abc import Mapping
+from collections.abc import Sequence
+
 from openspeleo_core._rust_lib import mapping as _lib  # type: ignore  # noqa: PGH003
 
+KeyMapping = _lib.KeyMapping
+Transform = _lib.Transform
 
-def apply_key_mapping(data: dict | list, mapping: dict[str, str]) -> dict:
-    if not isinstance(data, (dict, list)):
+
+def apply_key_mapping(
+    data: dict | list,
+    mapping: dict[str, str] | KeyMapping,
+    generic_containers: bool = False,
+) -> dict:
+    container_types = (Mapping, Sequence) if generic_containers else (dict, list)
+    if not isinstance(data, container_types) or isinstance(data, (str, bytes)):
         raise TypeError(f"Unexpected type received for `data`: {type(data)}")
 
+    if isinstance(mapping, KeyMapping):
+        return mapping.forward(data, generic_containers=generic_containers)
+
     if not isinstance(mapping, dict):
         raise TypeError(f"Unexpected type received for `mapping`: {type(mapping)}")
 
-    return _lib.apply_key_mapping(data=data, mapping=mapping)
+    return _lib.apply_key_mapping(
+        data=data, mapping=mapping, generic_containers=generic_containers
+    )
+
+
+def transform(data: dict | list, spec: dict | Transform) -> dict | list:
+    if not isinstance(data, (dict, list)):
+        raise TypeError(f"Unexpected type received for `data`: {type(data)}")
+
+    if isinstance(spec, Transform):
+        return spec.apply(data)
+
+    if not isinstance(spec, dict):
+        raise TypeError(f"Unexpected type received for `spec`: {type(spec)}")
+
+    return _lib.transform(data=data, spec=spec)
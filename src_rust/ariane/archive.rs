@@ -0,0 +1,128 @@
+use pyo3::{exceptions::PyKeyError, prelude::*, types::PyBytes};
+use std::io::{Cursor, Read, Seek, Write};
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pyfunction, gen_stub_pymethods};
+
+/// Entry of the TML archive holding the survey, the other ones are attachments.
+pub(crate) const DATA_ENTRY: &str = "Data.xml";
+
+/// An entry of a TML archive.
+#[gen_stub_pyclass]
+#[pyclass(module = "openspeleo_core._rust_lib.ariane", frozen)]
+#[derive(Clone, Debug)]
+pub struct TmlEntry {
+    #[pyo3(get)]
+    pub name: String,
+    /// Uncompressed size, in bytes.
+    #[pyo3(get)]
+    pub size: u64,
+    /// Size in the archive, in bytes.
+    #[pyo3(get)]
+    pub compressed_size: u64,
+    /// Compression method: `stored`, `deflated`, ...
+    #[pyo3(get)]
+    pub compression: String,
+}
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl TmlEntry {
+    pub fn __repr__(&self) -> String {
+        format!(
+            "TmlEntry(name={:?}, size={}, compression={:?})",
+            self.name, self.size, self.compression
+        )
+    }
+}
+
+fn compression_name(method: CompressionMethod) -> String {
+    match method {
+        CompressionMethod::Stored => "stored".to_string(),
+        CompressionMethod::Deflated => "deflated".to_string(),
+        method => format!("{method:?}").to_ascii_lowercase(),
+    }
+}
+
+fn zip_error(e: zip::result::ZipError) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Failed to open zip archive: {e}"))
+}
+
+/// Reads a TML archive in memory, so that it can be copied over itself.
+pub(crate) fn open_archive(path: &str) -> PyResult<ZipArchive<Cursor<Vec<u8>>>> {
+    let bytes = std::fs::read(path).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Failed to open file: {e}"))
+    })?;
+    ZipArchive::new(Cursor::new(bytes)).map_err(zip_error)
+}
+
+/// Copies every entry but "Data.xml" of `source` to `archive`, unchanged (compression included).
+pub(crate) fn copy_attachments<R: Read + Seek, W: Write + Seek>(
+    source: &mut ZipArchive<R>,
+    archive: &mut ZipWriter<W>,
+) -> PyResult<()> {
+    for index in 0..source.len() {
+        let entry = source.by_index_raw(index).map_err(zip_error)?;
+        if entry.name() == DATA_ENTRY {
+            continue;
+        }
+        archive.raw_copy_file(entry).map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyIOError, _>(format!(
+                "Failed to copy file in zip archive: {e}"
+            ))
+        })?;
+    }
+    Ok(())
+}
+
+/// Lists the entries of a TML archive: "Data.xml" and the attachments (images, backgrounds, ...)
+/// stored next to it, in archive order.
+///
+/// # Arguments
+///
+/// * `path`: The path of the TML file.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (path))]
+pub fn list_ariane_tml_entries(path: &str) -> PyResult<Vec<TmlEntry>> {
+    let mut archive = open_archive(path)?;
+    (0..archive.len())
+        .map(|index| {
+            let entry = archive.by_index_raw(index).map_err(zip_error)?;
+            Ok(TmlEntry {
+                name: entry.name().to_string(),
+                size: entry.size(),
+                compressed_size: entry.compressed_size(),
+                compression: compression_name(entry.compression()),
+            })
+        })
+        .collect()
+}
+
+/// Reads the uncompressed bytes of an entry of a TML archive.
+///
+/// # Arguments
+///
+/// * `path`: The path of the TML file.
+/// * `name`: The name of the entry, as listed by `list_ariane_tml_entries`.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (path, name))]
+pub fn read_ariane_tml_entry<'py>(
+    py: Python<'py>,
+    path: &str,
+    name: &str,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let mut archive = open_archive(path)?;
+    let mut entry = archive.by_name(name).map_err(|e| match e {
+        zip::result::ZipError::FileNotFound => {
+            PyKeyError::new_err(format!("No entry `{name}` in the archive"))
+        }
+        e => zip_error(e),
+    })?;
+    let mut bytes = Vec::with_capacity(entry.size() as usize);
+    entry.read_to_end(&mut bytes).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Failed to read file: {e}"))
+    })?;
+    Ok(PyBytes::new(py, &bytes))
+}
@@ -0,0 +1,105 @@
+use numpy::PyArray1;
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyDict, PyFloat, PyString},
+};
+use pythonize::depythonize;
+use serde_json::Value;
+
+use pyo3_stub_gen::derive::gen_stub_pyfunction;
+
+use super::table::{self, Cell, Column};
+use super::{deserialize, loader};
+
+/// Shot fields returned as arrays, followed by the computed station positions.
+const ARRAY_COLUMNS: &[&str] = &[
+    "Length",
+    "Azimut",
+    "Inclination",
+    "Depth",
+    "Left",
+    "Right",
+    "Up",
+    "Down",
+    "x",
+    "y",
+    "z",
+];
+
+/// Builds one `float64` array per numeric column and an object array of shot `ID`s.
+fn arrays_to_py<'py>(py: Python<'py>, document: &Value) -> PyResult<Bound<'py, PyDict>> {
+    let columns: Vec<Column> = std::iter::once("ID")
+        .chain(ARRAY_COLUMNS.iter().copied())
+        .map(Column::parse)
+        .collect();
+    let rows = table::shot_rows(document, &columns).map_err(PyValueError::new_err)?;
+    // `PyArray1` panics when NumPy is not installed, an ImportError is clearer
+    py.import("numpy")?;
+
+    let arrays = PyDict::new(py);
+    let ids: Vec<Py<PyAny>> = rows
+        .iter()
+        .map(|row| match &row[0] {
+            Cell::Text(id) => PyString::new(py, id).into_any().unbind(),
+            Cell::Number(id) => PyFloat::new(py, *id).into_any().unbind(),
+            Cell::Empty => py.None(),
+        })
+        .collect();
+    arrays.set_item("ID", PyArray1::from_vec(py, ids))?;
+
+    for (index, column) in columns.iter().enumerate().skip(1) {
+        let values: Vec<f64> = rows
+            .iter()
+            .map(|row| match row[index] {
+                Cell::Number(number) => number,
+                _ => f64::NAN,
+            })
+            .collect();
+        arrays.set_item(column.name(), PyArray1::from_vec(py, values))?;
+    }
+    Ok(arrays)
+}
+
+/// Returns the measurements and station positions of the shots of a loaded survey as NumPy
+/// arrays, one row per shot in document order.
+///
+/// The dict holds a `float64` array for `Length`, `Azimut`, `Inclination`, `Depth`, `Left`,
+/// `Right`, `Up` and `Down`, missing or invalid values being NaN, and for the `x`, `y` and `z`
+/// position of the shot station (see `export_shots_csv`). `ID` is an object array mapping
+/// each row back to its shot.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+pub fn shot_arrays<'py>(
+    py: Python<'py>,
+    data: &Bound<'py, PyDict>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let data: Value = depythonize(data)?;
+    arrays_to_py(py, &data)
+}
+
+/// Reads the shots of a TML file straight into NumPy arrays, without building the dict.
+///
+/// See `shot_arrays`; `lossy`, `unit` and `north` are as in `load_ariane_tml_file_to_dict`.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (path, lossy = false, unit = None, north = None))]
+pub fn load_ariane_tml_file_to_arrays<'py>(
+    py: Python<'py>,
+    path: &str,
+    lossy: bool,
+    unit: Option<&str>,
+    north: Option<&str>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let options = deserialize::ParseOptions::default();
+    let data = loader::read_ariane_file(
+        py,
+        path,
+        Some(loader::ArianeFormat::Tml),
+        &options,
+        lossy,
+        unit,
+        north,
+    )?;
+    arrays_to_py(py, &data)
+}
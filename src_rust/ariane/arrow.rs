@@ -0,0 +1,259 @@
+use arrow_array::builder::{Float64Builder, Int64Builder, StringBuilder};
+use arrow_array::ffi::to_ffi;
+use arrow_array::ffi_stream::FFI_ArrowArrayStream;
+use arrow_array::{Array, ArrayRef, RecordBatch, RecordBatchIterator, StructArray};
+use arrow_schema::ffi::FFI_ArrowSchema;
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyCapsule, PyDict},
+};
+use pythonize::depythonize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::sync::Arc;
+
+use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pyfunction, gen_stub_pymethods};
+
+use super::table::{self, Cell, Column};
+use super::{deserialize, loader, survey};
+
+/// Shot fields holding a station number, exported as integers.
+const INTEGER_FIELDS: &[&str] = &["ID", "FromID"];
+
+/// Builds a record batch of the shots of a loaded survey, one row per shot.
+///
+/// Measurement and computed columns are `float64`, `ID` and `FromID` are `int64` and the other
+/// ones `utf8`. Missing values, measurements which are not numbers and stations which are not
+/// integers are null. The survey `unit` is kept in the schema metadata.
+pub fn shot_batch(document: &Value, columns: &[Column]) -> Result<RecordBatch, String> {
+    let rows = table::shot_rows(document, columns)?;
+    let mut fields = Vec::with_capacity(columns.len());
+    let mut arrays: Vec<ArrayRef> = Vec::with_capacity(columns.len());
+    for (index, column) in columns.iter().enumerate() {
+        let cells = rows.iter().map(|row| &row[index]);
+        if INTEGER_FIELDS.contains(&column.name()) {
+            let mut builder = Int64Builder::with_capacity(rows.len());
+            for cell in cells {
+                builder.append_option(integer_cell(cell));
+            }
+            fields.push(Field::new(column.name(), DataType::Int64, true));
+            arrays.push(Arc::new(builder.finish()));
+        } else if column.is_numeric() {
+            let mut builder = Float64Builder::with_capacity(rows.len());
+            for cell in cells {
+                match cell {
+                    Cell::Number(number) => builder.append_value(*number),
+                    _ => builder.append_null(),
+                }
+            }
+            fields.push(Field::new(column.name(), DataType::Float64, true));
+            arrays.push(Arc::new(builder.finish()));
+        } else {
+            let mut builder = StringBuilder::with_capacity(rows.len(), rows.len() * 8);
+            for cell in cells {
+                match cell {
+                    Cell::Text(text) => builder.append_value(text),
+                    Cell::Number(number) => builder.append_value(number.to_string()),
+                    Cell::Empty => builder.append_null(),
+                }
+            }
+            fields.push(Field::new(column.name(), DataType::Utf8, true));
+            arrays.push(Arc::new(builder.finish()));
+        }
+    }
+
+    let metadata: HashMap<String, String> = survey::cave_file(document)
+        .ok()
+        .and_then(|cave| survey::field_str(cave, "unit"))
+        .map(|unit| HashMap::from([("unit".to_string(), unit.into_owned())]))
+        .unwrap_or_default();
+    let schema = Arc::new(Schema::new_with_metadata(fields, metadata));
+    RecordBatch::try_new(schema, arrays).map_err(|e| format!("Arrow error: {e}"))
+}
+
+/// Integer value of a cell, `78`, `"78"` and `"78.0"` alike.
+fn integer_cell(cell: &Cell<'_>) -> Option<i64> {
+    let number = match cell {
+        Cell::Number(number) => *number,
+        Cell::Text(text) => {
+            let text = text.trim();
+            if let Ok(integer) = text.parse::<i64>() {
+                return Some(integer);
+            }
+            text.parse::<f64>().ok()?
+        }
+        Cell::Empty => return None,
+    };
+    // Beyond 2^53, floats do not hold every integer
+    (number.fract() == 0.0 && number.abs() <= 9_007_199_254_740_992.0).then_some(number as i64)
+}
+
+fn arrow_error(e: ArrowError) -> PyErr {
+    PyValueError::new_err(format!("Arrow error: {e}"))
+}
+
+fn capsule_name(name: &str) -> CString {
+    CString::new(name).expect("capsule names have no NUL byte")
+}
+
+/// Shots of a survey as an Arrow record batch.
+///
+/// Implements the Arrow PyCapsule interface, so `pyarrow.record_batch(table)` or
+/// `polars.DataFrame(table)` take it without copying. For pandas, use
+/// `pyarrow.table(table).to_pandas()`.
+#[gen_stub_pyclass]
+#[pyclass(module = "openspeleo_core._rust_lib.ariane", frozen)]
+pub struct ShotTable {
+    batch: RecordBatch,
+}
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl ShotTable {
+    #[getter]
+    fn num_rows(&self) -> usize {
+        self.batch.num_rows()
+    }
+
+    #[getter]
+    fn column_names(&self) -> Vec<String> {
+        self.batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| field.name().clone())
+            .collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.batch.num_rows()
+    }
+
+    /// Exports the schema as an `arrow_schema` PyCapsule.
+    #[gen_stub(override_return_type(type_repr = "object", imports = ()))]
+    fn __arrow_c_schema__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyCapsule>> {
+        let schema =
+            FFI_ArrowSchema::try_from(self.batch.schema().as_ref()).map_err(arrow_error)?;
+        PyCapsule::new(py, schema, Some(capsule_name("arrow_schema")))
+    }
+
+    /// Exports the shots as a struct array, in `arrow_schema` and `arrow_array` PyCapsules.
+    /// `requested_schema` is ignored.
+    #[gen_stub(override_return_type(type_repr = "tuple[object, object]", imports = ()))]
+    #[pyo3(signature = (requested_schema = None))]
+    fn __arrow_c_array__<'py>(
+        &self,
+        py: Python<'py>,
+        requested_schema: Option<Bound<'py, PyAny>>,
+    ) -> PyResult<(Bound<'py, PyCapsule>, Bound<'py, PyCapsule>)> {
+        let _ = requested_schema;
+        let array = StructArray::from(self.batch.clone());
+        let (array, schema) = to_ffi(&array.to_data()).map_err(arrow_error)?;
+        Ok((
+            PyCapsule::new(py, schema, Some(capsule_name("arrow_schema")))?,
+            PyCapsule::new(py, array, Some(capsule_name("arrow_array")))?,
+        ))
+    }
+
+    /// Exports the shots as a stream of one record batch, in an `arrow_array_stream`
+    /// PyCapsule. `requested_schema` is ignored.
+    #[gen_stub(override_return_type(type_repr = "object", imports = ()))]
+    #[pyo3(signature = (requested_schema = None))]
+    fn __arrow_c_stream__<'py>(
+        &self,
+        py: Python<'py>,
+        requested_schema: Option<Bound<'py, PyAny>>,
+    ) -> PyResult<Bound<'py, PyCapsule>> {
+        let _ = requested_schema;
+        let reader = RecordBatchIterator::new([Ok(self.batch.clone())], self.batch.schema());
+        let stream = FFI_ArrowArrayStream::new(Box::new(reader));
+        PyCapsule::new(py, stream, Some(capsule_name("arrow_array_stream")))
+    }
+
+    /// Writes the shots to a Parquet file at `path`, `snappy` compressed or `uncompressed`.
+    #[pyo3(signature = (path, compression = "snappy"))]
+    fn to_parquet(&self, path: &str, compression: &str) -> PyResult<()> {
+        let compression = match compression {
+            "snappy" => Compression::SNAPPY,
+            "uncompressed" => Compression::UNCOMPRESSED,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "Unsupported compression: `{compression}`. Expected `snappy` or \
+                     `uncompressed`."
+                )))
+            }
+        };
+        let file = std::fs::File::create(path).map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Failed to create file: {e}"))
+        })?;
+        let properties = WriterProperties::builder()
+            .set_compression(compression)
+            .build();
+        let mut writer = ArrowWriter::try_new(file, self.batch.schema(), Some(properties))
+            .map_err(|e| PyValueError::new_err(format!("Parquet error: {e}")))?;
+        writer
+            .write(&self.batch)
+            .and_then(|()| writer.close().map(|_| ()))
+            .map_err(|e| {
+                PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Failed to write file: {e}"))
+            })
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!(
+            "ShotTable(rows={}, columns={})",
+            self.batch.num_rows(),
+            self.batch.num_columns()
+        )
+    }
+}
+
+/// Builds a `ShotTable` from a loaded survey, with `columns` as in `export_shots_csv`.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (data, columns = None))]
+pub fn shots_to_arrow(
+    data: &Bound<'_, PyDict>,
+    columns: Option<Vec<String>>,
+) -> PyResult<ShotTable> {
+    let columns = table::parse_columns(columns).map_err(PyValueError::new_err)?;
+    let data: Value = depythonize(data)?;
+    let batch = shot_batch(&data, &columns).map_err(PyValueError::new_err)?;
+    Ok(ShotTable { batch })
+}
+
+/// Reads the shots of a TML file straight into a `ShotTable`, without building the dict.
+///
+/// `columns` are as in `export_shots_csv`; `lossy`, `unit` and `north` as in
+/// `load_ariane_tml_file_to_dict`.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (path, columns = None, lossy = false, unit = None, north = None))]
+pub fn load_ariane_tml_file_to_arrow(
+    py: Python<'_>,
+    path: &str,
+    columns: Option<Vec<String>>,
+    lossy: bool,
+    unit: Option<&str>,
+    north: Option<&str>,
+) -> PyResult<ShotTable> {
+    let columns = table::parse_columns(columns).map_err(PyValueError::new_err)?;
+    let options = deserialize::ParseOptions::default();
+    let data = loader::read_ariane_file(
+        py,
+        path,
+        Some(loader::ArianeFormat::Tml),
+        &options,
+        lossy,
+        unit,
+        north,
+    )?;
+    let batch = shot_batch(&data, &columns).map_err(PyValueError::new_err)?;
+    Ok(ShotTable { batch })
+}
@@ -0,0 +1,452 @@
+use pyo3::{
+    exceptions::{PyTypeError, PyUserWarning, PyValueError},
+    prelude::*,
+    types::{PyDate, PyDateAccess, PyDict, PyString},
+};
+use pythonize::{depythonize, pythonize};
+use serde_json::Value;
+use std::ffi::CString;
+use std::sync::OnceLock;
+
+use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pyfunction, gen_stub_pymethods};
+
+use super::{projection, survey};
+
+/// World Magnetic Model coefficient files shipped with the crate, oldest first. Dates outside
+/// the validity windows of these releases are rejected rather than extrapolated.
+const EMBEDDED_MODELS: &[&str] = &[
+    include_str!("wmm/WMM2015.COF"),
+    include_str!("wmm/WMM2020.COF"),
+];
+
+/// Years a model is valid for from its epoch, like every World Magnetic Model release.
+const VALIDITY_YEARS: f64 = 5.0;
+
+/// Geomagnetic reference radius, in km.
+const REFERENCE_RADIUS: f64 = 6371.2;
+/// WGS84 semi-major axis, in km.
+const WGS84_A: f64 = 6378.137;
+const WGS84_F: f64 = 1.0 / 298.257_223_563;
+
+/// Spherical harmonic model of the main geomagnetic field, in the WMM/IGRF `.COF` format.
+#[gen_stub_pyclass]
+#[pyclass(module = "openspeleo_core._rust_lib.ariane", frozen)]
+#[derive(Clone, Debug)]
+pub struct GeomagneticModel {
+    #[pyo3(get)]
+    pub name: String,
+    /// Decimal year the coefficients refer to.
+    #[pyo3(get)]
+    pub epoch: f64,
+    degree: usize,
+    /// `(g, h, g_dot, h_dot)` in nT and nT/year, indexed by `n * (n + 1) / 2 + m`.
+    coefficients: Vec<(f64, f64, f64, f64)>,
+}
+
+impl GeomagneticModel {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+        let header = lines.next().ok_or("Empty coefficient file")?;
+        let mut fields = header.split_whitespace();
+        let epoch: f64 = fields
+            .next()
+            .and_then(|epoch| epoch.parse().ok())
+            .ok_or_else(|| format!("Invalid coefficient file header: `{header}`"))?;
+        let name = fields.next().unwrap_or("custom").to_string();
+
+        let mut terms = Vec::new();
+        for line in lines {
+            if line.starts_with("9999") {
+                break;
+            }
+            let values: Vec<&str> = line.split_whitespace().collect();
+            let parsed = match values.as_slice() {
+                [n, m, g, h, g_dot, h_dot, ..] => (|| {
+                    Some((
+                        n.parse::<usize>().ok()?,
+                        m.parse::<usize>().ok()?,
+                        (g.parse().ok()?, h.parse().ok()?),
+                        (g_dot.parse().ok()?, h_dot.parse().ok()?),
+                    ))
+                })(),
+                _ => None,
+            };
+            match parsed {
+                Some((n, m, ..)) if n == 0 || m > n => {
+                    return Err(format!("Invalid coefficient degree or order: `{line}`"))
+                }
+                Some(term) => terms.push(term),
+                None => return Err(format!("Invalid coefficient line: `{line}`")),
+            }
+        }
+
+        let degree = terms
+            .iter()
+            .map(|(n, ..)| *n)
+            .max()
+            .ok_or("No coefficients")?;
+        let mut coefficients = vec![(0.0, 0.0, 0.0, 0.0); (degree + 1) * (degree + 2) / 2];
+        for (n, m, (g, h), (g_dot, h_dot)) in terms {
+            coefficients[index(n, m)] = (g, h, g_dot, h_dot);
+        }
+        Ok(GeomagneticModel {
+            name,
+            epoch,
+            degree,
+            coefficients,
+        })
+    }
+
+    /// Last decimal year of the validity window of the model, which opens at its epoch.
+    pub fn valid_until(&self) -> f64 {
+        self.epoch + VALIDITY_YEARS
+    }
+
+    /// Whether `year` is within the validity window of the model.
+    pub fn covers(&self, year: f64) -> bool {
+        (self.epoch..self.valid_until()).contains(&year)
+    }
+
+    /// Declination in degrees, east positive, at a geodetic position and decimal year.
+    pub fn declination_at(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        altitude_km: f64,
+        year: f64,
+    ) -> f64 {
+        let dt = year - self.epoch;
+        let (lat, lon) = (latitude.to_radians(), longitude.to_radians());
+
+        // Geodetic to geocentric spherical coordinates
+        let e2 = WGS84_F * (2.0 - WGS84_F);
+        let radius = WGS84_A / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+        let p = (radius + altitude_km) * lat.cos();
+        let z = (radius * (1.0 - e2) + altitude_km) * lat.sin();
+        let r = p.hypot(z);
+        let geocentric_lat = (z / r).asin();
+
+        // Schmidt semi-normalized associated Legendre functions of the colatitude
+        let (cos_t, sin_t) = (geocentric_lat.sin(), geocentric_lat.cos());
+        let size = self.coefficients.len();
+        let (mut pnm, mut dpnm) = (vec![0.0; size], vec![0.0; size]);
+        pnm[0] = 1.0;
+        for n in 1..=self.degree {
+            for m in 0..=n {
+                let (k, nf, mf) = (index(n, m), n as f64, m as f64);
+                if n == m {
+                    let (prev, dprev) = (pnm[index(n - 1, n - 1)], dpnm[index(n - 1, n - 1)]);
+                    let factor = if n == 1 {
+                        1.0
+                    } else {
+                        ((2.0 * nf - 1.0) / (2.0 * nf)).sqrt()
+                    };
+                    pnm[k] = factor * sin_t * prev;
+                    dpnm[k] = factor * (sin_t * dprev + cos_t * prev);
+                } else {
+                    let (p1, dp1) = (pnm[index(n - 1, m)], dpnm[index(n - 1, m)]);
+                    let (p2, dp2) = if n >= m + 2 {
+                        (pnm[index(n - 2, m)], dpnm[index(n - 2, m)])
+                    } else {
+                        (0.0, 0.0)
+                    };
+                    let a = ((nf - 1.0).powi(2) - mf * mf).sqrt();
+                    let b = (nf * nf - mf * mf).sqrt();
+                    pnm[k] = ((2.0 * nf - 1.0) * cos_t * p1 - a * p2) / b;
+                    dpnm[k] = ((2.0 * nf - 1.0) * (cos_t * dp1 - sin_t * p1) - a * dp2) / b;
+                }
+            }
+        }
+
+        let (mut north, mut east, mut down) = (0.0, 0.0, 0.0);
+        for n in 1..=self.degree {
+            let scale = (REFERENCE_RADIUS / r).powi(n as i32 + 2);
+            for m in 0..=n {
+                let k = index(n, m);
+                let (g, h, g_dot, h_dot) = self.coefficients[k];
+                let (g, h) = (g + dt * g_dot, h + dt * h_dot);
+                let (sin_ml, cos_ml) = (m as f64 * lon).sin_cos();
+                let cosine_term = g * cos_ml + h * sin_ml;
+                north += scale * cosine_term * dpnm[k];
+                east += scale * m as f64 * (g * sin_ml - h * cos_ml) * pnm[k];
+                down -= scale * (n as f64 + 1.0) * cosine_term * pnm[k];
+            }
+        }
+        // The east component is undefined at the geographic poles
+        east /= sin_t.max(1e-12);
+
+        // Back to the geodetic frame, the east component is unchanged
+        let psi = geocentric_lat - lat;
+        let north = north * psi.cos() - down * psi.sin();
+        east.atan2(north).to_degrees()
+    }
+}
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl GeomagneticModel {
+    /// Loads a coefficient file in the WMM/IGRF `.COF` format, e.g. a newer World Magnetic
+    /// Model release.
+    #[staticmethod]
+    fn from_file(path: &str) -> PyResult<Self> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Failed to read file: {e}"))
+        })?;
+        GeomagneticModel::parse(&text).map_err(PyValueError::new_err)
+    }
+
+    /// The embedded World Magnetic Model releases, oldest first.
+    #[staticmethod]
+    fn embedded() -> Vec<GeomagneticModel> {
+        embedded_models().to_vec()
+    }
+
+    /// Declination in degrees (east positive) at `date`, a `datetime.date`, an ISO 8601 string
+    /// or a decimal year. `altitude` is in meters above the WGS84 ellipsoid. Raises a
+    /// `ValueError` when `date` is outside the five years following the model epoch.
+    #[pyo3(signature = (latitude, longitude, date, altitude = 0.0))]
+    fn declination(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        date: &Bound<'_, PyAny>,
+        altitude: f64,
+    ) -> PyResult<f64> {
+        let year = extract_year(date)?;
+        let model = model_for(std::slice::from_ref(self), year).map_err(PyValueError::new_err)?;
+        Ok(model.declination_at(latitude, longitude, altitude / 1000.0, year))
+    }
+
+    /// Decimal year the validity window of the model closes at, five years after its epoch.
+    #[getter(valid_until)]
+    fn py_valid_until(&self) -> f64 {
+        self.valid_until()
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!(
+            "GeomagneticModel(name={:?}, epoch={})",
+            self.name, self.epoch
+        )
+    }
+}
+
+fn index(n: usize, m: usize) -> usize {
+    n * (n + 1) / 2 + m
+}
+
+fn embedded_models() -> &'static [GeomagneticModel] {
+    static MODELS: OnceLock<Vec<GeomagneticModel>> = OnceLock::new();
+    MODELS.get_or_init(|| {
+        EMBEDDED_MODELS
+            .iter()
+            .map(|text| GeomagneticModel::parse(text).expect("embedded model is valid"))
+            .collect()
+    })
+}
+
+/// The model with the latest epoch among those valid at `year`.
+fn model_for(models: &[GeomagneticModel], year: f64) -> Result<&GeomagneticModel, String> {
+    models
+        .iter()
+        .filter(|model| model.covers(year))
+        .max_by(|a, b| a.epoch.total_cmp(&b.epoch))
+        .ok_or_else(|| {
+            let windows: Vec<String> = models
+                .iter()
+                .map(|model| {
+                    format!(
+                        "{} from {} to {}",
+                        model.name,
+                        model.epoch,
+                        model.valid_until()
+                    )
+                })
+                .collect();
+            format!(
+                "No geomagnetic model is valid at {year:.2} ({})",
+                windows.join(", ")
+            )
+        })
+}
+
+/// Emits a `UserWarning` for each warning of a declination correction.
+pub(crate) fn warn_correction(py: Python<'_>, warnings: &[String]) -> PyResult<()> {
+    for warning in warnings {
+        let message = CString::new(warning.as_str()).unwrap_or_default();
+        PyErr::warn(py, &py.get_type::<PyUserWarning>(), &message, 1)?;
+    }
+    Ok(())
+}
+
+/// Decimal year of a date (`2020-07-02` is `2020.5`).
+fn decimal_year((year, month, day): (i64, u32, u32)) -> f64 {
+    const CUMULATIVE_DAYS: [u32; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let day_of_year =
+        CUMULATIVE_DAYS[(month as usize - 1).min(11)] + day + u32::from(leap && month > 2) - 1;
+    year as f64 + f64::from(day_of_year) / if leap { 366.0 } else { 365.0 }
+}
+
+fn extract_year(date: &Bound<'_, PyAny>) -> PyResult<f64> {
+    if let Ok(date) = date.cast::<PyDate>() {
+        let ymd = (
+            i64::from(date.get_year()),
+            u32::from(date.get_month()),
+            u32::from(date.get_day()),
+        );
+        return Ok(decimal_year(ymd));
+    }
+    if let Ok(text) = date.cast::<PyString>() {
+        let text = text.to_str()?;
+        return survey::parse_date(text)
+            .map(decimal_year)
+            .ok_or_else(|| PyValueError::new_err(format!("Invalid date: `{text}`")));
+    }
+    date.extract::<f64>().map_err(|_| {
+        PyTypeError::new_err(format!(
+            "Expected a date, an ISO 8601 string or a decimal year, got `{date}`"
+        ))
+    })
+}
+
+/// Reference of the azimuths of a survey.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum North {
+    True,
+    Magnetic,
+}
+
+impl North {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "true" => Ok(North::True),
+            "magnetic" => Ok(North::Magnetic),
+            _ => Err(format!(
+                "Unsupported north reference: `{name}`. Expected `true` or `magnetic`."
+            )),
+        }
+    }
+}
+
+/// Rewrites the `Azimut` of every shot relative to `north`, and the `useMagneticAzimuth` flag.
+///
+/// Each shot uses the declination at its own `Date` and position, falling back to `location`
+/// and then to the first located shot (usually the entrance). Shots without a date use the
+/// date of the previous dated shot. A survey without the flag is taken as magnetic.
+///
+/// Each date uses the model of `models`, or of the embedded releases, in effect at that date.
+/// A date outside the validity window of every model is an error.
+pub fn correct_document(
+    document: &mut Value,
+    north: North,
+    location: Option<(f64, f64)>,
+    models: Option<&[GeomagneticModel]>,
+) -> Result<(), String> {
+    let cave = survey::cave_file_mut(document)?;
+    let magnetic = survey::field_str(cave, "useMagneticAzimuth").is_none_or(|flag| flag == "true");
+    let sign = match (magnetic, north) {
+        (true, North::True) => 1.0,
+        (false, North::Magnetic) => -1.0,
+        _ => return Ok(()),
+    };
+
+    let shots = match cave.get_mut("Data") {
+        Some(Value::Object(data)) => data.get_mut("SurveyData"),
+        _ => None,
+    };
+    let mut shots = survey::repeated_mut(shots);
+    let location = location
+        .or_else(|| shots.iter().find_map(|shot| survey::shot_location(shot)))
+        .ok_or("The survey has no located shot: pass a latitude and a longitude")?;
+    let mut date = shots
+        .iter()
+        .find_map(|shot| survey::field_str(shot, "Date").and_then(|d| survey::parse_date(&d)))
+        .ok_or("The survey has no dated shot to compute the declination at")?;
+
+    let models = models.unwrap_or_else(|| embedded_models());
+    for shot in &mut shots {
+        if let Some(shot_date) =
+            survey::field_str(shot, "Date").and_then(|d| survey::parse_date(&d))
+        {
+            date = shot_date;
+        }
+        let (latitude, longitude) = survey::shot_location(shot).unwrap_or(location);
+        let year = decimal_year(date);
+        let declination = model_for(models, year)?.declination_at(latitude, longitude, 0.0, year);
+
+        let Some(azimuth) = shot.get_mut("Azimut") else {
+            continue;
+        };
+        if let Some(value) = survey::scalar_f64(azimuth) {
+            survey::set_number(azimuth, (value + sign * declination).rem_euclid(360.0));
+        }
+    }
+
+    let flag = match north {
+        North::True => "false",
+        North::Magnetic => "true",
+    };
+    cave.insert(
+        "useMagneticAzimuth".to_string(),
+        Value::String(flag.to_string()),
+    );
+    Ok(())
+}
+
+/// Magnetic declination in degrees (east positive) from the embedded World Magnetic Model.
+///
+/// `date` is a `datetime.date`, an ISO 8601 string or a decimal year and `altitude` is in meters
+/// above the WGS84 ellipsoid. The release in effect at `date` is used, taken from `models`
+/// instead of the embedded ones if given. A `ValueError` is raised when no model is valid at
+/// `date`, e.g. before the oldest or after the newest embedded release.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (latitude, longitude, date, altitude = 0.0, models = None))]
+pub fn magnetic_declination(
+    latitude: f64,
+    longitude: f64,
+    date: &Bound<'_, PyAny>,
+    altitude: f64,
+    models: Option<Vec<PyRef<'_, GeomagneticModel>>>,
+) -> PyResult<f64> {
+    let year = extract_year(date)?;
+    let models = models.map(owned_models);
+    let models = models.as_deref().unwrap_or_else(|| embedded_models());
+    let model = model_for(models, year).map_err(PyValueError::new_err)?;
+    Ok(model.declination_at(latitude, longitude, altitude / 1000.0, year))
+}
+
+/// Returns a copy of a loaded survey with its azimuths relative to `north` (`true` or
+/// `magnetic`), correcting each shot with the declination at its date and position.
+///
+/// Shots without coordinates are placed at `latitude`/`longitude`, or at the first located
+/// shot. The `useMagneticAzimuth` flag is updated; a survey already relative to `north` is
+/// returned unchanged. Each shot uses the model in effect at its date, from `models` or the
+/// embedded releases; a `ValueError` is raised for a shot dated outside all of them.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (data, north, latitude = None, longitude = None, models = None))]
+pub fn correct_declination<'py>(
+    py: Python<'py>,
+    data: &Bound<'py, PyDict>,
+    north: &str,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    models: Option<Vec<PyRef<'_, GeomagneticModel>>>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let north = North::parse(north).map_err(PyValueError::new_err)?;
+    let location = projection::location(latitude, longitude)?;
+    let models = models.map(owned_models);
+    let mut data: Value = depythonize(data)?;
+    correct_document(&mut data, north, location, models.as_deref())
+        .map_err(PyValueError::new_err)?;
+    Ok(pythonize(py, &data)?)
+}
+
+fn owned_models(models: Vec<PyRef<'_, GeomagneticModel>>) -> Vec<GeomagneticModel> {
+    models
+        .iter()
+        .map(|model| GeomagneticModel::clone(model))
+        .collect()
+}
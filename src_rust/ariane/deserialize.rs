@@ -1,13 +1,20 @@
-use ahash::AHashMap;
-use pyo3::{exceptions::PyValueError, prelude::*};
+use pyo3::{
+    exceptions::{PyUnicodeWarning, PyValueError},
+    prelude::*,
+    types::PyBytes,
+};
 use pythonize::pythonize;
 use quick_xml::escape::resolve_predefined_entity;
 use quick_xml::events::{BytesRef, BytesStart, Event};
 use quick_xml::Reader;
 use serde_json::{Map, Value};
+use std::ffi::CString;
 
 use pyo3_stub_gen::derive::gen_stub_pyfunction;
 
+use super::encoding::{decode_xml, DecodeReport};
+use crate::mapping::key_mapping::KeyMapping;
+
 /// Resolves an entity reference to its string representation.
 /// Handles both predefined entities (lt, gt, amp, apos, quot) and character references (&#60; or &#x3C;).
 fn resolve_entity_ref(entity: &BytesRef<'_>) -> Option<String> {
@@ -25,15 +32,119 @@ fn resolve_entity_ref(entity: &BytesRef<'_>) -> Option<String> {
 
 #[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
 #[pyfunction]
-pub fn xml_str_to_dict(xml_str: &str, keep_null: bool) -> PyResult<Py<PyAny>> {
-    let value = parse_xml(xml_str, keep_null)
+#[pyo3(signature = (
+    xml_str,
+    keep_null,
+    keep_comments = false,
+    keep_processing_instructions = false,
+    mapping = None,
+))]
+pub fn xml_str_to_dict(
+    py: Python<'_>,
+    xml_str: &str,
+    keep_null: bool,
+    keep_comments: bool,
+    keep_processing_instructions: bool,
+    mapping: Option<&Bound<'_, KeyMapping>>,
+) -> PyResult<Py<PyAny>> {
+    let options = ParseOptions {
+        keep_null,
+        keep_comments,
+        keep_processing_instructions,
+    };
+    xml_to_py(py, xml_str, &options, mapping)
+}
+
+/// Parses `xml_str`, renames its element and attribute names with `mapping` and converts the
+/// result to Python objects.
+pub(crate) fn xml_to_py(
+    py: Python<'_>,
+    xml_str: &str,
+    options: &ParseOptions,
+    mapping: Option<&Bound<'_, KeyMapping>>,
+) -> PyResult<Py<PyAny>> {
+    let value = parse_xml(xml_str, options)
         .map_err(|e| PyValueError::new_err(format!("XML parsing error: {e}")))?;
-    Python::attach(|py| Ok(pythonize(py, &value)?.into()))
+    value_to_py(py, value, mapping)
+}
+
+/// Converts a parsed document to Python, renaming its keys with `mapping` first.
+pub(crate) fn value_to_py(
+    py: Python<'_>,
+    mut value: Value,
+    mapping: Option<&Bound<'_, KeyMapping>>,
+) -> PyResult<Py<PyAny>> {
+    if let Some(mapping) = mapping {
+        value = mapping.get().forward_xml(py, value)?;
+    }
+    Ok(pythonize(py, &value)?.into())
+}
+
+/// Parses the raw bytes of an XML document, detecting its encoding from the BOM or the XML
+/// declaration (UTF-8, UTF-16, ISO-8859-1/Windows-1252 and ISO-8859-15).
+///
+/// With `lossy`, undecodable bytes are replaced by U+FFFD and a `UnicodeWarning` lists them.
+/// `mapping` renames element and attribute names before the result is converted to Python.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (
+    xml_bytes,
+    keep_null,
+    keep_comments = false,
+    keep_processing_instructions = false,
+    lossy = false,
+    mapping = None,
+))]
+pub fn xml_bytes_to_dict(
+    py: Python<'_>,
+    xml_bytes: &Bound<'_, PyBytes>,
+    keep_null: bool,
+    keep_comments: bool,
+    keep_processing_instructions: bool,
+    lossy: bool,
+    mapping: Option<&Bound<'_, KeyMapping>>,
+) -> PyResult<Py<PyAny>> {
+    let (xml_str, report) = decode_xml(xml_bytes.as_bytes(), lossy).map_err(decode_error)?;
+    warn_decode_report(py, &report)?;
+    let options = ParseOptions {
+        keep_null,
+        keep_comments,
+        keep_processing_instructions,
+    };
+    xml_to_py(py, &xml_str, &options, mapping)
+}
+
+/// Converts a decoding failure into a `ValueError` hinting at the lossy mode.
+pub(crate) fn decode_error(e: String) -> PyErr {
+    PyValueError::new_err(format!(
+        "XML decoding error: {e}. Pass `lossy=True` to replace undecodable bytes."
+    ))
+}
+
+/// Emits a `UnicodeWarning` describing what the lossy decoding replaced, if anything.
+pub(crate) fn warn_decode_report(py: Python<'_>, report: &DecodeReport) -> PyResult<()> {
+    if let Some(summary) = report.summary() {
+        let message = CString::new(summary).unwrap_or_default();
+        PyErr::warn(py, &py.get_type::<PyUnicodeWarning>(), &message, 1)?;
+    }
+    Ok(())
+}
+
+/// Controls which optional parts of the XML document end up in the parsed value.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ParseOptions {
+    /// Keep empty elements as `null` values, see `empty_element_value`.
+    pub keep_null: bool,
+    /// Keep XML comments under the reserved `#comment` key, see `insert_misc`.
+    pub keep_comments: bool,
+    /// Keep processing instructions under the reserved `#pi` key, see `insert_misc`.
+    pub keep_processing_instructions: bool,
 }
 
-fn collect_attrs(e: &BytesStart<'_>) -> AHashMap<String, Value> {
+/// Collects the attributes of an element as `@name` keys, in document order.
+fn collect_attrs(e: &BytesStart<'_>) -> Map<String, Value> {
     let iter = e.attributes();
-    let mut map = AHashMap::with_capacity(iter.size_hint().1.unwrap_or(0));
+    let mut map = Map::with_capacity(iter.size_hint().1.unwrap_or(0));
 
     for attr in iter.filter_map(Result::ok) {
         // Safety: According to XML spec and quick_xml guarantees, element and attribute names are valid UTF-8
@@ -50,8 +161,105 @@ fn collect_attrs(e: &BytesStart<'_>) -> AHashMap<String, Value> {
     map
 }
 
+/// Moves the accumulated text into the current element.
+///
+/// Text-only elements become a plain string, while text mixed with children is stored under `#text`.
+/// The runs of text around the children are joined with a space (`see <b/> page` is `see page`).
+fn flush_text(text_buffer: &mut String, current_value: &mut Option<Value>) {
+    if text_buffer.is_empty() {
+        return;
+    }
+
+    // Trim leading/trailing whitespace from the complete accumulated text
+    let trimmed = text_buffer.trim();
+    if !trimmed.is_empty() {
+        match current_value {
+            Some(Value::Object(obj)) if !obj.is_empty() => match obj.get_mut("#text") {
+                Some(Value::String(existing)) => {
+                    existing.push(' ');
+                    existing.push_str(trimmed);
+                }
+                _ => {
+                    obj.insert("#text".to_string(), Value::String(trimmed.to_owned()));
+                }
+            },
+            _ => *current_value = Some(Value::String(trimmed.to_owned())),
+        }
+    }
+    text_buffer.clear();
+}
+
+/// Value of an empty element (`<name/>`): its attributes when it has some, `null` with
+/// `keep_null`, `None` when it is skipped.
+///
+/// Attributes are kept with `keep_null` as well, `null` would lose them.
+fn empty_element_value(attrs: Map<String, Value>, keep_null: bool) -> Option<Value> {
+    if !attrs.is_empty() {
+        Some(Value::Object(attrs))
+    } else if keep_null {
+        Some(Value::Null)
+    } else {
+        None
+    }
+}
+
+/// Returns the object children can be added to, turning a text-only value into `{"#text": ...}`.
+///
+/// Used for every child node, so that text mixed with child elements (`<a>text<b/></a>`) keeps
+/// both, whatever their order.
+fn as_parent(current_value: &mut Option<Value>) -> Option<&mut Map<String, Value>> {
+    if let Some(Value::String(_)) = current_value {
+        let text = current_value.take().unwrap_or_default();
+        let mut obj = Map::new();
+        obj.insert("#text".to_string(), text);
+        *current_value = Some(Value::Object(obj));
+    }
+    match current_value {
+        Some(Value::Object(obj)) => Some(obj),
+        _ => None,
+    }
+}
+
+/// Adds a child to `parent`, converting repeated keys to an array.
+fn insert_child(parent: &mut Map<String, Value>, name: String, value: Value) {
+    // Handle duplicate keys by converting to array
+    if let Some(existing) = parent.get_mut(&name) {
+        if let Value::Array(ref mut arr) = existing {
+            arr.push(value);
+        } else {
+            let existing_val = existing.take();
+            *existing = Value::Array(vec![existing_val, value]);
+        }
+    } else {
+        parent.insert(name, value);
+    }
+}
+
+/// Number of child nodes (elements, comments and processing instructions) of `parent`.
+fn node_count(parent: &Map<String, Value>) -> usize {
+    parent
+        .iter()
+        .filter(|(key, _)| !key.starts_with('@') && *key != "#text")
+        .map(|(_, value)| match value {
+            Value::Array(items) => items.len(),
+            _ => 1,
+        })
+        .sum()
+}
+
+/// Adds a comment or processing instruction to `parent` as `{"index": ..., "text": ...}`, the
+/// index being its position among the child nodes so that it is written back at the same place.
+fn insert_misc(parent: &mut Map<String, Value>, key: &str, text: String) {
+    let mut node = Map::with_capacity(2);
+    node.insert("index".to_string(), Value::from(node_count(parent)));
+    node.insert("text".to_string(), Value::String(text));
+    insert_child(parent, key.to_string(), Value::Object(node));
+}
+
 // XML to Dict implementation with optional null field preservation
-fn parse_xml(xml: &str, keep_null: bool) -> Result<Value, String> {
+pub fn parse_xml(xml: &str, options: &ParseOptions) -> Result<Value, String> {
+    let keep_null = options.keep_null;
+
     // Create a new XML reader with optimizations
     let mut reader = Reader::from_str(xml);
     // NOTE: trim_text must be false in quick-xml 0.38+ because text is now split across
@@ -63,13 +271,14 @@ fn parse_xml(xml: &str, keep_null: bool) -> Result<Value, String> {
     reader.config_mut().expand_empty_elements = false;
 
     // Initialize variables to keep track of the parsing state
-    let mut stack: Vec<(String, Option<Value>, AHashMap<String, Value>)> = Vec::with_capacity(32);
-    let mut root: Option<Value> = None;
+    let mut stack: Vec<(String, Option<Value>, Map<String, Value>)> = Vec::with_capacity(32);
+    // Top-level nodes: the root element plus any comment or PI around it
+    let mut document: Map<String, Value> = Map::new();
+    let mut has_root = false;
     let mut current_value: Option<Value> = None;
-    let mut current_attrs: AHashMap<String, Value> = AHashMap::default();
+    let mut current_attrs: Map<String, Value> = Map::new();
     let mut buf = Vec::with_capacity(1024);
-    let mut root_name = String::new();
-    // Text accumulator for consecutive Text/GeneralRef events (needed for quick-xml 0.38+)
+    // Text accumulator for consecutive Text/GeneralRef/CData events (needed for quick-xml 0.38+)
     let mut text_buffer = String::new();
 
     loop {
@@ -77,28 +286,19 @@ fn parse_xml(xml: &str, keep_null: bool) -> Result<Value, String> {
         match reader.read_event_into(&mut buf) {
             Ok(Event::Start(e)) => {
                 // Flush any accumulated text before processing new element
-                if !text_buffer.is_empty() {
-                    // Trim leading/trailing whitespace from the complete accumulated text
-                    let trimmed = text_buffer.trim();
-                    if !trimmed.is_empty() {
-                        current_value = Some(Value::String(trimmed.to_owned()));
-                    }
-                    text_buffer.clear();
-                }
+                flush_text(&mut text_buffer, &mut current_value);
 
                 // Handle the start of an element
 
                 // Safety: According to XML spec and quick_xml guarantees, element and attribute names are valid UTF-8
                 let name = unsafe { std::str::from_utf8_unchecked(e.name().as_ref()) }.to_string();
 
-                // Set the root name if it's not already set
-                if root_name.is_empty() {
-                    root_name = name.clone();
-                }
-
                 // Handle attributes efficiently with pre-allocation
                 let attrs = collect_attrs(&e);
 
+                // Text seen so far is kept alongside the upcoming child, see `as_parent`
+                as_parent(&mut current_value);
+
                 // Push the current state onto the stack
                 stack.push((name, current_value, current_attrs));
                 current_attrs = attrs;
@@ -110,41 +310,51 @@ fn parse_xml(xml: &str, keep_null: bool) -> Result<Value, String> {
                     text_buffer.push_str(&text);
                 }
             }
+            Ok(Event::CData(e)) => {
+                // CDATA sections are plain text that must not be unescaped
+                if let Ok(text) = e.decode() {
+                    text_buffer.push_str(&text);
+                }
+            }
             Ok(Event::GeneralRef(e)) => {
                 // Handle entity references like &lt; &gt; &amp; etc. (new in quick-xml 0.38+)
                 if let Some(resolved) = resolve_entity_ref(&e) {
                     text_buffer.push_str(&resolved);
                 }
             }
+            Ok(Event::Comment(e)) if options.keep_comments => {
+                flush_text(&mut text_buffer, &mut current_value);
+                let comment = e.decode().map_err(|e| e.to_string())?.into_owned();
+                let parent = as_parent(&mut current_value).unwrap_or(&mut document);
+                insert_misc(parent, "#comment", comment);
+            }
+            Ok(Event::PI(e)) if options.keep_processing_instructions => {
+                flush_text(&mut text_buffer, &mut current_value);
+                let pi = std::str::from_utf8(&e)
+                    .map_err(|e| e.to_string())?
+                    .to_owned();
+                let parent = as_parent(&mut current_value).unwrap_or(&mut document);
+                insert_misc(parent, "#pi", pi);
+            }
             Ok(Event::End(_)) => {
                 // Flush any accumulated text before processing end element
-                if !text_buffer.is_empty() {
-                    // Trim leading/trailing whitespace from the complete accumulated text
-                    let trimmed = text_buffer.trim();
-                    if !trimmed.is_empty() {
-                        current_value = Some(Value::String(trimmed.to_owned()));
-                    }
-                    text_buffer.clear();
-                }
+                flush_text(&mut text_buffer, &mut current_value);
 
                 // Handle the end of an element
-                // let (name, parent_val, parent_attrs) = stack.pop().unwrap();
                 let (name, parent_val, parent_attrs) = match stack.pop() {
                     Some(t) => t,
                     None => return Err("Unexpected end tag without matching start".to_string()),
                 };
 
-                let mut obj = match current_value.take() {
-                    Some(Value::Object(m)) => m,
+                // Attributes come first, followed by the children in document order
+                let mut obj = std::mem::take(&mut current_attrs);
+                match current_value.take() {
+                    Some(Value::Object(m)) => obj.extend(m),
                     Some(v) => {
-                        let mut m = Map::new();
-                        m.insert("#text".to_string(), v);
-                        m
+                        obj.insert("#text".to_string(), v);
                     }
-                    None => Map::new(),
-                };
-
-                obj.extend(current_attrs);
+                    None => (),
+                }
 
                 current_value = parent_val;
                 current_attrs = parent_attrs;
@@ -156,93 +366,46 @@ fn parse_xml(xml: &str, keep_null: bool) -> Result<Value, String> {
                     Value::Object(obj)
                 };
 
-                // Check if the new value is null and if we should keep null values
-                if keep_null || new_value != Value::Null {
-                    // Check if the new value is an empty object and if we should keep null values
-                    if let Value::Object(ref obj) = new_value {
-                        if obj.is_empty() && !keep_null {
-                            continue;
-                        }
+                // Check if the new value is an empty object and if we should keep null values
+                if let Value::Object(ref obj) = new_value {
+                    if obj.is_empty() && !keep_null {
+                        buf.clear();
+                        continue;
                     }
+                }
 
-                    // Add the new value to the parent object
-                    if let Some(Value::Object(ref mut parent)) = current_value {
-                        // Handle duplicate keys by converting to array
-                        if let Some(existing) = parent.get_mut(&name) {
-                            if let Value::Array(ref mut arr) = existing {
-                                arr.push(new_value);
-                            } else {
-                                let existing_val = existing.take();
-                                parent.insert(name, Value::Array(vec![existing_val, new_value]));
-                            }
-                        } else {
-                            parent.insert(name, new_value);
-                        }
-                    } else {
-                        root = Some(new_value);
+                // Add the new value to the parent object
+                match current_value {
+                    Some(Value::Object(ref mut parent)) => insert_child(parent, name, new_value),
+                    _ => {
+                        has_root = true;
+                        insert_child(&mut document, name, new_value);
                     }
                 }
             }
             Ok(Event::Empty(e)) => {
                 // Flush any accumulated text before processing empty element
-                if !text_buffer.is_empty() {
-                    // Trim leading/trailing whitespace from the complete accumulated text
-                    let trimmed = text_buffer.trim();
-                    if !trimmed.is_empty() {
-                        current_value = Some(Value::String(trimmed.to_owned()));
-                    }
-                    text_buffer.clear();
-                }
+                flush_text(&mut text_buffer, &mut current_value);
 
                 // Handle empty elements
                 // Safety: According to XML spec and quick_xml guarantees, element and attribute names are valid UTF-8
                 let name = unsafe { std::str::from_utf8_unchecked(e.name().as_ref()) }.to_string();
 
-                // Set the root name if it's not already set
-                if root_name.is_empty() {
-                    root_name = name.clone();
-                }
-
                 // Handle attributes with pre-allocation
                 let attrs = collect_attrs(&e);
 
-                // Create a new value from the attributes
-                let new_value = if keep_null {
-                    Value::Null
-                } else if attrs.is_empty() {
+                let Some(new_value) = empty_element_value(attrs, keep_null) else {
                     // Skip this empty element without attributes
                     buf.clear();
                     continue;
-                } else {
-                    Value::Object(attrs.into_iter().collect())
                 };
 
-                // Check if the new value is null and if we should keep null values
-                if keep_null || new_value != Value::Null {
-                    // Check if the new value is an empty object and if we should keep null values
-                    if let Value::Object(ref obj) = new_value {
-                        if obj.is_empty() && !keep_null {
-                            buf.clear();
-                            continue;
-                        }
-                    }
-
-                    // Add the new value to the parent object
-                    if let Some(Value::Object(ref mut parent)) = current_value {
-                        // Handle duplicate keys by converting to array
-                        if let Some(existing) = parent.get_mut(&name) {
-                            if let Value::Array(ref mut arr) = existing {
-                                arr.push(new_value);
-                            } else {
-                                let existing_val = std::mem::replace(existing, Value::Null);
-                                let arr = vec![existing_val, new_value];
-                                parent.insert(name.clone(), Value::Array(arr));
-                            }
-                        } else {
-                            parent.insert(name, new_value);
-                        }
-                    } else {
-                        root = Some(new_value);
+                // Add the new value to the parent object
+                match as_parent(&mut current_value) {
+                    Some(parent) => insert_child(parent, name, new_value),
+                    None => {
+                        has_root = true;
+                        insert_child(&mut document, name, new_value);
                     }
                 }
             }
@@ -260,11 +423,8 @@ fn parse_xml(xml: &str, keep_null: bool) -> Result<Value, String> {
         buf.clear();
     }
 
-    // Create the final root object
-    root.map(|r| {
-        let mut root_obj = Map::new();
-        root_obj.insert(root_name, r);
-        Value::Object(root_obj)
-    })
-    .ok_or_else(|| "Empty XML document".to_string())
+    if !has_root {
+        return Err("Empty XML document".to_string());
+    }
+    Ok(Value::Object(document))
 }
@@ -0,0 +1,465 @@
+use ahash::{AHashMap, AHashSet};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+use pythonize::{depythonize, pythonize};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fmt::Write;
+
+use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pyfunction, gen_stub_pymethods};
+
+use super::survey;
+
+/// Value of a field before and after, `None` when the field is missing.
+#[derive(Clone, Debug, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ShotChange {
+    pub id: String,
+    pub changes: Vec<FieldChange>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SectionRename {
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LayerChange {
+    pub name: String,
+    pub changes: Vec<FieldChange>,
+}
+
+/// Domain-level differences between two surveys.
+///
+/// Shots are matched by `ID`, layers by `name`. Numbers are compared numerically, so `5` and
+/// `5.0` are equal. A `Section` renamed on every shot it contains is reported once as a
+/// renamed section instead of a change on each shot.
+#[gen_stub_pyclass]
+#[pyclass(module = "openspeleo_core._rust_lib.ariane", frozen)]
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SurveyDiff {
+    pub header: Vec<FieldChange>,
+    pub sections_renamed: Vec<SectionRename>,
+    pub shots_added: Vec<Value>,
+    pub shots_removed: Vec<Value>,
+    pub shots_modified: Vec<ShotChange>,
+    pub layers_added: Vec<String>,
+    pub layers_removed: Vec<String>,
+    pub layers_modified: Vec<LayerChange>,
+}
+
+/// A shot present in both surveys: its key, the old and the new version.
+type MatchedShot<'a> = (&'a str, &'a Map<String, Value>, &'a Map<String, Value>);
+
+/// Elements of `CaveFile` compared as a whole rather than as header fields.
+pub(super) const BODY_ELEMENTS: &[&str] = &["Data", "Layers"];
+
+impl SurveyDiff {
+    pub fn compute(old: &Value, new: &Value) -> Result<Self, String> {
+        let old = survey::cave_file(old)?;
+        let new = survey::cave_file(new)?;
+
+        let mut diff = SurveyDiff::default();
+        diff.diff_header(old, new);
+        diff.diff_shots(&survey::shots(old), &survey::shots(new));
+        diff.diff_layers(&survey::layers(old), &survey::layers(new));
+        Ok(diff)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.header.is_empty()
+            && self.sections_renamed.is_empty()
+            && self.shots_added.is_empty()
+            && self.shots_removed.is_empty()
+            && self.shots_modified.is_empty()
+            && self.layers_added.is_empty()
+            && self.layers_removed.is_empty()
+            && self.layers_modified.is_empty()
+    }
+
+    fn diff_header(&mut self, old: &Map<String, Value>, new: &Map<String, Value>) {
+        let keys = old
+            .keys()
+            .chain(new.keys().filter(|key| !old.contains_key(*key)));
+        for key in keys {
+            if BODY_ELEMENTS.contains(&key.as_str()) {
+                continue;
+            }
+            diff_values(key, old.get(key), new.get(key), &mut self.header);
+        }
+    }
+
+    fn diff_shots(&mut self, old: &[&Map<String, Value>], new: &[&Map<String, Value>]) {
+        let old_keys = shot_keys(old);
+        let new_keys = shot_keys(new);
+        let old_by_key: AHashMap<&str, &Map<String, Value>> = old_keys
+            .iter()
+            .map(String::as_str)
+            .zip(old.iter().copied())
+            .collect();
+        let new_by_key: AHashSet<&str> = new_keys.iter().map(String::as_str).collect();
+
+        let matched: Vec<MatchedShot<'_>> = new_keys
+            .iter()
+            .zip(new)
+            .filter_map(|(key, shot)| Some((key.as_str(), *old_by_key.get(key.as_str())?, *shot)))
+            .collect();
+
+        self.sections_renamed = find_section_renames(old, new, &matched);
+        let renamed: AHashMap<&str, &str> = self
+            .sections_renamed
+            .iter()
+            .map(|rename| (rename.old.as_str(), rename.new.as_str()))
+            .collect();
+
+        for (key, old_shot, new_shot) in matched {
+            let mut changes = Vec::new();
+            diff_maps("", old_shot, new_shot, &mut changes);
+            changes.retain(|change| !(change.field == "Section" && is_rename(change, &renamed)));
+            if !changes.is_empty() {
+                self.shots_modified.push(ShotChange {
+                    id: key.to_string(),
+                    changes,
+                });
+            }
+        }
+
+        self.shots_removed = old_keys
+            .iter()
+            .zip(old)
+            .filter(|(key, _)| !new_by_key.contains(key.as_str()))
+            .map(|(_, shot)| Value::Object((*shot).clone()))
+            .collect();
+        self.shots_added = new_keys
+            .iter()
+            .zip(new)
+            .filter(|(key, _)| !old_by_key.contains_key(key.as_str()))
+            .map(|(_, shot)| Value::Object((*shot).clone()))
+            .collect();
+    }
+
+    fn diff_layers(&mut self, old: &[&Map<String, Value>], new: &[&Map<String, Value>]) {
+        let name = |layer: &Map<String, Value>| {
+            survey::field_str(layer, "name").map(|name| name.into_owned())
+        };
+        let old_by_name: AHashMap<String, &Map<String, Value>> = old
+            .iter()
+            .filter_map(|layer| Some((name(layer)?, *layer)))
+            .collect();
+        let new_names: AHashSet<String> = new.iter().filter_map(|layer| name(layer)).collect();
+
+        for layer in new {
+            let Some(layer_name) = name(layer) else {
+                continue;
+            };
+            match old_by_name.get(&layer_name) {
+                Some(old_layer) => {
+                    let mut changes = Vec::new();
+                    diff_maps("", old_layer, layer, &mut changes);
+                    if !changes.is_empty() {
+                        self.layers_modified.push(LayerChange {
+                            name: layer_name,
+                            changes,
+                        });
+                    }
+                }
+                None => self.layers_added.push(layer_name),
+            }
+        }
+        self.layers_removed = old
+            .iter()
+            .filter_map(|layer| name(layer))
+            .filter(|layer_name| !new_names.contains(layer_name))
+            .collect();
+    }
+
+    /// Human-readable summary of the differences.
+    pub fn report(&self) -> String {
+        if self.is_empty() {
+            return "No differences".to_string();
+        }
+
+        let mut out = String::new();
+        if !self.header.is_empty() {
+            out.push_str("Header:\n");
+            write_changes(&mut out, &self.header, "  ");
+        }
+        if !self.sections_renamed.is_empty() {
+            out.push_str("Sections renamed:\n");
+            for rename in &self.sections_renamed {
+                let _ = writeln!(out, "  '{}' -> '{}'", rename.old, rename.new);
+            }
+        }
+        for (title, shots) in [
+            ("Shots added", &self.shots_added),
+            ("Shots removed", &self.shots_removed),
+        ] {
+            if shots.is_empty() {
+                continue;
+            }
+            let ids: Vec<String> = shots
+                .iter()
+                .filter_map(Value::as_object)
+                .map(|shot| survey::station_id(shot, "ID").unwrap_or_else(|| "?".to_string()))
+                .collect();
+            let _ = writeln!(out, "{title} ({}): {}", shots.len(), ids.join(", "));
+        }
+        if !self.shots_modified.is_empty() {
+            let _ = writeln!(out, "Shots modified ({}):", self.shots_modified.len());
+            for shot in &self.shots_modified {
+                let _ = writeln!(out, "  Shot {}:", shot.id);
+                write_changes(&mut out, &shot.changes, "    ");
+            }
+        }
+        if !self.layers_added.is_empty() {
+            let _ = writeln!(out, "Layers added: {}", self.layers_added.join(", "));
+        }
+        if !self.layers_removed.is_empty() {
+            let _ = writeln!(out, "Layers removed: {}", self.layers_removed.join(", "));
+        }
+        if !self.layers_modified.is_empty() {
+            out.push_str("Layers modified:\n");
+            for layer in &self.layers_modified {
+                let _ = writeln!(out, "  Layer {}:", layer.name);
+                write_changes(&mut out, &layer.changes, "    ");
+            }
+        }
+        out.truncate(out.trim_end().len());
+        out
+    }
+}
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl SurveyDiff {
+    /// Changed fields of `CaveFile` outside `Data` and `Layers` (`caveName`, `unit`, ...).
+    #[getter(header)]
+    fn py_header<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, &self.header)?)
+    }
+
+    #[getter(sections_renamed)]
+    fn py_sections_renamed<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, &self.sections_renamed)?)
+    }
+
+    #[getter(shots_added)]
+    fn py_shots_added<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, &self.shots_added)?)
+    }
+
+    #[getter(shots_removed)]
+    fn py_shots_removed<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, &self.shots_removed)?)
+    }
+
+    #[getter(shots_modified)]
+    fn py_shots_modified<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, &self.shots_modified)?)
+    }
+
+    #[getter(layers_added)]
+    fn py_layers_added(&self) -> Vec<String> {
+        self.layers_added.clone()
+    }
+
+    #[getter(layers_removed)]
+    fn py_layers_removed(&self) -> Vec<String> {
+        self.layers_removed.clone()
+    }
+
+    #[getter(layers_modified)]
+    fn py_layers_modified<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, &self.layers_modified)?)
+    }
+
+    /// The whole diff as a dict.
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, self)?)
+    }
+
+    #[pyo3(name = "report")]
+    fn py_report(&self) -> String {
+        self.report()
+    }
+
+    fn __bool__(&self) -> bool {
+        !self.is_empty()
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!(
+            "SurveyDiff(header={}, sections_renamed={}, shots_added={}, shots_removed={}, \
+             shots_modified={}, layers_added={}, layers_removed={}, layers_modified={})",
+            self.header.len(),
+            self.sections_renamed.len(),
+            self.shots_added.len(),
+            self.shots_removed.len(),
+            self.shots_modified.len(),
+            self.layers_added.len(),
+            self.layers_removed.len(),
+            self.layers_modified.len(),
+        )
+    }
+}
+
+/// Compares two loaded surveys at the domain level.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+pub fn diff_surveys(old: &Bound<'_, PyDict>, new: &Bound<'_, PyDict>) -> PyResult<SurveyDiff> {
+    let old: Value = depythonize(old)?;
+    let new: Value = depythonize(new)?;
+    SurveyDiff::compute(&old, &new).map_err(PyValueError::new_err)
+}
+
+/// Matching key of each shot: its `ID`, or its position among the shots without one.
+pub(super) fn shot_keys(shots: &[&Map<String, Value>]) -> Vec<String> {
+    let mut missing = 0;
+    shots
+        .iter()
+        .map(|shot| match survey::station_id(shot, "ID") {
+            Some(id) => id,
+            None => {
+                missing += 1;
+                format!("#{missing}")
+            }
+        })
+        .collect()
+}
+
+/// Sections whose matched shots all moved to one new name that did not exist before.
+fn find_section_renames(
+    old: &[&Map<String, Value>],
+    new: &[&Map<String, Value>],
+    matched: &[MatchedShot<'_>],
+) -> Vec<SectionRename> {
+    let sections = |shots: &[&Map<String, Value>]| -> AHashSet<String> {
+        shots
+            .iter()
+            .filter_map(|shot| survey::field_str(shot, "Section"))
+            .map(|section| section.into_owned())
+            .collect()
+    };
+    let old_sections = sections(old);
+    let new_sections = sections(new);
+
+    // Old section -> new sections of the matched shots, in document order
+    let mut targets: Vec<(String, Vec<String>)> = Vec::new();
+    for (_, old_shot, new_shot) in matched {
+        let (Some(from), Some(to)) = (
+            survey::field_str(old_shot, "Section"),
+            survey::field_str(new_shot, "Section"),
+        ) else {
+            continue;
+        };
+        let index = match targets.iter().position(|(section, _)| *section == from) {
+            Some(index) => index,
+            None => {
+                targets.push((from.into_owned(), Vec::new()));
+                targets.len() - 1
+            }
+        };
+        if !targets[index].1.iter().any(|section| *section == to) {
+            targets[index].1.push(to.into_owned());
+        }
+    }
+
+    targets
+        .into_iter()
+        .filter_map(|(from, to)| match to.as_slice() {
+            [to] if *to != from && !new_sections.contains(&from) && !old_sections.contains(to) => {
+                Some(SectionRename {
+                    old: from,
+                    new: to.clone(),
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn is_rename(change: &FieldChange, renamed: &AHashMap<&str, &str>) -> bool {
+    let (Some(old), Some(new)) = (&change.old, &change.new) else {
+        return false;
+    };
+    match (survey::scalar_str(old), survey::scalar_str(new)) {
+        (Some(old), Some(new)) => renamed.get(old.as_ref()) == Some(&new.as_ref()),
+        _ => false,
+    }
+}
+
+fn diff_maps(
+    prefix: &str,
+    old: &Map<String, Value>,
+    new: &Map<String, Value>,
+    out: &mut Vec<FieldChange>,
+) {
+    let keys = old
+        .keys()
+        .chain(new.keys().filter(|key| !old.contains_key(*key)));
+    for key in keys {
+        let path = match prefix {
+            "" => key.clone(),
+            prefix => format!("{prefix}.{key}"),
+        };
+        diff_values(&path, old.get(key), new.get(key), out);
+    }
+}
+
+/// Whether two values are equal at the domain level, as compared by the diff.
+pub(super) fn values_equal(old: Option<&Value>, new: Option<&Value>) -> bool {
+    let mut changes = Vec::new();
+    diff_values("", old, new, &mut changes);
+    changes.is_empty()
+}
+
+pub(super) fn maps_equal(old: &Map<String, Value>, new: &Map<String, Value>) -> bool {
+    let mut changes = Vec::new();
+    diff_maps("", old, new, &mut changes);
+    changes.is_empty()
+}
+
+/// Records the differences between two values, recursing into dicts and same-length lists.
+fn diff_values(path: &str, old: Option<&Value>, new: Option<&Value>, out: &mut Vec<FieldChange>) {
+    let change = || FieldChange {
+        field: path.to_string(),
+        old: old.cloned(),
+        new: new.cloned(),
+    };
+    match (old, new) {
+        (None | Some(Value::Null), None | Some(Value::Null)) => (),
+        (Some(Value::Object(old)), Some(Value::Object(new))) => diff_maps(path, old, new, out),
+        (Some(Value::Array(old)), Some(Value::Array(new))) if old.len() == new.len() => {
+            for (i, (old, new)) in old.iter().zip(new).enumerate() {
+                diff_values(&format!("{path}[{i}]"), Some(old), Some(new), out);
+            }
+        }
+        (Some(old), Some(new)) if survey::scalars_equal(old, new) => (),
+        _ => out.push(change()),
+    }
+}
+
+fn write_changes(out: &mut String, changes: &[FieldChange], indent: &str) {
+    let show = |value: &Option<Value>| match value {
+        None | Some(Value::Null) => "(missing)".to_string(),
+        Some(value) => match survey::scalar_str(value) {
+            Some(text) => format!("'{text}'"),
+            None => value.to_string(),
+        },
+    };
+    for change in changes {
+        let _ = writeln!(
+            out,
+            "{indent}{}: {} -> {}",
+            change.field,
+            show(&change.old),
+            show(&change.new)
+        );
+    }
+}
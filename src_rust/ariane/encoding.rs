@@ -0,0 +1,368 @@
+use std::borrow::Cow;
+
+/// Characters for the bytes `0x80..=0x9F` in Windows-1252.
+///
+/// The five unassigned bytes map to the matching C1 control, as browsers do.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+/// Encodings supported when reading XML documents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XmlEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// Also used for `ISO-8859-1` labels, which are in practice almost always Windows-1252.
+    Windows1252,
+    Iso8859_15,
+}
+
+impl XmlEncoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            XmlEncoding::Utf8 => "UTF-8",
+            XmlEncoding::Utf16Le => "UTF-16LE",
+            XmlEncoding::Utf16Be => "UTF-16BE",
+            XmlEncoding::Windows1252 => "windows-1252",
+            XmlEncoding::Iso8859_15 => "ISO-8859-15",
+        }
+    }
+
+    /// Maps an `encoding="..."` label to a supported encoding.
+    fn from_label(label: &str) -> Option<Self> {
+        match label.trim().to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" | "us-ascii" | "ascii" => Some(XmlEncoding::Utf8),
+            "utf-16le" => Some(XmlEncoding::Utf16Le),
+            "utf-16" | "utf-16be" => Some(XmlEncoding::Utf16Be),
+            "iso-8859-1" | "iso8859-1" | "iso_8859-1" | "latin1" | "latin-1" | "l1"
+            | "windows-1252" | "cp1252" | "x-cp1252" => Some(XmlEncoding::Windows1252),
+            "iso-8859-15" | "iso8859-15" | "iso_8859-15" | "latin9" | "latin-9" | "l9" => {
+                Some(XmlEncoding::Iso8859_15)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A byte sequence which could not be decoded and was replaced by U+FFFD.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replacement {
+    /// Offset of the sequence in the original bytes.
+    pub offset: usize,
+    pub bytes: Vec<u8>,
+}
+
+/// Details about how an XML document was decoded to UTF-8.
+#[derive(Debug)]
+pub struct DecodeReport {
+    pub encoding: XmlEncoding,
+    /// Encoding declared in the document but not supported, decoded as UTF-8 instead.
+    pub unknown_label: Option<String>,
+    pub replacements: Vec<Replacement>,
+}
+
+impl DecodeReport {
+    /// Human readable summary of everything the lossy mode had to replace.
+    pub fn summary(&self) -> Option<String> {
+        if self.unknown_label.is_none() && self.replacements.is_empty() {
+            return None;
+        }
+
+        let mut summary = String::new();
+        if let Some(label) = &self.unknown_label {
+            summary.push_str(&format!(
+                "Unsupported encoding `{label}`, the document was decoded as UTF-8. "
+            ));
+        }
+        if !self.replacements.is_empty() {
+            summary.push_str(&format!(
+                "Replaced {} invalid {} byte sequence(s):",
+                self.replacements.len(),
+                self.encoding.name()
+            ));
+            for replacement in self.replacements.iter().take(10) {
+                let bytes: Vec<String> = replacement
+                    .bytes
+                    .iter()
+                    .map(|b| format!("0x{b:02X}"))
+                    .collect();
+                summary.push_str(&format!(
+                    " [{}] at byte {}",
+                    bytes.join(" "),
+                    replacement.offset
+                ));
+            }
+            if self.replacements.len() > 10 {
+                summary.push_str(" ...");
+            }
+        }
+        Some(summary.trim_end().to_string())
+    }
+}
+
+/// Encoding given by the BOM of a document, with the length of the BOM.
+fn bom_encoding(bytes: &[u8]) -> Option<(XmlEncoding, usize)> {
+    match bytes {
+        [0xEF, 0xBB, 0xBF, ..] => Some((XmlEncoding::Utf8, 3)),
+        [0xFF, 0xFE, ..] => Some((XmlEncoding::Utf16Le, 2)),
+        [0xFE, 0xFF, ..] => Some((XmlEncoding::Utf16Be, 2)),
+        _ => None,
+    }
+}
+
+/// Maps a declared label to a supported encoding, or to UTF-8 along with the unsupported label.
+///
+/// The label is read from single-byte text, so without a BOM the text cannot be UTF-16 whatever
+/// it declares: it is read as UTF-8 instead.
+fn declared_label(label: String) -> (XmlEncoding, usize, Option<String>) {
+    match XmlEncoding::from_label(&label) {
+        Some(XmlEncoding::Utf16Le | XmlEncoding::Utf16Be) => (XmlEncoding::Utf8, 0, None),
+        Some(encoding) => (encoding, 0, None),
+        None => (XmlEncoding::Utf8, 0, Some(label)),
+    }
+}
+
+/// Detects the encoding of an XML document from its BOM or its XML declaration.
+///
+/// Returns the encoding, the length of the BOM and the declared label if it is not supported.
+fn detect_encoding(bytes: &[u8]) -> (XmlEncoding, usize, Option<String>) {
+    if let Some((encoding, bom_len)) = bom_encoding(bytes) {
+        return (encoding, bom_len, None);
+    }
+    match bytes {
+        // `<?` without BOM
+        [0x3C, 0x00, 0x3F, 0x00, ..] => return (XmlEncoding::Utf16Le, 0, None),
+        [0x00, 0x3C, 0x00, 0x3F, ..] => return (XmlEncoding::Utf16Be, 0, None),
+        _ => (),
+    }
+
+    match declared_encoding(bytes) {
+        Some(label) => declared_label(label),
+        None => (XmlEncoding::Utf8, 0, None),
+    }
+}
+
+/// Extracts the `encoding` pseudo-attribute of the XML declaration of an ASCII-compatible document.
+fn declared_encoding(bytes: &[u8]) -> Option<String> {
+    if !bytes.starts_with(b"<?xml") {
+        return None;
+    }
+    let end = bytes.iter().take(1024).position(|&b| b == b'>')?;
+    let decl = std::str::from_utf8(&bytes[..end]).ok()?;
+
+    let rest = &decl[decl.find("encoding")? + "encoding".len()..];
+    let rest = rest.trim_start().strip_prefix('=')?.trim_start();
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let rest = &rest[1..];
+    Some(rest[..rest.find(quote)?].to_string())
+}
+
+/// Decodes the raw bytes of an XML document to UTF-8.
+///
+/// The encoding is taken from the BOM, then from the XML declaration, and defaults to UTF-8.
+/// In `lossy` mode, undecodable sequences are replaced by U+FFFD and listed in the report
+/// instead of failing.
+pub fn decode_xml(bytes: &[u8], lossy: bool) -> Result<(Cow<'_, str>, DecodeReport), String> {
+    let (encoding, bom_len, unknown_label) = detect_encoding(bytes);
+    decode(bytes, encoding, bom_len, unknown_label, lossy)
+}
+
+/// Decodes the raw bytes of a text survey export to UTF-8.
+///
+/// The encoding is taken from the BOM, then from `declared` (e.g. the `encoding` command of a
+/// Therion file). Otherwise, text which is not valid UTF-8 is read as Windows-1252, the code
+/// page of the Windows tools most exports come from. `lossy` is as in [`decode_xml`].
+pub fn decode_text<'a>(
+    bytes: &'a [u8],
+    declared: Option<String>,
+    lossy: bool,
+) -> Result<(Cow<'a, str>, DecodeReport), String> {
+    let (encoding, bom_len, unknown_label) = match (bom_encoding(bytes), declared) {
+        (Some((encoding, bom_len)), _) => (encoding, bom_len, None),
+        (None, Some(label)) => declared_label(label),
+        (None, None) if std::str::from_utf8(bytes).is_ok() => (XmlEncoding::Utf8, 0, None),
+        (None, None) => (XmlEncoding::Windows1252, 0, None),
+    };
+    decode(bytes, encoding, bom_len, unknown_label, lossy)
+}
+
+fn decode(
+    bytes: &[u8],
+    encoding: XmlEncoding,
+    bom_len: usize,
+    unknown_label: Option<String>,
+    lossy: bool,
+) -> Result<(Cow<'_, str>, DecodeReport), String> {
+    if let Some(label) = &unknown_label {
+        if !lossy {
+            return Err(format!("Unsupported encoding: `{label}`"));
+        }
+    }
+    let content = &bytes[bom_len..];
+
+    let mut replacements = Vec::new();
+    let text = match encoding {
+        XmlEncoding::Utf8 => decode_utf8(content, bom_len, lossy, &mut replacements)?,
+        XmlEncoding::Utf16Le | XmlEncoding::Utf16Be => Cow::Owned(decode_utf16(
+            content,
+            encoding == XmlEncoding::Utf16Be,
+            bom_len,
+            lossy,
+            &mut replacements,
+        )?),
+        XmlEncoding::Windows1252 | XmlEncoding::Iso8859_15 => {
+            Cow::Owned(decode_single_byte(content, encoding))
+        }
+    };
+
+    let report = DecodeReport {
+        encoding,
+        unknown_label,
+        replacements,
+    };
+    Ok((text, report))
+}
+
+/// Same as [`decode_xml`] for an owned buffer, reusing it for valid UTF-8 documents.
+pub fn decode_xml_owned(bytes: Vec<u8>, lossy: bool) -> Result<(String, DecodeReport), String> {
+    decode_owned(bytes, |bytes| decode_xml(bytes, lossy))
+}
+
+/// Same as [`decode_text`] for an owned buffer, reusing it for valid UTF-8 text.
+pub fn decode_text_owned(
+    bytes: Vec<u8>,
+    declared: Option<String>,
+    lossy: bool,
+) -> Result<(String, DecodeReport), String> {
+    decode_owned(bytes, |bytes| decode_text(bytes, declared, lossy))
+}
+
+/// Decodes an owned buffer with `decode`, reusing it when the text borrows from it.
+fn decode_owned(
+    bytes: Vec<u8>,
+    decode: impl FnOnce(&[u8]) -> Result<(Cow<'_, str>, DecodeReport), String>,
+) -> Result<(String, DecodeReport), String> {
+    let (text, report) = decode(&bytes)?;
+    let bom_len = match text {
+        Cow::Owned(text) => return Ok((text, report)),
+        Cow::Borrowed(text) => bytes.len() - text.len(),
+    };
+
+    let mut bytes = bytes;
+    bytes.drain(..bom_len);
+    // Safety: `decode` only borrows the bytes once validated as UTF-8
+    Ok((unsafe { String::from_utf8_unchecked(bytes) }, report))
+}
+
+fn decode_utf8<'a>(
+    content: &'a [u8],
+    offset: usize,
+    lossy: bool,
+    replacements: &mut Vec<Replacement>,
+) -> Result<Cow<'a, str>, String> {
+    let error = match std::str::from_utf8(content) {
+        Ok(text) => return Ok(Cow::Borrowed(text)),
+        Err(e) => e,
+    };
+    if !lossy {
+        return Err(format!(
+            "Invalid UTF-8 byte sequence at byte {}",
+            offset + error.valid_up_to()
+        ));
+    }
+
+    let mut text = String::with_capacity(content.len());
+    let mut pos = 0;
+    for chunk in content.utf8_chunks() {
+        text.push_str(chunk.valid());
+        pos += chunk.valid().len();
+        let invalid = chunk.invalid();
+        if !invalid.is_empty() {
+            text.push(char::REPLACEMENT_CHARACTER);
+            replacements.push(Replacement {
+                offset: offset + pos,
+                bytes: invalid.to_vec(),
+            });
+            pos += invalid.len();
+        }
+    }
+    Ok(Cow::Owned(text))
+}
+
+fn decode_utf16(
+    content: &[u8],
+    big_endian: bool,
+    offset: usize,
+    lossy: bool,
+    replacements: &mut Vec<Replacement>,
+) -> Result<String, String> {
+    let units = content.chunks_exact(2).map(|pair| {
+        if big_endian {
+            u16::from_be_bytes([pair[0], pair[1]])
+        } else {
+            u16::from_le_bytes([pair[0], pair[1]])
+        }
+    });
+
+    let mut text = String::with_capacity(content.len() / 2);
+    let mut pos = 0;
+    for decoded in char::decode_utf16(units) {
+        match decoded {
+            Ok(c) => {
+                text.push(c);
+                pos += 2 * c.len_utf16();
+            }
+            Err(e) => {
+                if !lossy {
+                    return Err(format!(
+                        "Unpaired UTF-16 surrogate 0x{:04X} at byte {}",
+                        e.unpaired_surrogate(),
+                        offset + pos
+                    ));
+                }
+                text.push(char::REPLACEMENT_CHARACTER);
+                replacements.push(Replacement {
+                    offset: offset + pos,
+                    bytes: content[pos..pos + 2].to_vec(),
+                });
+                pos += 2;
+            }
+        }
+    }
+
+    if content.len() % 2 == 1 {
+        if !lossy {
+            return Err("Truncated UTF-16 document: odd number of bytes".to_string());
+        }
+        text.push(char::REPLACEMENT_CHARACTER);
+        replacements.push(Replacement {
+            offset: offset + content.len() - 1,
+            bytes: content[content.len() - 1..].to_vec(),
+        });
+    }
+    Ok(text)
+}
+
+fn decode_single_byte(content: &[u8], encoding: XmlEncoding) -> String {
+    content
+        .iter()
+        .map(|&b| match (encoding, b) {
+            (_, 0x00..=0x7F) => b as char,
+            (XmlEncoding::Windows1252, 0x80..=0x9F) => WINDOWS_1252_HIGH[(b - 0x80) as usize],
+            (XmlEncoding::Iso8859_15, 0xA4) => '\u{20AC}',
+            (XmlEncoding::Iso8859_15, 0xA6) => '\u{0160}',
+            (XmlEncoding::Iso8859_15, 0xA8) => '\u{0161}',
+            (XmlEncoding::Iso8859_15, 0xB4) => '\u{017D}',
+            (XmlEncoding::Iso8859_15, 0xB8) => '\u{017E}',
+            (XmlEncoding::Iso8859_15, 0xBC) => '\u{0152}',
+            (XmlEncoding::Iso8859_15, 0xBD) => '\u{0153}',
+            (XmlEncoding::Iso8859_15, 0xBE) => '\u{0178}',
+            // Latin-1 maps the remaining bytes to the code point of the same value
+            _ => b as char,
+        })
+        .collect()
+}
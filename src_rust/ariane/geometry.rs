@@ -0,0 +1,103 @@
+use ahash::AHashMap;
+use serde_json::{Map, Value};
+use std::collections::VecDeque;
+
+use super::survey;
+
+/// Position of a station relative to the survey start, in the survey unit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Position {
+    /// Towards the east.
+    pub x: f64,
+    /// Towards the north.
+    pub y: f64,
+    /// Below the reference level, as recorded by Ariane.
+    pub depth: f64,
+}
+
+/// East, north and depth offsets of a shot from its origin station.
+fn shot_vector(shot: &Map<String, Value>, from: &Position) -> Option<(f64, f64, f64)> {
+    let number = |field| shot.get(field).and_then(survey::scalar_f64);
+    let length = number("Length").unwrap_or(0.0);
+    if length == 0.0 {
+        // Closure shots only link two stations and carry no measurement
+        return Some((0.0, 0.0, 0.0));
+    }
+
+    let (horizontal, dz) = match number("Depth") {
+        // Depth gauge readings take precedence over the inclination
+        Some(depth) => {
+            let dz = depth - from.depth;
+            ((length * length - dz * dz).max(0.0).sqrt(), dz)
+        }
+        None => {
+            let inclination = number("Inclination").unwrap_or(0.0).to_radians();
+            (length * inclination.cos(), -length * inclination.sin())
+        }
+    };
+    let azimuth = number("Azimut")?.to_radians();
+    Some((horizontal * azimuth.sin(), horizontal * azimuth.cos(), dz))
+}
+
+/// Positions of the stations reached from a start shot (`FromID` of `-1`) or from a shot whose
+/// origin station is missing. Each such root is placed at the origin, at its recorded depth.
+pub fn station_positions(shots: &[&Map<String, Value>]) -> AHashMap<String, Position> {
+    let mut children: AHashMap<String, Vec<usize>> = AHashMap::new();
+    let ids: Vec<Option<String>> = shots
+        .iter()
+        .map(|shot| survey::station_id(shot, "ID"))
+        .collect();
+    let stations: ahash::AHashSet<&str> = ids.iter().filter_map(|id| id.as_deref()).collect();
+
+    let mut positions: AHashMap<String, Position> = AHashMap::new();
+    let mut queue: VecDeque<String> = VecDeque::new();
+    for (index, shot) in shots.iter().enumerate() {
+        let Some(id) = &ids[index] else {
+            continue;
+        };
+        match survey::station_id(shot, "FromID") {
+            Some(from) if stations.contains(from.as_str()) => {
+                children.entry(from).or_default().push(index);
+            }
+            _ => {
+                if positions.contains_key(id) {
+                    continue;
+                }
+                let depth = shot.get("Depth").and_then(survey::scalar_f64);
+                let position = Position {
+                    x: 0.0,
+                    y: 0.0,
+                    depth: depth.unwrap_or(0.0),
+                };
+                positions.insert(id.clone(), position);
+                queue.push_back(id.clone());
+            }
+        }
+    }
+
+    while let Some(station) = queue.pop_front() {
+        let Some(indices) = children.get(&station) else {
+            continue;
+        };
+        let from = positions[&station];
+        for &index in indices {
+            let Some(id) = &ids[index] else {
+                continue;
+            };
+            if positions.contains_key(id) {
+                continue;
+            }
+            let Some((dx, dy, dz)) = shot_vector(shots[index], &from) else {
+                continue;
+            };
+            let position = Position {
+                x: from.x + dx,
+                y: from.y + dy,
+                depth: from.depth + dz,
+            };
+            positions.insert(id.clone(), position);
+            queue.push_back(id.clone());
+        }
+    }
+    positions
+}
@@ -0,0 +1,274 @@
+use ahash::AHashMap;
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+use pythonize::depythonize;
+use quick_xml::escape::partial_escape;
+use quick_xml::events::{BytesDecl, BytesText, Event};
+use quick_xml::Writer;
+use serde_json::{Map, Value};
+use std::io::{BufWriter, Write};
+
+use pyo3_stub_gen::derive::gen_stub_pyfunction;
+
+use super::projection::{self, Geodetic};
+use super::serialize::xml_write_error;
+use super::{declination, survey, units};
+
+const GPX_NAMESPACE: &str = "http://www.topografix.com/GPX/1/1";
+
+/// A named point with its elevation in meters.
+struct Waypoint {
+    position: Geodetic,
+    name: String,
+    description: Option<String>,
+}
+
+/// Traverse of a `Section`, split where it branches off a station other than the last one.
+struct Track {
+    name: String,
+    segments: Vec<Vec<Geodetic>>,
+}
+
+/// Shots with non-zero `Latitude`/`Longitude` (entrances and surface fixes), in document order.
+fn waypoints(cave: &Map<String, Value>) -> Result<Vec<Waypoint>, String> {
+    let meters = units::survey_unit(cave)?.meters();
+    let elevation = cave
+        .get("firstStartAbsoluteElevation")
+        .and_then(survey::scalar_f64)
+        .unwrap_or(0.0);
+
+    let mut waypoints = Vec::new();
+    for shot in survey::shots(cave) {
+        let Some((latitude, longitude)) = survey::shot_location(shot) else {
+            continue;
+        };
+        let depth = shot
+            .get("Depth")
+            .and_then(survey::scalar_f64)
+            .unwrap_or(0.0);
+        let id = survey::station_id(shot, "ID").unwrap_or_default();
+        let name = survey::field_str(shot, "Name")
+            .filter(|name| !name.trim().is_empty())
+            .map_or_else(|| format!("Station {id}"), |name| name.into_owned());
+        let description = survey::field_str(shot, "Comment")
+            .filter(|comment| !comment.trim().is_empty() && *comment != name)
+            .map(|comment| comment.into_owned());
+        waypoints.push(Waypoint {
+            position: Geodetic {
+                latitude,
+                longitude,
+                altitude: (elevation - depth) * meters,
+            },
+            name,
+            description,
+        });
+    }
+    Ok(waypoints)
+}
+
+/// One track per `Section`, in order of first appearance. Closure shots are left out.
+fn tracks(cave: &Map<String, Value>, stations: &AHashMap<String, Geodetic>) -> Vec<Track> {
+    let mut tracks: Vec<Track> = Vec::new();
+    let mut last_station: Vec<Option<String>> = Vec::new();
+    for shot in survey::shots(cave) {
+        if survey::field_str(shot, "Type").is_some_and(|kind| kind == "CLOSURE") {
+            continue;
+        }
+        let (Some(from), Some(to)) = (
+            survey::station_id(shot, "FromID").and_then(|id| Some((stations.get(&id)?, id))),
+            survey::station_id(shot, "ID").and_then(|id| Some((stations.get(&id)?, id))),
+        ) else {
+            continue;
+        };
+
+        let name = survey::field_str(shot, "Section").unwrap_or_default();
+        let index = match tracks.iter().position(|track| track.name == name) {
+            Some(index) => index,
+            None => {
+                tracks.push(Track {
+                    name: name.into_owned(),
+                    segments: Vec::new(),
+                });
+                last_station.push(None);
+                tracks.len() - 1
+            }
+        };
+        let track = &mut tracks[index];
+        if last_station[index].as_ref() != Some(&from.1) {
+            track.segments.push(vec![*from.0]);
+        }
+        if let Some(segment) = track.segments.last_mut() {
+            segment.push(*to.0);
+        }
+        last_station[index] = Some(to.1);
+    }
+    tracks
+}
+
+/// Text content, escaping only `<`, `>` and `&` as the XML writer does.
+fn text(content: &str) -> BytesText<'_> {
+    BytesText::from_escaped(partial_escape(content))
+}
+
+fn write_point<W: Write>(
+    writer: &mut Writer<W>,
+    element: &str,
+    position: &Geodetic,
+    name: Option<&str>,
+    description: Option<&str>,
+) -> std::io::Result<()> {
+    let latitude = format!("{:.8}", position.latitude);
+    let longitude = format!("{:.8}", position.longitude);
+    writer
+        .create_element(element)
+        .with_attributes([("lat", latitude.as_str()), ("lon", longitude.as_str())])
+        .write_inner_content(|writer| {
+            writer
+                .create_element("ele")
+                .write_text_content(text(&format!("{:.2}", position.altitude)))?;
+            if let Some(name) = name {
+                writer
+                    .create_element("name")
+                    .write_text_content(text(name))?;
+            }
+            if let Some(description) = description {
+                writer
+                    .create_element("desc")
+                    .write_text_content(text(description))?;
+            }
+            Ok(())
+        })?;
+    Ok(())
+}
+
+/// Content of a GPX document built from a survey.
+pub struct Gpx {
+    name: Option<String>,
+    waypoints: Vec<Waypoint>,
+    tracks: Vec<Track>,
+    /// Warnings of the correction to true north of the station positions.
+    pub warnings: Vec<String>,
+}
+
+impl Gpx {
+    /// Located shots become waypoints and each `Section` a track of its stations. Elevations
+    /// are `firstStartAbsoluteElevation` minus the station `Depth`, in meters. Station
+    /// positions come from `projection::station_geodetic`, placed from `location` when no shot
+    /// is located.
+    pub fn from_document(document: &Value, location: Option<(f64, f64)>) -> Result<Self, String> {
+        let cave = survey::cave_file(document)?;
+        let georeference = projection::station_geodetic(document, location)?;
+        let stations: AHashMap<String, Geodetic> = georeference.stations.into_iter().collect();
+        Ok(Gpx {
+            name: survey::field_str(cave, "caveName").map(|name| name.into_owned()),
+            waypoints: waypoints(cave)?,
+            tracks: tracks(cave, &stations),
+            warnings: georeference.warnings,
+        })
+    }
+
+    /// Writes the GPX 1.1 document to `out`, which is handed back once everything is written.
+    pub fn write<W: Write>(&self, out: W) -> std::io::Result<W> {
+        let mut writer = Writer::new_with_indent(out, b' ', 2);
+        writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+        writer
+            .create_element("gpx")
+            .with_attributes([
+                ("version", "1.1"),
+                ("creator", "openspeleo_core"),
+                ("xmlns", GPX_NAMESPACE),
+            ])
+            .write_inner_content(|writer| {
+                if let Some(name) = &self.name {
+                    writer
+                        .create_element("metadata")
+                        .write_inner_content(|writer| {
+                            writer
+                                .create_element("name")
+                                .write_text_content(text(name))?;
+                            Ok(())
+                        })?;
+                }
+                for waypoint in &self.waypoints {
+                    write_point(
+                        writer,
+                        "wpt",
+                        &waypoint.position,
+                        Some(&waypoint.name),
+                        waypoint.description.as_deref(),
+                    )?;
+                }
+                for track in &self.tracks {
+                    writer.create_element("trk").write_inner_content(|writer| {
+                        if !track.name.is_empty() {
+                            writer
+                                .create_element("name")
+                                .write_text_content(text(&track.name))?;
+                        }
+                        for segment in &track.segments {
+                            writer
+                                .create_element("trkseg")
+                                .write_inner_content(|writer| {
+                                    for point in segment {
+                                        write_point(writer, "trkpt", point, None, None)?;
+                                    }
+                                    Ok(())
+                                })?;
+                        }
+                        Ok(())
+                    })?;
+                }
+                Ok(())
+            })?;
+        let mut out = writer.into_inner();
+        out.write_all(b"\n")?;
+        Ok(out)
+    }
+}
+
+/// Converts a loaded survey to a GPX 1.1 document.
+///
+/// Shots with non-zero `Latitude`/`Longitude` (entrances and surface fixes) become waypoints
+/// and each `Section` a track, with elevations from `Depth`. Surveys without a located shot
+/// are placed at `latitude`/`longitude`. Magnetic azimuths are corrected to true north, or used
+/// as is with a `UserWarning` when no shot is dated.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (data, latitude = None, longitude = None))]
+pub fn dict_to_gpx_str(
+    py: Python<'_>,
+    data: &Bound<'_, PyDict>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+) -> PyResult<String> {
+    let location = projection::location(latitude, longitude)?;
+    let data: Value = depythonize(data)?;
+    let gpx = Gpx::from_document(&data, location).map_err(PyValueError::new_err)?;
+    declination::warn_correction(py, &gpx.warnings)?;
+    let buffer = gpx.write(Vec::new()).map_err(xml_write_error)?;
+    String::from_utf8(buffer)
+        .map_err(|e| PyValueError::new_err(format!("UTF-8 conversion error: {e}")))
+}
+
+/// Writes a loaded survey to a GPX 1.1 file at `path`. See `dict_to_gpx_str`.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (data, path, latitude = None, longitude = None))]
+pub fn dict_to_gpx_file(
+    py: Python<'_>,
+    data: &Bound<'_, PyDict>,
+    path: &str,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+) -> PyResult<()> {
+    let location = projection::location(latitude, longitude)?;
+    let data: Value = depythonize(data)?;
+    let gpx = Gpx::from_document(&data, location).map_err(PyValueError::new_err)?;
+    declination::warn_correction(py, &gpx.warnings)?;
+    let file = std::fs::File::create(path).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Failed to create file: {e}"))
+    })?;
+    let writer = BufWriter::with_capacity(65_536, file);
+    gpx.write(writer)
+        .and_then(|mut w| w.flush())
+        .map_err(xml_write_error)
+}
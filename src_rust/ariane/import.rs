@@ -0,0 +1,609 @@
+use ahash::AHashMap;
+use pyo3::prelude::*;
+use serde_json::{json, Map, Value};
+
+use pyo3_stub_gen::derive::gen_stub_pyfunction;
+
+use super::units::LengthUnit;
+use super::{declination, deserialize, encoding, loader, pockettopo, survey, therion, topodroid};
+
+/// Date, team and declination shared by the readings of a survey trip.
+#[derive(Clone, Debug, Default)]
+pub struct Trip {
+    pub date: Option<(i64, u32, u32)>,
+    pub team: Vec<String>,
+    /// Angle from true to magnetic north, east positive, in degrees. `None` when the compass
+    /// readings are kept magnetic.
+    pub declination: Option<f64>,
+}
+
+/// A shot as read from a survey file, with lengths in meters and angles in degrees.
+#[derive(Clone, Debug, Default)]
+pub struct Reading {
+    pub from: String,
+    /// Station reached by a leg, `None` for a splay.
+    pub to: Option<String>,
+    pub length: f64,
+    pub azimuth: f64,
+    pub inclination: f64,
+    /// Depth gauge readings at `from` and `to`, in meters below the surface.
+    pub depths: Option<(f64, f64)>,
+    /// Left, right, up and down passage dimensions at `from`.
+    pub lrud: [Option<f64>; 4],
+    pub comment: Option<String>,
+    pub section: String,
+    /// Index of the trip in `ImportedSurvey::trips`.
+    pub trip: usize,
+}
+
+/// A survey read from a text export, before it is laid out as an Ariane survey.
+#[derive(Clone, Debug)]
+pub struct ImportedSurvey {
+    pub name: String,
+    /// Unit the lengths are written in.
+    pub unit: LengthUnit,
+    pub trips: Vec<Trip>,
+    pub readings: Vec<Reading>,
+}
+
+/// Azimuth brought within `[0, 360)`.
+pub fn normalize_azimuth(azimuth: f64) -> f64 {
+    let azimuth = azimuth.rem_euclid(360.0);
+    if azimuth >= 360.0 {
+        0.0
+    } else {
+        azimuth
+    }
+}
+
+/// Merges a run of readings of the same leg (DistoX users shoot each leg several times).
+fn average(run: &[&Reading]) -> Reading {
+    let count = run.len() as f64;
+    let mean = |value: &dyn Fn(&Reading) -> f64| run.iter().map(|r| value(r)).sum::<f64>() / count;
+    let (sin, cos) = (
+        mean(&|r| r.azimuth.to_radians().sin()),
+        mean(&|r| r.azimuth.to_radians().cos()),
+    );
+    let depths = run.iter().all(|r| r.depths.is_some()).then(|| {
+        (
+            mean(&|r| r.depths.unwrap().0),
+            mean(&|r| r.depths.unwrap().1),
+        )
+    });
+    let mut lrud = [None; 4];
+    for (index, value) in lrud.iter_mut().enumerate() {
+        *value = run.iter().find_map(|r| r.lrud[index]);
+    }
+    Reading {
+        length: mean(&|r| r.length),
+        azimuth: normalize_azimuth(sin.atan2(cos).to_degrees()),
+        inclination: mean(&|r| r.inclination),
+        depths,
+        lrud,
+        comment: run.iter().find_map(|r| r.comment.clone()),
+        ..run[0].clone()
+    }
+}
+
+/// Readings with the runs of repeated legs averaged, splays left as they are.
+fn merge_repeated_legs(readings: &[Reading]) -> Vec<Reading> {
+    let mut merged = Vec::with_capacity(readings.len());
+    let mut run: Vec<&Reading> = Vec::new();
+    for reading in readings {
+        let repeated = run.last().is_some_and(|last| {
+            reading.to.is_some()
+                && last.to == reading.to
+                && last.from == reading.from
+                && last.trip == reading.trip
+        });
+        if !repeated && !run.is_empty() {
+            merged.push(average(&run));
+            run.clear();
+        }
+        run.push(reading);
+    }
+    if !run.is_empty() {
+        merged.push(average(&run));
+    }
+    merged
+}
+
+impl Reading {
+    /// The reading as measured from the other end of the shot.
+    pub fn reversed(&self) -> Reading {
+        Reading {
+            from: self.to.clone().unwrap_or_default(),
+            to: Some(self.from.clone()),
+            azimuth: normalize_azimuth(self.azimuth + 180.0),
+            inclination: -self.inclination,
+            depths: self.depths.map(|(from, to)| (to, from)),
+            ..self.clone()
+        }
+    }
+
+    /// The reading as a splay from its `from` station.
+    pub fn into_splay(self) -> Reading {
+        Reading { to: None, ..self }
+    }
+}
+
+/// Whether a station name stands for the unnamed end of a splay.
+pub fn is_anonymous(station: &str) -> bool {
+    matches!(station, "" | "-" | ".")
+}
+
+/// Position of a splay end in the cross-section of its station, towards the right and up, from
+/// the `bearing` of the splay relative to the station shot.
+fn cross_section(splay: &Reading, bearing: f64) -> (f64, f64) {
+    let (horizontal, up) = match splay.depths {
+        Some((from, to)) => {
+            let up = from - to;
+            ((splay.length * splay.length - up * up).max(0.0).sqrt(), up)
+        }
+        None => {
+            let inclination = splay.inclination.to_radians();
+            (
+                splay.length * inclination.cos(),
+                splay.length * inclination.sin(),
+            )
+        }
+    };
+    (horizontal * bearing.to_radians().sin(), up)
+}
+
+/// Cross-section Ariane writes for a shot without walls.
+const EMPTY_SECTION: &[(f64, f64)] = &[(0.0, 0.0), (180.0, 0.0), (90.0, 0.0), (270.0, 0.0)];
+
+/// Station created in the Ariane survey, with its depth in meters.
+#[derive(Clone, Copy)]
+struct Station {
+    shot: usize,
+    depth: f64,
+}
+
+/// Lays readings out as Ariane shots, one station per shot with `ID`s numbered from 0.
+struct Builder<'a> {
+    survey: &'a ImportedSurvey,
+    /// Meters to the survey unit.
+    scale: f64,
+    /// Whether the compass readings are kept relative to magnetic north.
+    magnetic: bool,
+    shots: Vec<Map<String, Value>>,
+    /// Azimuth of each shot, the first leg leaving the station for start shots.
+    azimuths: Vec<Option<f64>>,
+    stations: AHashMap<String, Station>,
+}
+
+impl<'a> Builder<'a> {
+    fn new(survey: &'a ImportedSurvey) -> Self {
+        Builder {
+            survey,
+            scale: 1.0 / survey.unit.meters(),
+            magnetic: survey.trips.iter().all(|trip| trip.declination.is_none()),
+            shots: Vec::new(),
+            azimuths: Vec::new(),
+            stations: AHashMap::new(),
+        }
+    }
+
+    fn length(&self, meters: f64) -> Value {
+        Value::String(survey::float_text(meters * self.scale))
+    }
+
+    /// Azimuth relative to the north the survey is written in.
+    fn azimuth(&self, reading: &Reading) -> f64 {
+        if self.magnetic {
+            return reading.azimuth;
+        }
+        let declination = self
+            .survey
+            .trips
+            .get(reading.trip)
+            .and_then(|trip| trip.declination)
+            .unwrap_or(0.0);
+        normalize_azimuth(reading.azimuth + declination)
+    }
+
+    /// Appends a shot ending at a new station and returns its index, which is also its `ID`.
+    #[allow(clippy::too_many_arguments)]
+    fn push(
+        &mut self,
+        kind: &str,
+        from: Option<usize>,
+        closure_to: Option<usize>,
+        name: &str,
+        reading: &Reading,
+        measured: bool,
+        depth: f64,
+    ) -> usize {
+        let id = self.shots.len();
+        let trip = self.survey.trips.get(reading.trip);
+        let azimuth = measured.then(|| self.azimuth(reading));
+        let length = if measured { reading.length } else { 0.0 };
+        let inclination = if measured { reading.inclination } else { 0.0 };
+        let station = |index: Option<usize>| index.map_or("-1".to_string(), |i| i.to_string());
+
+        let mut shot = Map::new();
+        shot.insert(
+            "Azimut".to_string(),
+            Value::String(survey::float_text(azimuth.unwrap_or(0.0))),
+        );
+        shot.insert(
+            "ClosureToID".to_string(),
+            Value::String(station(closure_to)),
+        );
+        shot.insert("Color".to_string(), json!("0xffffffff"));
+        if let Some(comment) = reading.comment.as_ref().filter(|_| measured) {
+            shot.insert("Comment".to_string(), Value::String(comment.clone()));
+        }
+        if let Some((year, month, day)) = trip.and_then(|trip| trip.date) {
+            shot.insert(
+                "Date".to_string(),
+                Value::String(format!("{year:04}-{month:02}-{day:02}")),
+            );
+        }
+        shot.insert("Depth".to_string(), self.length(depth));
+        let depth_in = if kind == "START" { 0.0 } else { -1.0 };
+        shot.insert(
+            "DepthIn".to_string(),
+            Value::String(survey::float_text(depth_in)),
+        );
+        shot.insert("Down".to_string(), json!("0.0"));
+        shot.insert("Excluded".to_string(), json!("false"));
+        if let Some(trip) = trip.filter(|trip| !trip.team.is_empty()) {
+            shot.insert("Explorer".to_string(), Value::String(trip.team.join(", ")));
+        }
+        shot.insert("FromID".to_string(), Value::String(station(from)));
+        shot.insert("ID".to_string(), Value::String(id.to_string()));
+        shot.insert(
+            "Inclination".to_string(),
+            Value::String(survey::float_text(inclination)),
+        );
+        shot.insert("Latitude".to_string(), json!("0.0"));
+        shot.insert("Left".to_string(), json!("0.0"));
+        shot.insert("Length".to_string(), self.length(length));
+        shot.insert(
+            "Locked".to_string(),
+            Value::String((kind == "START").to_string()),
+        );
+        shot.insert("Longitude".to_string(), json!("0.0"));
+        shot.insert("Name".to_string(), Value::String(name.to_string()));
+        shot.insert("Profiletype".to_string(), json!("VERTICAL"));
+        shot.insert("Right".to_string(), json!("0.0"));
+        shot.insert(
+            "Section".to_string(),
+            Value::String(reading.section.clone()),
+        );
+        shot.insert("Shape".to_string(), self.shape(EMPTY_SECTION));
+        shot.insert("Type".to_string(), Value::String(kind.to_string()));
+        shot.insert("Up".to_string(), json!("0.0"));
+
+        if let Some(from) = from.filter(|_| measured) {
+            if self.azimuths[from].is_none() {
+                self.azimuths[from] = azimuth;
+            }
+        }
+        self.shots.push(shot);
+        self.azimuths.push(azimuth.filter(|_| kind != "START"));
+        id
+    }
+
+    /// Cross-section of a shot from `(angle, length)` pairs, angles clockwise from up when
+    /// looking along the shot.
+    fn shape(&self, radii: &[(f64, f64)]) -> Value {
+        let radii: Vec<Value> = radii
+            .iter()
+            .map(|(angle, length)| {
+                json!({
+                    "angle": survey::float_text(*angle),
+                    "length": self.length(*length),
+                    "TensionCorridor": "1.0",
+                    "TensionProfile": "1.0",
+                })
+            })
+            .collect();
+        json!({
+            "hasProfileAzimut": "false",
+            "hasProfileTilt": "false",
+            "profileAzimut": "0.0",
+            "profileTilt": "0.0",
+            "RadiusCollection": {"RadiusVector": radii},
+        })
+    }
+
+    /// Adds a start shot for a station not reached by any previous leg.
+    fn start(&mut self, name: &str, reading: &Reading) -> Station {
+        let depth = reading.depths.map_or(0.0, |(from, _)| from);
+        let shot = self.push("START", None, None, name, reading, false, depth);
+        let station = Station { shot, depth };
+        self.stations.insert(name.to_string(), station);
+        station
+    }
+
+    fn leg(&mut self, reading: &Reading) {
+        let Some(to) = reading.to.as_deref() else {
+            return;
+        };
+        let reversed;
+        let reading =
+            if !self.stations.contains_key(&reading.from) && self.stations.contains_key(to) {
+                reversed = reading.reversed();
+                &reversed
+            } else {
+                reading
+            };
+        let to = reading.to.as_deref().unwrap_or_default();
+        let from = match self.stations.get(&reading.from) {
+            Some(station) => *station,
+            None => self.start(&reading.from, reading),
+        };
+        let depth = match reading.depths {
+            Some((_, to)) => to,
+            None => from.depth - reading.length * reading.inclination.to_radians().sin(),
+        };
+        let shot = self.push("REAL", Some(from.shot), None, to, reading, true, depth);
+        match self.stations.get(to) {
+            // Loop closure: the leg ends on a new station tied to the existing one
+            Some(existing) => {
+                let existing = existing.shot;
+                self.push(
+                    "CLOSURE",
+                    Some(shot),
+                    Some(existing),
+                    to,
+                    reading,
+                    false,
+                    depth,
+                );
+            }
+            None => {
+                self.stations
+                    .insert(to.to_string(), Station { shot, depth });
+            }
+        }
+    }
+
+    /// Sets the LRUD and the cross-section of the stations with splays or passage dimensions.
+    fn walls(&mut self, readings: &[Reading]) {
+        let mut splays: AHashMap<usize, Vec<&Reading>> = AHashMap::new();
+        let mut lruds: AHashMap<usize, [Option<f64>; 4]> = AHashMap::new();
+        for reading in readings {
+            let station = match self.stations.get(&reading.from) {
+                Some(station) => *station,
+                None => self.start(&reading.from, reading),
+            };
+            if reading.to.is_none() {
+                splays.entry(station.shot).or_default().push(reading);
+            }
+            if reading.lrud.iter().any(Option::is_some) {
+                let lrud = lruds.entry(station.shot).or_default();
+                for (value, read) in lrud.iter_mut().zip(reading.lrud) {
+                    *value = read.or(*value);
+                }
+            }
+        }
+
+        let mut walled: Vec<usize> = splays.keys().chain(lruds.keys()).copied().collect();
+        walled.sort_unstable();
+        walled.dedup();
+        for shot in walled {
+            let reference = self.azimuths[shot].unwrap_or(0.0);
+            let sections: Vec<(f64, f64)> = splays
+                .get(&shot)
+                .map(|splays| {
+                    splays
+                        .iter()
+                        .map(|splay| cross_section(splay, self.azimuth(splay) - reference))
+                        .collect()
+                })
+                .unwrap_or_default();
+            let from_splays = |pick: &dyn Fn(&(f64, f64)) -> f64| {
+                sections.iter().map(pick).fold(0.0_f64, f64::max)
+            };
+            let read = lruds.get(&shot).copied().unwrap_or_default();
+            let [left, right, up, down] = [
+                read[0].unwrap_or_else(|| from_splays(&|(right, _)| -right)),
+                read[1].unwrap_or_else(|| from_splays(&|(right, _)| *right)),
+                read[2].unwrap_or_else(|| from_splays(&|(_, up)| *up)),
+                read[3].unwrap_or_else(|| from_splays(&|(_, up)| -up)),
+            ];
+            let radii: Vec<(f64, f64)> = if sections.is_empty() {
+                vec![(0.0, up), (180.0, down), (90.0, right), (270.0, left)]
+            } else {
+                sections
+                    .iter()
+                    .map(|(right, up)| {
+                        (
+                            normalize_azimuth(right.atan2(*up).to_degrees()),
+                            right.hypot(*up),
+                        )
+                    })
+                    .collect()
+            };
+
+            let shape = self.shape(&radii);
+            let values = [
+                ("Left", self.length(left)),
+                ("Right", self.length(right)),
+                ("Up", self.length(up)),
+                ("Down", self.length(down)),
+                ("Shape", shape),
+            ];
+            for (field, value) in values {
+                self.shots[shot].insert(field.to_string(), value);
+            }
+        }
+    }
+}
+
+impl ImportedSurvey {
+    /// Warning for a survey whose trips do not all set a declination. The azimuths of the trips
+    /// without one are written as they were read, as if already relative to true north.
+    pub fn declination_warning(&self) -> Option<String> {
+        let mut used = vec![false; self.trips.len()];
+        for reading in &self.readings {
+            if let Some(used) = used.get_mut(reading.trip) {
+                *used = true;
+            }
+        }
+        let trips: Vec<(usize, &Trip)> = self
+            .trips
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| used[*index])
+            .collect();
+        let unset: Vec<String> = trips
+            .iter()
+            .filter(|(_, trip)| trip.declination.is_none())
+            .map(|(index, trip)| match trip.date {
+                Some((year, month, day)) => {
+                    format!("{} ({year:04}-{month:02}-{day:02})", index + 1)
+                }
+                None => (index + 1).to_string(),
+            })
+            .collect();
+        if unset.is_empty() || unset.len() == trips.len() {
+            return None;
+        }
+        Some(format!(
+            "Trip(s) {} set no declination while other trips do: their azimuths are taken as \
+             relative to true north, uncorrected",
+            unset.join(", ")
+        ))
+    }
+
+    /// Lays the survey out the way `load_ariane_tml_file_to_dict` returns an Ariane survey.
+    ///
+    /// Repeated legs are averaged and each station becomes the shot ending there, named after
+    /// it; a leg closing a loop ends on a new station tied back by a `CLOSURE` shot. Splays give
+    /// the cross-section (`RadiusVector`s) and the LRUD of their station, unless the file has
+    /// passage dimensions. Depths come from the depth gauge, or from the inclinations.
+    pub fn to_document(&self) -> Result<Value, String> {
+        if self.readings.is_empty() {
+            return Err("The file has no shot".to_string());
+        }
+        let readings = merge_repeated_legs(&self.readings);
+        let mut builder = Builder::new(self);
+        for reading in &readings {
+            builder.leg(reading);
+        }
+        builder.walls(&readings);
+
+        Ok(json!({
+            "CaveFile": {
+                "caveName": self.name,
+                "firstStartAbsoluteElevation": "0.0",
+                "unit": self.unit.code(),
+                "useMagneticAzimuth": builder.magnetic.to_string(),
+                "Data": {"SurveyData": builder.shots},
+            }
+        }))
+    }
+}
+
+/// Parses a `YYYY-MM-DD` date, also accepting `.` and `/` as separators.
+pub fn parse_date(text: &str) -> Option<(i64, u32, u32)> {
+    survey::parse_date(&text.trim().replace(['.', '/'], "-"))
+}
+
+/// Reads the encoding a text export declares, for the formats which have a way to.
+type DeclaredEncoding = fn(&[u8]) -> Option<String>;
+
+/// Reads a text export and lays it out as an Ariane survey, named after the file by default.
+fn load_text_file(
+    py: Python<'_>,
+    path: &str,
+    lossy: bool,
+    unit: Option<&str>,
+    north: Option<&str>,
+    parse: fn(&str, &str) -> Result<ImportedSurvey, String>,
+    declared_encoding: Option<DeclaredEncoding>,
+) -> PyResult<Py<PyAny>> {
+    let conversions = loader::Conversions::parse(unit, north)?;
+    let bytes = std::fs::read(path).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Failed to open file: {e}"))
+    })?;
+    let declared = declared_encoding.and_then(|declared| declared(&bytes));
+    let (text, report) = encoding::decode_text_owned(bytes, declared, lossy).map_err(|e| {
+        pyo3::exceptions::PyValueError::new_err(format!(
+            "Text decoding error: {e}. Pass `lossy=True` to replace undecodable bytes."
+        ))
+    })?;
+    deserialize::warn_decode_report(py, &report)?;
+
+    let name = std::path::Path::new(path)
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+    let survey = parse(&text, &name).map_err(pyo3::exceptions::PyValueError::new_err)?;
+    declination::warn_correction(py, survey.declination_warning().as_slice())?;
+    let mut value = survey
+        .to_document()
+        .map_err(pyo3::exceptions::PyValueError::new_err)?;
+    conversions.apply(&mut value)?;
+    deserialize::value_to_py(py, value, None)
+}
+
+/// Loads a PocketTopo text export (`.txt`) as an Ariane survey.
+///
+/// Legs become `SurveyData` shots named after their station, splays give the LRUD and the
+/// `RadiusVector`s of their station, and each `TRIP` its `Date`. Azimuths are corrected by the
+/// trip `DECLINATION` when one is set, otherwise they stay magnetic; a `UserWarning` is emitted
+/// when only some trips set one. Files which are not valid
+/// UTF-8 are read as Windows-1252. `lossy`, `unit` and `north` are as in
+/// `load_ariane_tml_file_to_dict`.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (path, lossy = false, unit = None, north = None))]
+pub fn load_pockettopo_txt_file_to_dict(
+    py: Python<'_>,
+    path: &str,
+    lossy: bool,
+    unit: Option<&str>,
+    north: Option<&str>,
+) -> PyResult<Py<PyAny>> {
+    load_text_file(py, path, lossy, unit, north, pockettopo::parse, None)
+}
+
+/// Loads a TopoDroid CSV export as an Ariane survey.
+///
+/// The `#` header gives the survey name, `Date`, `Explorer` team, declination and units. See
+/// `load_pockettopo_txt_file_to_dict` for the layout of the shots.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (path, lossy = false, unit = None, north = None))]
+pub fn load_topodroid_csv_file_to_dict(
+    py: Python<'_>,
+    path: &str,
+    lossy: bool,
+    unit: Option<&str>,
+    north: Option<&str>,
+) -> PyResult<Py<PyAny>> {
+    load_text_file(py, path, lossy, unit, north, topodroid::parse, None)
+}
+
+/// Loads a Therion centerline file (`.th`, as exported by TopoDroid) as an Ariane survey.
+///
+/// Each `centerline` is a trip giving its `date`, `team` and `declination` to its shots and
+/// each `survey` a `Section`. `normal` and `diving` data are supported; `equate`s are not
+/// followed, so equated surveys get their own start. The file is decoded as its `encoding`
+/// command says. See `load_pockettopo_txt_file_to_dict` for the layout of the shots.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (path, lossy = false, unit = None, north = None))]
+pub fn load_therion_th_file_to_dict(
+    py: Python<'_>,
+    path: &str,
+    lossy: bool,
+    unit: Option<&str>,
+    north: Option<&str>,
+) -> PyResult<Py<PyAny>> {
+    load_text_file(
+        py,
+        path,
+        lossy,
+        unit,
+        north,
+        therion::parse,
+        Some(therion::declared_encoding),
+    )
+}
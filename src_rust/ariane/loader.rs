@@ -2,31 +2,132 @@ use pyo3::prelude::*;
 
 use pyo3_stub_gen::derive::gen_stub_pyfunction;
 
-use super::deserialize;
+use pyo3::exceptions::PyValueError;
+use serde_json::Value;
+
+use super::{declination, deserialize, encoding, units};
+use crate::mapping::key_mapping::KeyMapping;
 
 /// Reads the contents of the "Data.xml" file from a zip archive.
 ///
+/// Use `load_ariane_file_to_dict` to read TMLU files (uncompressed XML) as well.
+///
 /// # Arguments
 ///
 /// * `path`: The path to the zip archive.
+/// * `keep_comments`: Keep XML comments under the reserved `#comment` key, with their position.
+/// * `keep_processing_instructions`: Keep processing instructions under the reserved `#pi` key.
+/// * `lossy`: Replace undecodable bytes instead of failing, with a `UnicodeWarning` listing them.
+/// * `mapping`: `KeyMapping` applied to element and attribute names while loading.
+/// * `unit`: Convert lengths and depths to this unit (`m` or `ft`), see `convert_units`.
+/// * `north`: Make the azimuths relative to this north (`true` or `magnetic`), see
+///   `correct_declination`.
 ///
 /// # Returns
 ///
 /// The contents of the "Data.xml" file as a string.
 #[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
 #[pyfunction]
-pub fn load_ariane_tml_file_to_dict(path: &str) -> PyResult<Py<PyAny>> {
-    let file = std::fs::File::open(path).map_err(|e| {
-        PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Failed to open file: {e}"))
-    })?;
-    // Use larger buffer for better I/O performance (64KiB instead of default 8KiB)
-    let reader = std::io::BufReader::with_capacity(65_536, file);
+#[pyo3(signature = (
+    path,
+    keep_comments = false,
+    keep_processing_instructions = false,
+    lossy = false,
+    mapping = None,
+    unit = None,
+    north = None,
+))]
+#[allow(clippy::too_many_arguments)]
+pub fn load_ariane_tml_file_to_dict(
+    py: Python<'_>,
+    path: &str,
+    keep_comments: bool,
+    keep_processing_instructions: bool,
+    lossy: bool,
+    mapping: Option<&Bound<'_, KeyMapping>>,
+    unit: Option<&str>,
+    north: Option<&str>,
+) -> PyResult<Py<PyAny>> {
+    let options = deserialize::ParseOptions {
+        keep_null: false,
+        keep_comments,
+        keep_processing_instructions,
+    };
+    let value = read_ariane_file(
+        py,
+        path,
+        Some(ArianeFormat::Tml),
+        &options,
+        lossy,
+        unit,
+        north,
+    )?;
+    deserialize::value_to_py(py, value, mapping)
+}
+
+/// Loads an Ariane file, either a TML file (a zip archive holding "Data.xml") or a TMLU file
+/// (the uncompressed XML). The format is told apart by the first bytes of the file, not by its
+/// extension.
+///
+/// Arguments are as in `load_ariane_tml_file_to_dict`.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (
+    path,
+    keep_comments = false,
+    keep_processing_instructions = false,
+    lossy = false,
+    mapping = None,
+    unit = None,
+    north = None,
+))]
+#[allow(clippy::too_many_arguments)]
+pub fn load_ariane_file_to_dict(
+    py: Python<'_>,
+    path: &str,
+    keep_comments: bool,
+    keep_processing_instructions: bool,
+    lossy: bool,
+    mapping: Option<&Bound<'_, KeyMapping>>,
+    unit: Option<&str>,
+    north: Option<&str>,
+) -> PyResult<Py<PyAny>> {
+    let options = deserialize::ParseOptions {
+        keep_null: false,
+        keep_comments,
+        keep_processing_instructions,
+    };
+    let value = read_ariane_file(py, path, None, &options, lossy, unit, north)?;
+    deserialize::value_to_py(py, value, mapping)
+}
+
+/// Format of an Ariane file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ArianeFormat {
+    /// Zip archive holding a "Data.xml" entry.
+    Tml,
+    /// The XML document itself.
+    Tmlu,
+}
+
+impl ArianeFormat {
+    /// Zip archives start with a local file header, or an end of central directory when empty.
+    pub(crate) fn detect(header: &[u8]) -> Self {
+        if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+            ArianeFormat::Tml
+        } else {
+            ArianeFormat::Tmlu
+        }
+    }
+}
 
+/// Reads the "Data.xml" entry of a TML archive.
+fn read_tml_data<R: std::io::Read + std::io::Seek>(reader: R) -> PyResult<Vec<u8>> {
     let mut archive = zip::ZipArchive::new(reader).map_err(|e| {
         PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Failed to open zip archive: {e}"))
     })?;
 
-    let mut xml_file = archive.by_name("Data.xml").map_err(|e| {
+    let mut xml_file = archive.by_name(super::archive::DATA_ENTRY).map_err(|e| {
         PyErr::new::<pyo3::exceptions::PyIOError, _>(format!(
             "Failed to find file in zip archive: {e}"
         ))
@@ -34,13 +135,89 @@ pub fn load_ariane_tml_file_to_dict(path: &str) -> PyResult<Py<PyAny>> {
 
     // Pre-allocate based on file size if available
     let file_size = xml_file.size() as usize;
-    let mut xml_contents = String::with_capacity(file_size);
+    let mut xml_bytes = Vec::with_capacity(file_size);
 
-    // Read directly into string to avoid Vec<u8> -> String conversion
-    std::io::Read::read_to_string(&mut xml_file, &mut xml_contents).map_err(|e| {
+    std::io::Read::read_to_end(&mut xml_file, &mut xml_bytes).map_err(|e| {
         PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Failed to read file: {e}"))
     })?;
+    Ok(xml_bytes)
+}
+
+/// Parses the XML document of a TML or TMLU file, converted to `unit` and `north` when given.
+///
+/// The file is read as `format`, or as the format detected from its first bytes when `None`.
+pub(crate) fn read_ariane_file(
+    py: Python<'_>,
+    path: &str,
+    format: Option<ArianeFormat>,
+    options: &deserialize::ParseOptions,
+    lossy: bool,
+    unit: Option<&str>,
+    north: Option<&str>,
+) -> PyResult<Value> {
+    let conversions = Conversions::parse(unit, north)?;
+
+    let file = std::fs::File::open(path).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Failed to open file: {e}"))
+    })?;
+    // Use larger buffer for better I/O performance (64KiB instead of default 8KiB)
+    let mut reader = std::io::BufReader::with_capacity(65_536, file);
+    let read_error = |e: std::io::Error| {
+        PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Failed to read file: {e}"))
+    };
+
+    let format = match format {
+        Some(format) => format,
+        None => ArianeFormat::detect(std::io::BufRead::fill_buf(&mut reader).map_err(read_error)?),
+    };
+    let xml_bytes = match format {
+        ArianeFormat::Tml => read_tml_data(reader)?,
+        ArianeFormat::Tmlu => {
+            let mut xml_bytes = Vec::new();
+            std::io::Read::read_to_end(&mut reader, &mut xml_bytes).map_err(read_error)?;
+            xml_bytes
+        }
+    };
+
+    // Older Ariane versions save Data.xml as ISO-8859-1, valid UTF-8 is reused without copy
+    let (xml_contents, report) =
+        encoding::decode_xml_owned(xml_bytes, lossy).map_err(deserialize::decode_error)?;
+    deserialize::warn_decode_report(py, &report)?;
+
+    let mut value = deserialize::parse_xml(&xml_contents, options)
+        .map_err(|e| PyValueError::new_err(format!("XML parsing error: {e}")))?;
+    conversions.apply(&mut value)?;
+    Ok(value)
+}
+
+/// The `unit` and `north` conversions applied by the loaders once a survey is read.
+pub(crate) struct Conversions {
+    unit: Option<units::LengthUnit>,
+    north: Option<declination::North>,
+}
+
+impl Conversions {
+    /// Checks the options before any file is read.
+    pub(crate) fn parse(unit: Option<&str>, north: Option<&str>) -> PyResult<Self> {
+        let unit = unit
+            .map(units::LengthUnit::parse)
+            .transpose()
+            .map_err(PyValueError::new_err)?;
+        let north = north
+            .map(declination::North::parse)
+            .transpose()
+            .map_err(PyValueError::new_err)?;
+        Ok(Conversions { unit, north })
+    }
 
-    // Convert XML to dict
-    deserialize::xml_str_to_dict(&xml_contents, false)
+    pub(crate) fn apply(&self, value: &mut Value) -> PyResult<()> {
+        if let Some(unit) = self.unit {
+            units::convert_document(value, unit).map_err(PyValueError::new_err)?;
+        }
+        if let Some(north) = self.north {
+            declination::correct_document(value, north, None, None)
+                .map_err(PyValueError::new_err)?;
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,394 @@
+use ahash::{AHashMap, AHashSet};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+use pythonize::{depythonize, pythonize};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pyfunction, gen_stub_pymethods};
+
+use super::{diff, survey};
+
+/// Fields of a shot holding a station identifier, rewritten when a shot is renumbered.
+const STATION_FIELDS: &[&str] = &["ID", "FromID", "ClosureToID"];
+
+/// A change made on both sides which could not be merged. The merged survey keeps our side.
+#[derive(Clone, Debug, Serialize)]
+pub struct MergeConflict {
+    /// `edit` when both sides changed a field differently, `edit_delete` when one side
+    /// removed an element the other one changed, `add` when both sides added a layer with the
+    /// same name but different content.
+    pub kind: &'static str,
+    /// `header`, `shot` or `layer`.
+    pub element: &'static str,
+    /// Shot `ID` or layer `name`, `None` for the header.
+    pub id: Option<String>,
+    /// Conflicting field, `None` when the whole element conflicts.
+    pub field: Option<String>,
+    pub base: Option<Value>,
+    pub ours: Option<Value>,
+    pub theirs: Option<Value>,
+}
+
+/// A shot added on their side whose `ID` was already taken by a shot added on ours.
+#[derive(Clone, Debug, Serialize)]
+pub struct Renumbering {
+    pub old: String,
+    pub new: String,
+}
+
+/// Result of a three-way merge: the merged survey, the conflicts and the renumbered shots.
+#[gen_stub_pyclass]
+#[pyclass(module = "openspeleo_core._rust_lib.ariane", frozen)]
+#[derive(Clone, Debug, Serialize)]
+pub struct SurveyMerge {
+    pub merged: Value,
+    pub conflicts: Vec<MergeConflict>,
+    pub renumbered: Vec<Renumbering>,
+}
+
+/// Elements of one kind, by matching key, in document order.
+type Keyed<'a> = Vec<(String, &'a Map<String, Value>)>;
+
+impl SurveyMerge {
+    pub fn compute(base: &Value, ours: &Value, theirs: &Value) -> Result<Self, String> {
+        let base_cave = survey::cave_file(base)?;
+        let our_cave = survey::cave_file(ours)?;
+        let their_cave = survey::cave_file(theirs)?;
+
+        let mut merge = SurveyMerge {
+            merged: Value::Null,
+            conflicts: Vec::new(),
+            renumbered: Vec::new(),
+        };
+
+        let mut cave = merge.merge_fields(
+            "header",
+            None,
+            base_cave,
+            our_cave,
+            their_cave,
+            diff::BODY_ELEMENTS,
+        );
+        let shots = merge.merge_shots(
+            &survey::shots(base_cave),
+            &survey::shots(our_cave),
+            &survey::shots(their_cave),
+        );
+        let layers = merge.merge_elements(
+            "layer",
+            &layers_by_name(survey::layers(base_cave)),
+            &layers_by_name(survey::layers(our_cave)),
+            &layers_by_name(survey::layers(their_cave)),
+        );
+
+        set_repeated(&mut cave, "Data", "SurveyData", shots);
+        set_repeated(&mut cave, "Layers", "layerList", layers);
+
+        merge.merged = match ours {
+            Value::Object(document) if document.contains_key("CaveFile") => {
+                let mut document = document.clone();
+                document.insert("CaveFile".to_string(), Value::Object(cave));
+                Value::Object(document)
+            }
+            _ => Value::Object(cave),
+        };
+        Ok(merge)
+    }
+
+    /// Renumbers the shots added on both sides with the same `ID`, then merges them.
+    fn merge_shots(
+        &mut self,
+        base: &[&Map<String, Value>],
+        ours: &[&Map<String, Value>],
+        theirs: &[&Map<String, Value>],
+    ) -> Vec<Map<String, Value>> {
+        let base_keys: AHashSet<String> = diff::shot_keys(base).into_iter().collect();
+        let our_shots: AHashMap<String, &Map<String, Value>> = diff::shot_keys(ours)
+            .into_iter()
+            .zip(ours.iter().copied())
+            .collect();
+
+        let mut next_id = [base, ours, theirs]
+            .iter()
+            .flat_map(|shots| shots.iter())
+            .flat_map(|shot| {
+                STATION_FIELDS
+                    .iter()
+                    .filter_map(|f| station_number(shot, f))
+            })
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let mut renumber: AHashMap<String, String> = AHashMap::new();
+        for (key, shot) in diff::shot_keys(theirs).into_iter().zip(theirs) {
+            if base_keys.contains(&key) || key.starts_with('#') {
+                continue;
+            }
+            let Some(our_shot) = our_shots.get(&key) else {
+                continue;
+            };
+            if !diff::maps_equal(our_shot, shot) {
+                self.renumbered.push(Renumbering {
+                    old: key.clone(),
+                    new: next_id.to_string(),
+                });
+                renumber.insert(key, next_id.to_string());
+                next_id += 1;
+            }
+        }
+
+        let theirs: Vec<Map<String, Value>> = theirs
+            .iter()
+            .map(|shot| {
+                let mut shot = (*shot).clone();
+                for field in STATION_FIELDS {
+                    let new = survey::station_id(&shot, field).and_then(|id| renumber.get(&id));
+                    if let Some(new) = new {
+                        shot.insert(field.to_string(), Value::String(new.clone()));
+                    }
+                }
+                shot
+            })
+            .collect();
+        self.merge_elements(
+            "shot",
+            &shots_by_id(base.to_vec()),
+            &shots_by_id(ours.to_vec()),
+            &shots_by_id(theirs.iter().collect()),
+        )
+    }
+
+    /// Three-way merge of matched elements. Our elements come first, in our order, followed by
+    /// the elements only added on their side.
+    fn merge_elements<'a>(
+        &mut self,
+        element: &'static str,
+        base: &Keyed<'a>,
+        ours: &Keyed<'a>,
+        theirs: &Keyed<'a>,
+    ) -> Vec<Map<String, Value>> {
+        let lookup = |items: &Keyed<'a>| -> AHashMap<String, &'a Map<String, Value>> {
+            items
+                .iter()
+                .map(|(key, item)| (key.clone(), *item))
+                .collect()
+        };
+        let (base_items, our_items, their_items) = (lookup(base), lookup(ours), lookup(theirs));
+
+        let mut merged = Vec::new();
+        for (key, our_item) in ours {
+            match (base_items.get(key), their_items.get(key)) {
+                (Some(base_item), Some(their_item)) => merged.push(self.merge_fields(
+                    element,
+                    Some(key),
+                    base_item,
+                    our_item,
+                    their_item,
+                    &[],
+                )),
+                (Some(base_item), None) => {
+                    if !diff::maps_equal(base_item, our_item) {
+                        self.whole_conflict(
+                            "edit_delete",
+                            element,
+                            key,
+                            Some(base_item),
+                            Some(our_item),
+                            None,
+                        );
+                        merged.push((*our_item).clone());
+                    }
+                }
+                (None, Some(their_item)) => {
+                    if !diff::maps_equal(our_item, their_item) {
+                        self.whole_conflict(
+                            "add",
+                            element,
+                            key,
+                            None,
+                            Some(our_item),
+                            Some(their_item),
+                        );
+                    }
+                    merged.push((*our_item).clone());
+                }
+                (None, None) => merged.push((*our_item).clone()),
+            }
+        }
+        for (key, their_item) in theirs {
+            if our_items.contains_key(key) {
+                continue;
+            }
+            match base_items.get(key) {
+                Some(base_item) => {
+                    if !diff::maps_equal(base_item, their_item) {
+                        self.whole_conflict(
+                            "edit_delete",
+                            element,
+                            key,
+                            Some(base_item),
+                            None,
+                            Some(their_item),
+                        );
+                    }
+                }
+                None => merged.push((*their_item).clone()),
+            }
+        }
+        merged
+    }
+
+    /// Three-way merge of the fields of one element. Fields in `skip` are taken from our side.
+    fn merge_fields(
+        &mut self,
+        element: &'static str,
+        id: Option<&str>,
+        base: &Map<String, Value>,
+        ours: &Map<String, Value>,
+        theirs: &Map<String, Value>,
+        skip: &[&str],
+    ) -> Map<String, Value> {
+        let mut merged = Map::new();
+        let keys = ours
+            .keys()
+            .chain(theirs.keys().filter(|key| !ours.contains_key(*key)));
+        for key in keys {
+            let (b, o, t) = (base.get(key), ours.get(key), theirs.get(key));
+            let value = if skip.contains(&key.as_str())
+                || diff::values_equal(b, t)
+                || diff::values_equal(o, t)
+            {
+                o
+            } else if diff::values_equal(b, o) {
+                t
+            } else {
+                self.conflicts.push(MergeConflict {
+                    kind: "edit",
+                    element,
+                    id: id.map(str::to_string),
+                    field: Some(key.clone()),
+                    base: b.cloned(),
+                    ours: o.cloned(),
+                    theirs: t.cloned(),
+                });
+                o
+            };
+            if let Some(value) = value {
+                merged.insert(key.clone(), value.clone());
+            }
+        }
+        merged
+    }
+
+    fn whole_conflict(
+        &mut self,
+        kind: &'static str,
+        element: &'static str,
+        id: &str,
+        base: Option<&Map<String, Value>>,
+        ours: Option<&Map<String, Value>>,
+        theirs: Option<&Map<String, Value>>,
+    ) {
+        let value =
+            |item: Option<&Map<String, Value>>| item.map(|item| Value::Object(item.clone()));
+        self.conflicts.push(MergeConflict {
+            kind,
+            element,
+            id: Some(id.to_string()),
+            field: None,
+            base: value(base),
+            ours: value(ours),
+            theirs: value(theirs),
+        });
+    }
+}
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl SurveyMerge {
+    /// The merged survey, writable with `dict_to_ariane_tml_file`.
+    #[getter(merged)]
+    fn py_merged<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, &self.merged)?)
+    }
+
+    #[getter(conflicts)]
+    fn py_conflicts<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, &self.conflicts)?)
+    }
+
+    #[getter(renumbered)]
+    fn py_renumbered<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, &self.renumbered)?)
+    }
+
+    #[getter]
+    fn has_conflicts(&self) -> bool {
+        !self.conflicts.is_empty()
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!(
+            "SurveyMerge(conflicts={}, renumbered={})",
+            self.conflicts.len(),
+            self.renumbered.len()
+        )
+    }
+}
+
+/// Three-way merge of two surveys edited from a common `base`.
+///
+/// Shots are matched by `ID` and layers by `name`; each field changed on a single side is
+/// taken from that side. Shots added on both sides with the same `ID` are kept, their shots
+/// being renumbered after the highest station along with the `FromID`/`ClosureToID`
+/// references to them. Changes which cannot be merged are reported as conflicts and resolved
+/// in favour of `ours`.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+pub fn merge_surveys(
+    base: &Bound<'_, PyDict>,
+    ours: &Bound<'_, PyDict>,
+    theirs: &Bound<'_, PyDict>,
+) -> PyResult<SurveyMerge> {
+    let base: Value = depythonize(base)?;
+    let ours: Value = depythonize(ours)?;
+    let theirs: Value = depythonize(theirs)?;
+    SurveyMerge::compute(&base, &ours, &theirs).map_err(PyValueError::new_err)
+}
+
+fn shots_by_id(shots: Vec<&Map<String, Value>>) -> Keyed<'_> {
+    diff::shot_keys(&shots).into_iter().zip(shots).collect()
+}
+
+fn layers_by_name(layers: Vec<&Map<String, Value>>) -> Keyed<'_> {
+    layers
+        .into_iter()
+        .filter_map(|layer| Some((survey::field_str(layer, "name")?.into_owned(), layer)))
+        .collect()
+}
+
+fn station_number(shot: &Map<String, Value>, field: &str) -> Option<i64> {
+    survey::station_id(shot, field)?.parse().ok()
+}
+
+/// Replaces the repeated `child` elements of `parent`, creating it when items are added.
+fn set_repeated(
+    cave: &mut Map<String, Value>,
+    parent: &str,
+    child: &str,
+    items: Vec<Map<String, Value>>,
+) {
+    let items = Value::Array(items.into_iter().map(Value::Object).collect());
+    match cave.get_mut(parent) {
+        Some(Value::Object(parent)) => {
+            parent.insert(child.to_string(), items);
+        }
+        _ if items.as_array().is_some_and(Vec::is_empty) => (),
+        _ => {
+            cave.insert(
+                parent.to_string(),
+                Value::Object(Map::from_iter([(child.to_string(), items)])),
+            );
+        }
+    }
+}
@@ -1,13 +1,83 @@
 use pyo3::prelude::*;
 
+mod archive;
+mod arrays;
+mod arrow;
+mod declination;
 mod deserialize;
+mod diff;
+mod encoding;
+mod geometry;
+mod gpx;
+mod import;
 mod loader;
+mod merge;
+mod model;
+mod number_format;
+mod pockettopo;
+mod projection;
 mod serialize;
+mod statistics;
+mod survey;
+mod table;
+mod therion;
+mod topodroid;
+mod units;
+mod validate;
+mod writer;
 
 #[pymodule]
 pub fn ariane(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(deserialize::xml_str_to_dict, m)?)?;
+    m.add_function(wrap_pyfunction!(deserialize::xml_bytes_to_dict, m)?)?;
     m.add_function(wrap_pyfunction!(serialize::dict_to_xml_str, m)?)?;
+    m.add_function(wrap_pyfunction!(serialize::dict_to_xml_file, m)?)?;
+    m.add_function(wrap_pyfunction!(serialize::dict_to_xml_stream, m)?)?;
+    m.add_function(wrap_pyfunction!(writer::dict_to_ariane_tml_file, m)?)?;
+    m.add_function(wrap_pyfunction!(writer::dict_to_ariane_tmlu_file, m)?)?;
     m.add_function(wrap_pyfunction!(loader::load_ariane_tml_file_to_dict, m)?)?;
+    m.add_function(wrap_pyfunction!(loader::load_ariane_file_to_dict, m)?)?;
+    m.add_function(wrap_pyfunction!(archive::list_ariane_tml_entries, m)?)?;
+    m.add_function(wrap_pyfunction!(archive::read_ariane_tml_entry, m)?)?;
+    m.add_function(wrap_pyfunction!(diff::diff_surveys, m)?)?;
+    m.add_function(wrap_pyfunction!(merge::merge_surveys, m)?)?;
+    m.add_function(wrap_pyfunction!(validate::validate, m)?)?;
+    m.add_function(wrap_pyfunction!(statistics::survey_statistics, m)?)?;
+    m.add_function(wrap_pyfunction!(units::convert_units, m)?)?;
+    m.add_function(wrap_pyfunction!(declination::magnetic_declination, m)?)?;
+    m.add_function(wrap_pyfunction!(declination::correct_declination, m)?)?;
+    m.add_function(wrap_pyfunction!(projection::wgs84_to_utm, m)?)?;
+    m.add_function(wrap_pyfunction!(projection::utm_to_wgs84, m)?)?;
+    m.add_function(wrap_pyfunction!(projection::wgs84_to_local, m)?)?;
+    m.add_function(wrap_pyfunction!(projection::local_to_wgs84, m)?)?;
+    m.add_function(wrap_pyfunction!(projection::station_coordinates, m)?)?;
+    m.add_function(wrap_pyfunction!(gpx::dict_to_gpx_str, m)?)?;
+    m.add_function(wrap_pyfunction!(gpx::dict_to_gpx_file, m)?)?;
+    m.add_function(wrap_pyfunction!(table::export_shots_csv, m)?)?;
+    m.add_function(wrap_pyfunction!(arrow::shots_to_arrow, m)?)?;
+    m.add_function(wrap_pyfunction!(arrow::load_ariane_tml_file_to_arrow, m)?)?;
+    m.add_function(wrap_pyfunction!(arrays::shot_arrays, m)?)?;
+    m.add_function(wrap_pyfunction!(arrays::load_ariane_tml_file_to_arrays, m)?)?;
+    m.add_function(wrap_pyfunction!(
+        import::load_pockettopo_txt_file_to_dict,
+        m
+    )?)?;
+    m.add_function(wrap_pyfunction!(
+        import::load_topodroid_csv_file_to_dict,
+        m
+    )?)?;
+    m.add_function(wrap_pyfunction!(import::load_therion_th_file_to_dict, m)?)?;
+    m.add_class::<serialize::XmlWriterOptions>()?;
+    m.add_class::<number_format::NumberFormat>()?;
+    m.add_class::<diff::SurveyDiff>()?;
+    m.add_class::<merge::SurveyMerge>()?;
+    m.add_class::<validate::Diagnostic>()?;
+    m.add_class::<statistics::SurveyStatistics>()?;
+    m.add_class::<declination::GeomagneticModel>()?;
+    m.add_class::<projection::UtmCoordinate>()?;
+    m.add_class::<arrow::ShotTable>()?;
+    m.add_class::<archive::TmlEntry>()?;
+    m.add_class::<model::Survey>()?;
+    m.add_class::<model::Shot>()?;
     Ok(())
 }
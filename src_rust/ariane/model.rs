@@ -0,0 +1,176 @@
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+use pythonize::{depythonize, pythonize};
+use serde_json::{Map, Value};
+
+use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pymethods};
+
+use super::survey;
+use super::units::{self, LengthUnit};
+
+/// A shot of a `Survey`, with its measurements as numbers in the survey unit.
+#[gen_stub_pyclass]
+#[pyclass(module = "openspeleo_core._rust_lib.ariane", frozen)]
+#[derive(Clone, Debug)]
+pub struct Shot {
+    #[pyo3(get)]
+    pub id: Option<String>,
+    #[pyo3(get)]
+    pub from_id: Option<String>,
+    #[pyo3(get)]
+    pub length: Option<f64>,
+    /// Azimuth in degrees, see `Survey.magnetic`.
+    #[pyo3(get)]
+    pub azimuth: Option<f64>,
+    #[pyo3(get)]
+    pub inclination: Option<f64>,
+    #[pyo3(get)]
+    pub depth: Option<f64>,
+    /// Depth at the start of the shot, `-1.0` when not recorded.
+    #[pyo3(get)]
+    pub depth_in: Option<f64>,
+    #[pyo3(get)]
+    pub left: Option<f64>,
+    #[pyo3(get)]
+    pub right: Option<f64>,
+    #[pyo3(get)]
+    pub up: Option<f64>,
+    #[pyo3(get)]
+    pub down: Option<f64>,
+    #[pyo3(get)]
+    pub date: Option<String>,
+    #[pyo3(get)]
+    pub comment: Option<String>,
+}
+
+impl Shot {
+    fn from_map(shot: &Map<String, Value>) -> Self {
+        let number = |key: &str| shot.get(key).and_then(survey::scalar_f64);
+        let text = |key: &str| survey::field_str(shot, key).map(|text| text.into_owned());
+        Shot {
+            id: survey::station_id(shot, "ID"),
+            from_id: survey::station_id(shot, "FromID"),
+            length: number("Length"),
+            azimuth: number("Azimut"),
+            inclination: number("Inclination"),
+            depth: number("Depth"),
+            depth_in: number("DepthIn"),
+            left: number("Left"),
+            right: number("Right"),
+            up: number("Up"),
+            down: number("Down"),
+            date: text("Date"),
+            comment: text("Comment"),
+        }
+    }
+}
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl Shot {
+    pub fn __repr__(&self) -> String {
+        format!(
+            "Shot(id={:?}, from_id={:?}, length={:?})",
+            self.id, self.from_id, self.length
+        )
+    }
+}
+
+/// Typed view of a loaded survey.
+///
+/// Built from the dict returned by the loaders, which it keeps: `to_dict` gives it back, with
+/// the changes made through the typed model (e.g. `convert_units`).
+#[gen_stub_pyclass]
+#[pyclass(module = "openspeleo_core._rust_lib.ariane", frozen)]
+pub struct Survey {
+    document: Value,
+    unit: LengthUnit,
+    shots: Vec<Shot>,
+}
+
+impl Survey {
+    fn from_document(document: Value) -> Result<Self, String> {
+        let cave = survey::cave_file(&document)?;
+        let unit = units::survey_unit(cave)?;
+        let shots = survey::shots(cave)
+            .into_iter()
+            .map(Shot::from_map)
+            .collect();
+        Ok(Survey {
+            document,
+            unit,
+            shots,
+        })
+    }
+}
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl Survey {
+    /// Reads a loaded survey, converted to `unit` (`m` or `ft`) if given.
+    #[new]
+    #[pyo3(signature = (data, unit = None))]
+    fn new(data: &Bound<'_, PyDict>, unit: Option<&str>) -> PyResult<Self> {
+        let mut document: Value = depythonize(data)?;
+        if let Some(unit) = unit {
+            let unit = LengthUnit::parse(unit).map_err(PyValueError::new_err)?;
+            units::convert_document(&mut document, unit).map_err(PyValueError::new_err)?;
+        }
+        Survey::from_document(document).map_err(PyValueError::new_err)
+    }
+
+    /// Name of the cave, from `caveName`.
+    #[getter]
+    fn name(&self) -> Option<String> {
+        let cave = survey::cave_file(&self.document).ok()?;
+        survey::field_str(cave, "caveName").map(|name| name.into_owned())
+    }
+
+    /// Length unit of the measurements: `m` or `ft`.
+    #[getter]
+    fn unit(&self) -> &'static str {
+        self.unit.code()
+    }
+
+    /// Whether the azimuths are relative to magnetic north, from `useMagneticAzimuth`.
+    #[getter]
+    fn magnetic(&self) -> bool {
+        survey::cave_file(&self.document)
+            .ok()
+            .and_then(|cave| survey::field_str(cave, "useMagneticAzimuth"))
+            .is_none_or(|flag| flag == "true")
+    }
+
+    /// Shots in document order.
+    #[getter]
+    fn shots(&self) -> Vec<Shot> {
+        self.shots.clone()
+    }
+
+    fn __len__(&self) -> usize {
+        self.shots.len()
+    }
+
+    /// Returns a copy of the survey with its lengths and depths in `target` (`m` or `ft`), as
+    /// `convert_units` does for the dict representation.
+    fn convert_units(&self, target: &str) -> PyResult<Survey> {
+        let target = LengthUnit::parse(target).map_err(PyValueError::new_err)?;
+        let mut document = self.document.clone();
+        units::convert_document(&mut document, target).map_err(PyValueError::new_err)?;
+        Survey::from_document(document).map_err(PyValueError::new_err)
+    }
+
+    /// Returns the survey as the dict the loaders return.
+    #[gen_stub(override_return_type(type_repr = "dict", imports = ()))]
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, &self.document)?)
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!(
+            "Survey(name={:?}, unit={:?}, shots={})",
+            self.name().unwrap_or_default(),
+            self.unit.code(),
+            self.shots.len()
+        )
+    }
+}
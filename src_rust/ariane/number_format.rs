@@ -0,0 +1,218 @@
+use ahash::AHashMap;
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBool, PyDict, PyString},
+};
+use serde_json::Number;
+
+use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pymethods};
+
+/// Numeric fields Ariane always writes as floating point numbers (e.g. `333.0`).
+const ARIANE_FLOAT_FIELDS: &[&str] = &[
+    "Azimut",
+    "Depth",
+    "DepthIn",
+    "Down",
+    "Inclination",
+    "Latitude",
+    "Left",
+    "Length",
+    "Longitude",
+    "Right",
+    "TensionCorridor",
+    "TensionProfile",
+    "Up",
+    "angle",
+    "dashScale",
+    "firstStartAbsoluteElevation",
+    "length",
+    "lineTypeScale",
+    "opacity",
+    "profileAzimut",
+    "profileTilt",
+    "strokeThickness",
+];
+
+/// Numeric fields Ariane always writes as integers (station identifiers).
+const ARIANE_INTEGER_FIELDS: &[&str] = &["ClosureToID", "FromID", "ID"];
+
+/// How a numeric value is written out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NumberStyle {
+    /// Integers stay integers, floats use their shortest representation with a decimal point.
+    Auto,
+    /// Always written as a float, with at least one decimal (`3` -> `3.0`).
+    Float,
+    /// Rounded to the nearest integer.
+    Integer,
+    /// Written with a fixed number of decimals.
+    Fixed(usize),
+}
+
+impl std::fmt::Display for NumberStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NumberStyle::Auto => f.write_str("'auto'"),
+            NumberStyle::Float => f.write_str("'float'"),
+            NumberStyle::Integer => f.write_str("'int'"),
+            NumberStyle::Fixed(precision) => write!(f, "{precision}"),
+        }
+    }
+}
+
+impl NumberStyle {
+    fn extract(value: &Bound<'_, PyAny>) -> PyResult<Self> {
+        // `bool` is an `int` subclass, `True` would be read as one decimal
+        if value.is_instance_of::<PyBool>() {
+            return Err(Self::unsupported(value));
+        }
+        if let Ok(precision) = value.extract::<usize>() {
+            return Ok(NumberStyle::Fixed(precision));
+        }
+        if let Ok(style) = value.cast::<PyString>() {
+            match style.to_str()? {
+                "auto" => return Ok(NumberStyle::Auto),
+                "float" => return Ok(NumberStyle::Float),
+                "int" => return Ok(NumberStyle::Integer),
+                _ => (),
+            }
+        }
+        Err(Self::unsupported(value))
+    }
+
+    fn unsupported(value: &Bound<'_, PyAny>) -> PyErr {
+        PyValueError::new_err(format!(
+            "Unsupported number style: `{value}`. \
+             Expected `auto`, `float`, `int` or a number of decimals."
+        ))
+    }
+
+    fn to_object<'py>(self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        match self {
+            NumberStyle::Auto => Ok(PyString::new(py, "auto").into_any()),
+            NumberStyle::Float => Ok(PyString::new(py, "float").into_any()),
+            NumberStyle::Integer => Ok(PyString::new(py, "int").into_any()),
+            NumberStyle::Fixed(precision) => Ok(precision.into_pyobject(py)?.into_any()),
+        }
+    }
+}
+
+/// Formatting policy applied to numeric values when writing XML.
+///
+/// Styles are given per element name, either as `"auto"`, `"float"`, `"int"` or as a number
+/// of decimals. Exponent notation is never used.
+#[gen_stub_pyclass]
+#[pyclass(module = "openspeleo_core._rust_lib.ariane", frozen)]
+#[derive(Clone, Debug)]
+pub struct NumberFormat {
+    pub default: NumberStyle,
+    pub fields: AHashMap<String, NumberStyle>,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat {
+            default: NumberStyle::Auto,
+            fields: AHashMap::default(),
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Number formatting used by Ariane (Java `Double.toString` for measurements).
+    pub fn ariane_preset() -> Self {
+        let fields = ARIANE_FLOAT_FIELDS
+            .iter()
+            .map(|name| (name.to_string(), NumberStyle::Float))
+            .chain(
+                ARIANE_INTEGER_FIELDS
+                    .iter()
+                    .map(|name| (name.to_string(), NumberStyle::Integer)),
+            )
+            .collect();
+        NumberFormat {
+            default: NumberStyle::Auto,
+            fields,
+        }
+    }
+
+    /// Formats `number` according to the style configured for the element `name`.
+    pub fn format(&self, name: &str, number: &Number) -> String {
+        let style = self.fields.get(name).copied().unwrap_or(self.default);
+
+        match (style, number.as_i64(), number.as_u64()) {
+            (NumberStyle::Auto | NumberStyle::Integer, Some(n), _) => return n.to_string(),
+            (NumberStyle::Auto | NumberStyle::Integer, _, Some(n)) => return n.to_string(),
+            _ => (),
+        }
+
+        // Every `serde_json::Number` is representable as a `f64` without `arbitrary_precision`
+        let float = number.as_f64().unwrap_or_default();
+        match style {
+            NumberStyle::Integer => format!("{float:.0}"),
+            NumberStyle::Fixed(precision) => format!("{float:.precision$}"),
+            NumberStyle::Auto | NumberStyle::Float => {
+                // `Display` for `f64` gives the shortest round-trip form and never an exponent
+                let mut repr = float.to_string();
+                if float.is_finite() && !repr.contains('.') {
+                    repr.push_str(".0");
+                }
+                repr
+            }
+        }
+    }
+}
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl NumberFormat {
+    #[new]
+    #[pyo3(signature = (default = None, fields = None))]
+    fn new(
+        default: Option<&Bound<'_, PyAny>>,
+        fields: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<Self> {
+        let default = match default {
+            Some(style) => NumberStyle::extract(style)?,
+            None => NumberStyle::Auto,
+        };
+        let fields = match fields {
+            Some(fields) => fields
+                .iter()
+                .map(|(name, style)| Ok((name.extract()?, NumberStyle::extract(&style)?)))
+                .collect::<PyResult<_>>()?,
+            None => AHashMap::default(),
+        };
+        Ok(NumberFormat { default, fields })
+    }
+
+    /// Number formatting reproducing the files written by Ariane.
+    #[staticmethod]
+    fn ariane() -> Self {
+        Self::ariane_preset()
+    }
+
+    /// Style used for elements without a specific rule.
+    #[getter(default)]
+    fn py_default<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.default.to_object(py)
+    }
+
+    /// Style of `name`, falling back on the default style.
+    fn style_for<'py>(&self, py: Python<'py>, name: &str) -> PyResult<Bound<'py, PyAny>> {
+        self.fields
+            .get(name)
+            .copied()
+            .unwrap_or(self.default)
+            .to_object(py)
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!(
+            "NumberFormat(default={}, fields={})",
+            self.default,
+            self.fields.len()
+        )
+    }
+}
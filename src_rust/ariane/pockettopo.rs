@@ -0,0 +1,109 @@
+use super::import::{self, ImportedSurvey, Reading, Trip};
+use super::units::LengthUnit;
+
+/// Section of a station: PocketTopo names stations `<series>.<point>`.
+fn section(station: &str, survey: &str) -> String {
+    match station.split_once('.') {
+        Some((series, _)) => format!("Series {series}"),
+        None => survey.to_string(),
+    }
+}
+
+fn number(text: &str) -> Result<f64, String> {
+    text.parse()
+        .map_err(|_| format!("`{text}` is not a number"))
+}
+
+/// Parses a shot line: `from [to] azimuth inclination distance`, optionally followed by the
+/// extended elevation direction (`<` or `>`) and a quoted comment. Splays have no `to`.
+fn shot(line: &str, trip: usize, survey: &str) -> Result<Reading, String> {
+    let (line, comment) = match line.split_once('"') {
+        Some((line, comment)) => (line, Some(comment.trim_end().trim_end_matches('"'))),
+        None => (line, None),
+    };
+    let mut fields: Vec<&str> = if line.contains('\t') {
+        line.split('\t').map(str::trim).collect()
+    } else {
+        line.split_whitespace().collect()
+    };
+    fields.retain(|field| !matches!(*field, "<" | ">"));
+    while fields.last().is_some_and(|field| field.is_empty()) {
+        fields.pop();
+    }
+
+    let (from, to, measures) = match fields.as_slice() {
+        [from, to, measures @ ..] if measures.len() == 3 => (*from, Some(*to), measures),
+        [from, measures @ ..] if measures.len() == 3 => (*from, None, measures),
+        _ => {
+            return Err(
+                "Expected `from [to] azimuth inclination distance` separated by tabs".to_string(),
+            )
+        }
+    };
+    let to = to.filter(|to| !to.is_empty());
+    Ok(Reading {
+        from: from.to_string(),
+        to: to.map(str::to_string),
+        azimuth: number(measures[0])?,
+        inclination: number(measures[1])?,
+        length: number(measures[2])?,
+        comment: comment
+            .filter(|comment| !comment.is_empty())
+            .map(str::to_string),
+        section: section(to.unwrap_or(from), survey),
+        trip,
+        ..Reading::default()
+    })
+}
+
+/// Parses a PocketTopo text export: `TRIP` blocks with their `DATE` and `DECLINATION`, each
+/// followed by the shots under `DATA`. `PLAN` and `ELEVATION` drawings are skipped.
+pub fn parse(text: &str, name: &str) -> Result<ImportedSurvey, String> {
+    let mut trips: Vec<Trip> = Vec::new();
+    let mut readings = Vec::new();
+    let mut in_data = false;
+    for (index, line) in text.lines().enumerate() {
+        let error = |e: String| format!("Line {}: {e}", index + 1);
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match keyword {
+            "TRIP" => {
+                trips.push(Trip::default());
+                in_data = false;
+            }
+            "DATE" => {
+                let date = import::parse_date(rest)
+                    .ok_or_else(|| error(format!("`{}` is not a date", rest.trim())))?;
+                if trips.is_empty() {
+                    trips.push(Trip::default());
+                }
+                trips.last_mut().unwrap().date = Some(date);
+            }
+            "DECLINATION" => {
+                let declination = number(rest.trim()).map_err(error)?;
+                if trips.is_empty() {
+                    trips.push(Trip::default());
+                }
+                // PocketTopo writes 0.00 when no declination is set
+                trips.last_mut().unwrap().declination = (declination != 0.0).then_some(declination);
+            }
+            "DATA" => in_data = true,
+            "PLAN" | "ELEVATION" => in_data = false,
+            _ if in_data => {
+                let trip = trips.len().saturating_sub(1);
+                readings.push(shot(line, trip, name).map_err(error)?);
+            }
+            _ => (),
+        }
+    }
+
+    Ok(ImportedSurvey {
+        name: name.to_string(),
+        unit: LengthUnit::Meters,
+        trips,
+        readings,
+    })
+}
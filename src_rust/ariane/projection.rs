@@ -0,0 +1,605 @@
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+use pythonize::{depythonize, pythonize};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pyfunction, gen_stub_pymethods};
+
+use super::{declination, geometry, survey, units};
+
+/// WGS84 semi-major axis, in meters.
+const WGS84_A: f64 = 6_378_137.0;
+const WGS84_F: f64 = 1.0 / 298.257_223_563;
+
+/// Scale factor on the central meridian of a UTM zone.
+const UTM_K0: f64 = 0.9996;
+const UTM_FALSE_EASTING: f64 = 500_000.0;
+/// False northing of the southern hemisphere zones.
+const UTM_FALSE_NORTHING: f64 = 10_000_000.0;
+/// Latitudes covered by UTM, beyond which the polar stereographic grids apply.
+const UTM_LATITUDES: std::ops::RangeInclusive<f64> = -80.0..=84.0;
+
+fn eccentricity_squared() -> f64 {
+    WGS84_F * (2.0 - WGS84_F)
+}
+
+/// Position on the WGS84 ellipsoid. `altitude` is in meters above the ellipsoid.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Geodetic {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: f64,
+}
+
+/// Transverse Mercator projection on WGS84, using Krüger's series to the sixth order in `n`
+/// (Karney, "Transverse Mercator with an accuracy of a few nanometers", 2011).
+struct TransverseMercator {
+    /// Radius of the rectifying sphere.
+    a: f64,
+    e: f64,
+    alpha: [f64; 6],
+    beta: [f64; 6],
+}
+
+impl TransverseMercator {
+    fn wgs84() -> Self {
+        let n = WGS84_F / (2.0 - WGS84_F);
+        let (n2, n3, n4, n5, n6) = (n * n, n.powi(3), n.powi(4), n.powi(5), n.powi(6));
+        TransverseMercator {
+            a: WGS84_A / (1.0 + n) * (1.0 + n2 / 4.0 + n4 / 64.0 + n6 / 256.0),
+            e: eccentricity_squared().sqrt(),
+            alpha: [
+                n / 2.0 - 2.0 / 3.0 * n2 + 5.0 / 16.0 * n3 + 41.0 / 180.0 * n4 - 127.0 / 288.0 * n5
+                    + 7891.0 / 37800.0 * n6,
+                13.0 / 48.0 * n2 - 3.0 / 5.0 * n3 + 557.0 / 1440.0 * n4 + 281.0 / 630.0 * n5
+                    - 1_983_433.0 / 1_935_360.0 * n6,
+                61.0 / 240.0 * n3 - 103.0 / 140.0 * n4
+                    + 15061.0 / 26880.0 * n5
+                    + 167_603.0 / 181_440.0 * n6,
+                49561.0 / 161_280.0 * n4 - 179.0 / 168.0 * n5 + 6_601_661.0 / 7_257_600.0 * n6,
+                34729.0 / 80640.0 * n5 - 3_418_889.0 / 1_995_840.0 * n6,
+                212_378_941.0 / 319_334_400.0 * n6,
+            ],
+            beta: [
+                n / 2.0 - 2.0 / 3.0 * n2 + 37.0 / 96.0 * n3 - 1.0 / 360.0 * n4 - 81.0 / 512.0 * n5
+                    + 96199.0 / 604_800.0 * n6,
+                n2 / 48.0 + n3 / 15.0 - 437.0 / 1440.0 * n4 + 46.0 / 105.0 * n5
+                    - 1_118_711.0 / 3_870_720.0 * n6,
+                17.0 / 480.0 * n3 - 37.0 / 840.0 * n4 - 209.0 / 4480.0 * n5 + 5569.0 / 90720.0 * n6,
+                4397.0 / 161_280.0 * n4 - 11.0 / 504.0 * n5 - 830_251.0 / 7_257_600.0 * n6,
+                4583.0 / 161_280.0 * n5 - 108_847.0 / 3_991_680.0 * n6,
+                20_648_693.0 / 638_668_800.0 * n6,
+            ],
+        }
+    }
+
+    /// Conformal latitude of a geodetic latitude, both as tangents.
+    fn conformal(&self, tau: f64) -> f64 {
+        let sigma = (self.e * (self.e * tau / tau.hypot(1.0)).atanh()).sinh();
+        tau * sigma.hypot(1.0) - sigma * tau.hypot(1.0)
+    }
+
+    /// Projects a point `lambda` radians east of the central meridian. Returns the easting and
+    /// northing relative to the central meridian and the equator (unscaled), the convergence
+    /// in radians and the point scale factor.
+    fn forward(&self, phi: f64, lambda: f64) -> (f64, f64, f64, f64) {
+        let tau = phi.tan();
+        let tau_prime = self.conformal(tau);
+        let xi_prime = tau_prime.atan2(lambda.cos());
+        let eta_prime = (lambda.sin() / tau_prime.hypot(lambda.cos())).asinh();
+
+        let (mut xi, mut eta) = (xi_prime, eta_prime);
+        let (mut p, mut q) = (1.0, 0.0);
+        for (j, alpha) in self.alpha.iter().enumerate() {
+            let k = 2.0 * (j + 1) as f64;
+            let (sin, cos) = (k * xi_prime).sin_cos();
+            let (sinh, cosh) = ((k * eta_prime).sinh(), (k * eta_prime).cosh());
+            xi += alpha * sin * cosh;
+            eta += alpha * cos * sinh;
+            p += k * alpha * cos * cosh;
+            q += k * alpha * sin * sinh;
+        }
+
+        let gamma = (tau_prime / tau_prime.hypot(1.0) * lambda.tan()).atan() + q.atan2(p);
+        let e2 = eccentricity_squared();
+        let scale = (1.0 - e2 * phi.sin().powi(2)).sqrt() * tau.hypot(1.0)
+            / tau_prime.hypot(lambda.cos())
+            * (self.a / WGS84_A)
+            * p.hypot(q);
+        (self.a * eta, self.a * xi, gamma, scale)
+    }
+
+    /// Inverse of `forward`: latitude and longitude from the central meridian, in radians.
+    fn inverse(&self, x: f64, y: f64) -> (f64, f64) {
+        let (xi, eta) = (y / self.a, x / self.a);
+        let (mut xi_prime, mut eta_prime) = (xi, eta);
+        for (j, beta) in self.beta.iter().enumerate() {
+            let k = 2.0 * (j + 1) as f64;
+            xi_prime -= beta * (k * xi).sin() * (k * eta).cosh();
+            eta_prime -= beta * (k * xi).cos() * (k * eta).sinh();
+        }
+
+        let tau_prime = xi_prime.sin() / eta_prime.sinh().hypot(xi_prime.cos());
+        let e2 = eccentricity_squared();
+        // Newton-Raphson on the conformal latitude
+        let mut tau = tau_prime;
+        for _ in 0..10 {
+            let tau_i = self.conformal(tau);
+            let delta = (tau_prime - tau_i) / tau_i.hypot(1.0) * (1.0 + (1.0 - e2) * tau * tau)
+                / ((1.0 - e2) * tau.hypot(1.0));
+            tau += delta;
+            if delta.abs() < 1e-12 {
+                break;
+            }
+        }
+        (tau.atan(), eta_prime.sinh().atan2(xi_prime.cos()))
+    }
+}
+
+/// Longitude wrapped to [-180, 180).
+fn wrap_longitude(longitude: f64) -> f64 {
+    (longitude + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// UTM zone of a position, with the Norway and Svalbard exceptions.
+pub fn utm_zone(latitude: f64, longitude: f64) -> u8 {
+    let longitude = wrap_longitude(longitude);
+    if (56.0..64.0).contains(&latitude) && (3.0..12.0).contains(&longitude) {
+        return 32;
+    }
+    if (72.0..=84.0).contains(&latitude) && (0.0..42.0).contains(&longitude) {
+        return match longitude {
+            l if l < 9.0 => 31,
+            l if l < 21.0 => 33,
+            l if l < 33.0 => 35,
+            _ => 37,
+        };
+    }
+    ((longitude + 180.0) / 6.0).floor() as u8 % 60 + 1
+}
+
+fn central_meridian(zone: u8) -> f64 {
+    f64::from(zone) * 6.0 - 183.0
+}
+
+fn check_zone(zone: u8) -> Result<u8, String> {
+    if (1..=60).contains(&zone) {
+        Ok(zone)
+    } else {
+        Err(format!("Invalid UTM zone: {zone}. Expected 1 to 60."))
+    }
+}
+
+/// Position projected on a UTM zone.
+#[gen_stub_pyclass]
+#[pyclass(module = "openspeleo_core._rust_lib.ariane", frozen)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct UtmCoordinate {
+    /// Zone number, from 1 to 60.
+    #[pyo3(get)]
+    pub zone: u8,
+    /// `N` or `S`.
+    #[pyo3(get)]
+    pub hemisphere: char,
+    #[pyo3(get)]
+    pub easting: f64,
+    #[pyo3(get)]
+    pub northing: f64,
+    /// Angle from true north to grid north, clockwise in degrees. A true azimuth minus the
+    /// convergence gives the grid azimuth.
+    #[pyo3(get)]
+    pub convergence: f64,
+    /// Ratio of a grid distance to the distance on the ellipsoid.
+    #[pyo3(get)]
+    pub scale_factor: f64,
+}
+
+impl UtmCoordinate {
+    /// Projects a position on its own zone, or on `zone` when given.
+    pub fn from_geodetic(latitude: f64, longitude: f64, zone: Option<u8>) -> Result<Self, String> {
+        if !UTM_LATITUDES.contains(&latitude) {
+            return Err(format!(
+                "Latitude {latitude} is outside the UTM range (80°S to 84°N)"
+            ));
+        }
+        let zone = match zone {
+            Some(zone) => check_zone(zone)?,
+            None => utm_zone(latitude, longitude),
+        };
+        let lambda = wrap_longitude(longitude - central_meridian(zone)).to_radians();
+        let (x, y, gamma, scale) =
+            TransverseMercator::wgs84().forward(latitude.to_radians(), lambda);
+        let hemisphere = if latitude < 0.0 { 'S' } else { 'N' };
+        Ok(UtmCoordinate {
+            zone,
+            hemisphere,
+            easting: UTM_FALSE_EASTING + UTM_K0 * x,
+            northing: UTM_K0 * y
+                + if latitude < 0.0 {
+                    UTM_FALSE_NORTHING
+                } else {
+                    0.0
+                },
+            convergence: gamma.to_degrees(),
+            scale_factor: UTM_K0 * scale,
+        })
+    }
+
+    /// Latitude and longitude of a UTM position.
+    pub fn geodetic(zone: u8, hemisphere: char, easting: f64, northing: f64) -> (f64, f64) {
+        let northing = match hemisphere {
+            'S' => northing - UTM_FALSE_NORTHING,
+            _ => northing,
+        };
+        let (phi, lambda) = TransverseMercator::wgs84()
+            .inverse((easting - UTM_FALSE_EASTING) / UTM_K0, northing / UTM_K0);
+        (
+            phi.to_degrees(),
+            wrap_longitude(lambda.to_degrees() + central_meridian(zone)),
+        )
+    }
+}
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl UtmCoordinate {
+    /// Latitude and longitude of the position.
+    fn to_wgs84(&self) -> (f64, f64) {
+        UtmCoordinate::geodetic(self.zone, self.hemisphere, self.easting, self.northing)
+    }
+
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, self)?)
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!(
+            "UtmCoordinate({}{} {:.3} {:.3})",
+            self.zone, self.hemisphere, self.easting, self.northing
+        )
+    }
+}
+
+fn parse_hemisphere(name: &str) -> Result<char, String> {
+    match name.trim().to_ascii_uppercase().as_str() {
+        "N" | "NORTH" => Ok('N'),
+        "S" | "SOUTH" => Ok('S'),
+        _ => Err(format!(
+            "Unsupported hemisphere: `{name}`. Expected `N` (north) or `S` (south)."
+        )),
+    }
+}
+
+/// Earth-centered, Earth-fixed cartesian coordinates of a position.
+fn to_ecef(position: &Geodetic) -> [f64; 3] {
+    let e2 = eccentricity_squared();
+    let (sin_phi, cos_phi) = position.latitude.to_radians().sin_cos();
+    let (sin_lambda, cos_lambda) = position.longitude.to_radians().sin_cos();
+    let n = WGS84_A / (1.0 - e2 * sin_phi * sin_phi).sqrt();
+    [
+        (n + position.altitude) * cos_phi * cos_lambda,
+        (n + position.altitude) * cos_phi * sin_lambda,
+        (n * (1.0 - e2) + position.altitude) * sin_phi,
+    ]
+}
+
+fn from_ecef([x, y, z]: [f64; 3]) -> Geodetic {
+    let e2 = eccentricity_squared();
+    let p = x.hypot(y);
+    let mut phi = z.atan2(p * (1.0 - e2));
+    let mut altitude = 0.0;
+    for _ in 0..10 {
+        let n = WGS84_A / (1.0 - e2 * phi.sin().powi(2)).sqrt();
+        altitude = p / phi.cos() - n;
+        phi = z.atan2(p * (1.0 - e2 * n / (n + altitude)));
+    }
+    Geodetic {
+        latitude: phi.to_degrees(),
+        longitude: y.atan2(x).to_degrees(),
+        altitude,
+    }
+}
+
+/// Local tangent plane (east, north, up) at an origin on the ellipsoid, in meters.
+pub struct LocalFrame {
+    origin: [f64; 3],
+    /// Rows are the east, north and up unit vectors in ECEF.
+    axes: [[f64; 3]; 3],
+}
+
+impl LocalFrame {
+    pub fn new(origin: &Geodetic) -> Self {
+        let (sin_phi, cos_phi) = origin.latitude.to_radians().sin_cos();
+        let (sin_lambda, cos_lambda) = origin.longitude.to_radians().sin_cos();
+        LocalFrame {
+            origin: to_ecef(origin),
+            axes: [
+                [-sin_lambda, cos_lambda, 0.0],
+                [-sin_phi * cos_lambda, -sin_phi * sin_lambda, cos_phi],
+                [cos_phi * cos_lambda, cos_phi * sin_lambda, sin_phi],
+            ],
+        }
+    }
+
+    pub fn to_local(&self, position: &Geodetic) -> (f64, f64, f64) {
+        let point = to_ecef(position);
+        let delta: Vec<f64> = (0..3).map(|i| point[i] - self.origin[i]).collect();
+        let [east, north, up] = self
+            .axes
+            .map(|axis| (0..3).map(|i| axis[i] * delta[i]).sum::<f64>());
+        (east, north, up)
+    }
+
+    pub fn to_geodetic(&self, east: f64, north: f64, up: f64) -> Geodetic {
+        let point: [f64; 3] = std::array::from_fn(|i| {
+            self.origin[i] + self.axes[0][i] * east + self.axes[1][i] * north + self.axes[2][i] * up
+        });
+        from_ecef(point)
+    }
+}
+
+/// Station positions of a survey on the ellipsoid.
+pub struct Georeference {
+    /// Position of the located station.
+    pub origin: Geodetic,
+    /// Position of every placed station, in document order.
+    pub stations: Vec<(String, Geodetic)>,
+    /// Warnings of the correction to true north, see `declination::correct_document`.
+    pub warnings: Vec<String>,
+}
+
+/// Places the stations of a loaded survey on the ellipsoid.
+///
+/// Stations are placed from the first located shot, or from the survey start at `location`
+/// when no shot is located. Magnetic azimuths are first corrected to true north, unless no shot
+/// is dated: they are then used as is, with a warning. Altitudes are
+/// `firstStartAbsoluteElevation` minus the station depth.
+pub fn station_geodetic(
+    document: &Value,
+    location: Option<(f64, f64)>,
+) -> Result<Georeference, String> {
+    let cave = survey::cave_file(document)?;
+    let magnetic = survey::field_str(cave, "useMagneticAzimuth").is_none_or(|flag| flag == "true");
+    let corrected;
+    let mut warnings = Vec::new();
+    let dated = survey::shots(cave).iter().any(|shot| {
+        survey::field_str(shot, "Date")
+            .and_then(|date| survey::parse_date(&date))
+            .is_some()
+    });
+    let cave = if magnetic && !dated {
+        warnings.push(
+            "The survey has no dated shot to compute the declination at: magnetic azimuths \
+             are used without correction"
+                .to_string(),
+        );
+        cave
+    } else if magnetic {
+        let mut copy = document.clone();
+        declination::correct_document(&mut copy, declination::North::True, location, None)?;
+        corrected = copy;
+        survey::cave_file(&corrected)?
+    } else {
+        cave
+    };
+
+    let meters = units::survey_unit(cave)?.meters();
+    let elevation = cave
+        .get("firstStartAbsoluteElevation")
+        .and_then(survey::scalar_f64)
+        .unwrap_or(0.0);
+    let shots = survey::shots(cave);
+    let positions = geometry::station_positions(&shots);
+
+    let (anchor, latitude, longitude) = shots
+        .iter()
+        .find_map(|shot| {
+            Some((
+                survey::station_id(shot, "ID")?,
+                survey::shot_location(shot)?,
+            ))
+        })
+        .or_else(|| Some((start_station(&shots)?, location?)))
+        .map(|(station, (latitude, longitude))| (station, latitude, longitude))
+        .ok_or("The survey has no located shot: pass a latitude and a longitude")?;
+    let Some(origin) = positions.get(&anchor) else {
+        return Err(format!("Station `{anchor}` has no position"));
+    };
+    let altitude = |depth: f64| (elevation - depth) * meters;
+    let anchor = Geodetic {
+        latitude,
+        longitude,
+        altitude: altitude(origin.depth),
+    };
+    let frame = LocalFrame::new(&anchor);
+
+    let mut stations = Vec::with_capacity(positions.len());
+    let mut seen = ahash::AHashSet::new();
+    for shot in &shots {
+        let Some(id) = survey::station_id(shot, "ID") else {
+            continue;
+        };
+        let Some(position) = positions.get(&id) else {
+            continue;
+        };
+        if !seen.insert(id.clone()) {
+            continue;
+        }
+        let mut geodetic = frame.to_geodetic(
+            (position.x - origin.x) * meters,
+            (position.y - origin.y) * meters,
+            (origin.depth - position.depth) * meters,
+        );
+        geodetic.altitude = altitude(position.depth);
+        stations.push((id, geodetic));
+    }
+    Ok(Georeference {
+        origin: anchor,
+        stations,
+        warnings,
+    })
+}
+
+/// Station of the first start shot (`FromID` of `-1`), or of the first shot.
+fn start_station(shots: &[&Map<String, Value>]) -> Option<String> {
+    shots
+        .iter()
+        .find(|shot| survey::station_id(shot, "FromID").as_deref() == Some("-1"))
+        .or(shots.first())
+        .and_then(|shot| survey::station_id(shot, "ID"))
+}
+
+/// Location given as an optional `latitude`/`longitude` pair.
+pub fn location(latitude: Option<f64>, longitude: Option<f64>) -> PyResult<Option<(f64, f64)>> {
+    match (latitude, longitude) {
+        (Some(latitude), Some(longitude)) => Ok(Some((latitude, longitude))),
+        (None, None) => Ok(None),
+        _ => Err(PyValueError::new_err(
+            "`latitude` and `longitude` must be given together",
+        )),
+    }
+}
+
+/// Coordinate reference system of exported station positions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Crs {
+    Wgs84,
+    Utm,
+    Local,
+}
+
+impl Crs {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "wgs84" => Ok(Crs::Wgs84),
+            "utm" => Ok(Crs::Utm),
+            "local" => Ok(Crs::Local),
+            _ => Err(format!(
+                "Unsupported coordinate system: `{name}`. Expected `wgs84`, `utm` or `local`."
+            )),
+        }
+    }
+}
+
+/// Projects a WGS84 position on UTM, in its own zone or in `zone` (1 to 60).
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (latitude, longitude, zone = None))]
+pub fn wgs84_to_utm(latitude: f64, longitude: f64, zone: Option<u8>) -> PyResult<UtmCoordinate> {
+    UtmCoordinate::from_geodetic(latitude, longitude, zone).map_err(PyValueError::new_err)
+}
+
+/// Returns the WGS84 latitude and longitude of a UTM position. `hemisphere` is `N` or `S`.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+pub fn utm_to_wgs84(
+    easting: f64,
+    northing: f64,
+    zone: u8,
+    hemisphere: &str,
+) -> PyResult<(f64, f64)> {
+    let zone = check_zone(zone).map_err(PyValueError::new_err)?;
+    let hemisphere = parse_hemisphere(hemisphere).map_err(PyValueError::new_err)?;
+    Ok(UtmCoordinate::geodetic(zone, hemisphere, easting, northing))
+}
+
+/// Returns the east, north and up offsets in meters of a WGS84 position from an origin, in the
+/// plane tangent to the ellipsoid at the origin. Altitudes are above the ellipsoid.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (latitude, longitude, origin_latitude, origin_longitude, altitude = 0.0, origin_altitude = 0.0))]
+pub fn wgs84_to_local(
+    latitude: f64,
+    longitude: f64,
+    origin_latitude: f64,
+    origin_longitude: f64,
+    altitude: f64,
+    origin_altitude: f64,
+) -> (f64, f64, f64) {
+    let frame = LocalFrame::new(&Geodetic {
+        latitude: origin_latitude,
+        longitude: origin_longitude,
+        altitude: origin_altitude,
+    });
+    frame.to_local(&Geodetic {
+        latitude,
+        longitude,
+        altitude,
+    })
+}
+
+/// Inverse of `wgs84_to_local`: the latitude, longitude and altitude of east, north and up
+/// offsets from an origin.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (east, north, up, origin_latitude, origin_longitude, origin_altitude = 0.0))]
+pub fn local_to_wgs84(
+    east: f64,
+    north: f64,
+    up: f64,
+    origin_latitude: f64,
+    origin_longitude: f64,
+    origin_altitude: f64,
+) -> (f64, f64, f64) {
+    let frame = LocalFrame::new(&Geodetic {
+        latitude: origin_latitude,
+        longitude: origin_longitude,
+        altitude: origin_altitude,
+    });
+    let position = frame.to_geodetic(east, north, up);
+    (position.latitude, position.longitude, position.altitude)
+}
+
+/// Returns the position of every station of a loaded survey, keyed by station `ID`.
+///
+/// `crs` is `wgs84` (`latitude`, `longitude`, `altitude`), `utm` (`zone`, `hemisphere`,
+/// `easting`, `northing`, `altitude`, `convergence`) or `local` (`east`, `north`, `up` in meters
+/// from the located station). UTM positions share the zone of the located station unless
+/// `zone` is given. Stations are placed from the first located shot, or from the survey start
+/// at `latitude`/`longitude`. Magnetic azimuths are corrected to true north first, or used as is
+/// with a `UserWarning` when no shot is dated.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (data, crs = "wgs84", zone = None, latitude = None, longitude = None))]
+pub fn station_coordinates<'py>(
+    py: Python<'py>,
+    data: &Bound<'py, PyDict>,
+    crs: &str,
+    zone: Option<u8>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let crs = Crs::parse(crs).map_err(PyValueError::new_err)?;
+    let location = location(latitude, longitude)?;
+    let data: Value = depythonize(data)?;
+    let georeference = station_geodetic(&data, location).map_err(PyValueError::new_err)?;
+    declination::warn_correction(py, &georeference.warnings)?;
+    let origin = &georeference.origin;
+    let frame = LocalFrame::new(origin);
+    let zone = zone.unwrap_or_else(|| utm_zone(origin.latitude, origin.longitude));
+
+    let mut coordinates = Map::new();
+    for (id, position) in &georeference.stations {
+        let value = match crs {
+            Crs::Wgs84 => serde_json::to_value(position),
+            Crs::Utm => {
+                let utm =
+                    UtmCoordinate::from_geodetic(position.latitude, position.longitude, Some(zone))
+                        .map_err(PyValueError::new_err)?;
+                let mut value = serde_json::to_value(utm);
+                if let Ok(Value::Object(fields)) = &mut value {
+                    fields.remove("scale_factor");
+                    fields.insert("altitude".to_string(), position.altitude.into());
+                }
+                value
+            }
+            Crs::Local => {
+                let (east, north, up) = frame.to_local(position);
+                Ok(serde_json::json!({"east": east, "north": north, "up": up}))
+            }
+        }
+        .map_err(|e| PyValueError::new_err(format!("Failed to serialize: {e}")))?;
+        coordinates.insert(id.clone(), value);
+    }
+    Ok(pythonize(py, &coordinates)?)
+}
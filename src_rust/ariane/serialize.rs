@@ -1,97 +1,559 @@
-use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBytes, PyDict},
+};
 use pythonize::depythonize;
-use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::escape::partial_escape;
+use quick_xml::events::{BytesDecl, BytesEnd, BytesPI, BytesStart, BytesText, Event};
 use quick_xml::Writer;
-use serde_json::Value;
-use std::io::Cursor;
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::io::{BufWriter, Write};
 
-use pyo3_stub_gen::derive::gen_stub_pyfunction;
+use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pyfunction, gen_stub_pymethods};
+
+use super::number_format::NumberFormat;
+use crate::mapping::key_mapping::KeyMapping;
+
+/// Line terminator used between the lines of the generated XML document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    fn parse(value: &str) -> PyResult<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "lf" | "\n" => Ok(LineEnding::Lf),
+            "crlf" | "\r\n" => Ok(LineEnding::CrLf),
+            _ => Err(PyValueError::new_err(format!(
+                "Unsupported line ending: `{value}`. Expected `lf` or `crlf`."
+            ))),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "lf",
+            LineEnding::CrLf => "crlf",
+        }
+    }
+
+    fn as_bytes(self) -> &'static [u8] {
+        match self {
+            LineEnding::Lf => b"\n",
+            LineEnding::CrLf => b"\r\n",
+        }
+    }
+}
+
+/// Formatting options used when writing a dict back to XML.
+///
+/// The default options produce a compact single-line document, while
+/// `XmlWriterOptions.ariane()` reproduces the layout of files saved by Ariane.
+#[gen_stub_pyclass]
+#[pyclass(module = "openspeleo_core._rust_lib.ariane", frozen)]
+#[derive(Clone, Debug)]
+pub struct XmlWriterOptions {
+    /// Character used to indent nested elements (a space or a tab).
+    #[pyo3(get)]
+    pub indent_char: char,
+    /// Number of `indent_char` per nesting level. `0` disables pretty-printing.
+    #[pyo3(get)]
+    pub indent_size: usize,
+    /// Value of the `version` field of the XML declaration.
+    #[pyo3(get)]
+    pub version: String,
+    /// Value of the `encoding` field of the XML declaration, omitted if `None`.
+    #[pyo3(get)]
+    pub encoding: Option<String>,
+    /// Value of the `standalone` field of the XML declaration, omitted if `None`.
+    #[pyo3(get)]
+    pub standalone: Option<String>,
+    /// Terminate the document with a line ending.
+    #[pyo3(get)]
+    pub trailing_newline: bool,
+    pub line_ending: LineEnding,
+    /// Formatting policy applied to numeric values.
+    #[pyo3(get)]
+    pub number_format: NumberFormat,
+}
+
+impl Default for XmlWriterOptions {
+    fn default() -> Self {
+        XmlWriterOptions {
+            indent_char: ' ',
+            indent_size: 0,
+            version: "1.0".to_string(),
+            encoding: Some("utf-8".to_string()),
+            standalone: None,
+            trailing_newline: false,
+            line_ending: LineEnding::Lf,
+            number_format: NumberFormat::default(),
+        }
+    }
+}
+
+impl XmlWriterOptions {
+    /// Layout used by Ariane: 4-space indentation, `UTF-8`, `standalone="yes"` and Ariane's
+    /// number formatting.
+    pub fn ariane_preset() -> Self {
+        XmlWriterOptions {
+            indent_char: ' ',
+            indent_size: 4,
+            version: "1.0".to_string(),
+            encoding: Some("UTF-8".to_string()),
+            standalone: Some("yes".to_string()),
+            trailing_newline: true,
+            line_ending: LineEnding::Lf,
+            number_format: NumberFormat::ariane_preset(),
+        }
+    }
+}
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl XmlWriterOptions {
+    #[new]
+    #[pyo3(signature = (
+        indent_char = ' ',
+        indent_size = 0,
+        version = "1.0".to_string(),
+        encoding = Some("utf-8".to_string()),
+        standalone = None,
+        trailing_newline = false,
+        line_ending = "lf",
+        number_format = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        indent_char: char,
+        indent_size: usize,
+        version: String,
+        encoding: Option<String>,
+        standalone: Option<String>,
+        trailing_newline: bool,
+        line_ending: &str,
+        number_format: Option<PyRef<'_, NumberFormat>>,
+    ) -> PyResult<Self> {
+        if indent_char != ' ' && indent_char != '\t' {
+            return Err(PyValueError::new_err(format!(
+                "Unsupported indent character: {indent_char:?}. Expected a space or a tab."
+            )));
+        }
+        Ok(XmlWriterOptions {
+            indent_char,
+            indent_size,
+            version,
+            encoding,
+            standalone,
+            trailing_newline,
+            line_ending: LineEnding::parse(line_ending)?,
+            number_format: number_format.map(|n| n.clone()).unwrap_or_default(),
+        })
+    }
+
+    /// Options reproducing the files written by Ariane.
+    #[staticmethod]
+    fn ariane() -> Self {
+        Self::ariane_preset()
+    }
+
+    /// Line ending used in the document: `"lf"` or `"crlf"`.
+    #[getter(line_ending)]
+    fn py_line_ending(&self) -> &'static str {
+        self.line_ending.as_str()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "XmlWriterOptions(indent_char={:?}, indent_size={}, version={:?}, encoding={:?}, \
+             standalone={:?}, trailing_newline={}, line_ending={:?}, number_format={})",
+            self.indent_char,
+            self.indent_size,
+            self.version,
+            self.encoding,
+            self.standalone,
+            self.trailing_newline,
+            self.line_ending.as_str(),
+            self.number_format.__repr__()
+        )
+    }
+}
 
 #[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
 #[pyfunction]
-pub fn dict_to_xml_str(data: &Bound<'_, PyDict>, root_name: &str) -> PyResult<String> {
-    let value = depythonize(data)?;
-    let mut writer = Writer::new(Cursor::new(Vec::new()));
-    writer
-        .write_event(Event::Decl(quick_xml::events::BytesDecl::new(
-            "1.0",
-            Some("utf-8"),
-            None,
-        )))
-        .map_err(|e| PyValueError::new_err(format!("XML writing error: {e}")))?;
-
-    value_to_xml(&value, root_name, &mut writer)
-        .map_err(|e| PyValueError::new_err(format!("XML generation error: {e}")))?;
+#[pyo3(signature = (data, root_name, options = None, mapping = None))]
+pub fn dict_to_xml_str(
+    py: Python<'_>,
+    data: &Bound<'_, PyDict>,
+    root_name: &str,
+    options: Option<PyRef<'_, XmlWriterOptions>>,
+    mapping: Option<&Bound<'_, KeyMapping>>,
+) -> PyResult<String> {
+    let (root_name, value) = depythonize_element(py, data, root_name, mapping)?;
+    let options = options.map(|o| o.clone()).unwrap_or_default();
 
-    let xml_string = String::from_utf8(writer.into_inner().into_inner())
+    let buffer = write_xml_document(&value, &root_name, &options, Vec::new())
+        .map_err(|e| PyValueError::new_err(format!("XML generation error: {e}")))?;
+    let xml_string = String::from_utf8(buffer)
         .map_err(|e| PyValueError::new_err(format!("UTF-8 conversion error: {e}")))?;
     Ok(xml_string)
 }
 
-fn value_to_xml(
+/// Writes the XML document directly to the file at `path`, without building it in memory.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (data, path, root_name, options = None, mapping = None))]
+pub fn dict_to_xml_file(
+    py: Python<'_>,
+    data: &Bound<'_, PyDict>,
+    path: &str,
+    root_name: &str,
+    options: Option<PyRef<'_, XmlWriterOptions>>,
+    mapping: Option<&Bound<'_, KeyMapping>>,
+) -> PyResult<()> {
+    let (root_name, value) = depythonize_element(py, data, root_name, mapping)?;
+    let options = options.map(|o| o.clone()).unwrap_or_default();
+
+    let file = std::fs::File::create(path).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Failed to create file: {e}"))
+    })?;
+    let writer = BufWriter::with_capacity(65_536, file);
+
+    write_xml_document(&value, &root_name, &options, writer)
+        .and_then(|mut w| w.flush())
+        .map_err(xml_write_error)
+}
+
+/// Writes the XML document to a binary file-like object (anything with a `write(bytes)` method).
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (data, fileobj, root_name, options = None, mapping = None))]
+pub fn dict_to_xml_stream(
+    py: Python<'_>,
+    data: &Bound<'_, PyDict>,
+    fileobj: &Bound<'_, PyAny>,
+    root_name: &str,
+    options: Option<PyRef<'_, XmlWriterOptions>>,
+    mapping: Option<&Bound<'_, KeyMapping>>,
+) -> PyResult<()> {
+    let (root_name, value) = depythonize_element(py, data, root_name, mapping)?;
+    let options = options.map(|o| o.clone()).unwrap_or_default();
+
+    let writer = BufWriter::with_capacity(65_536, PyFileWriter { fileobj });
+
+    write_xml_document(&value, &root_name, &options, writer)
+        .and_then(|mut w| w.flush())
+        .map_err(xml_write_error)
+}
+
+/// Converts the content of the root element to a `Value`.
+///
+/// With a `mapping`, the root and everything below it are renamed back to source names.
+fn depythonize_element(
+    py: Python<'_>,
+    data: &Bound<'_, PyDict>,
+    root_name: &str,
+    mapping: Option<&Bound<'_, KeyMapping>>,
+) -> PyResult<(String, Value)> {
+    let value = depythonize(data)?;
+    match mapping {
+        Some(mapping) => mapping.get().inverse_xml_element(py, root_name, value),
+        None => Ok((root_name.to_string(), value)),
+    }
+}
+
+/// Converts a serialization error into the matching Python exception.
+///
+/// Invalid data is reported as a `ValueError`, I/O failures keep their `OSError` subclass and
+/// exceptions raised by a Python file object are propagated unchanged.
+pub(crate) fn xml_write_error(e: std::io::Error) -> PyErr {
+    if e.kind() == std::io::ErrorKind::InvalidData {
+        return PyValueError::new_err(format!("XML generation error: {e}"));
+    }
+    PyErr::from(e)
+}
+
+/// Adapter forwarding the bytes to the `write()` method of a Python file-like object.
+struct PyFileWriter<'a, 'py> {
+    fileobj: &'a Bound<'py, PyAny>,
+}
+
+impl Write for PyFileWriter<'_, '_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let bytes = PyBytes::new(self.fileobj.py(), buf);
+        self.fileobj.call_method1("write", (bytes,))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.fileobj.hasattr("flush")? {
+            self.fileobj.call_method0("flush")?;
+        }
+        Ok(())
+    }
+}
+
+/// XML writer indenting with the configured line ending.
+///
+/// quick-xml only indents with `\n`, which can not be told apart from the newlines of text,
+/// attributes and comments once written. The indentation is written here instead, following the
+/// same rules: every event but text starts on a new line, unless it directly follows text.
+struct IndentWriter<W: Write> {
+    inner: Writer<W>,
+    /// Indentation of one level, empty when not pretty-printing.
+    indent: Vec<u8>,
+    line_ending: LineEnding,
+    depth: usize,
+    line_break: bool,
+}
+
+impl<W: Write> IndentWriter<W> {
+    fn new(out: W, options: &XmlWriterOptions) -> Self {
+        IndentWriter {
+            inner: Writer::new(out),
+            indent: vec![options.indent_char as u8; options.indent_size],
+            line_ending: options.line_ending,
+            depth: 0,
+            line_break: false,
+        }
+    }
+
+    fn write_event(&mut self, event: Event<'_>) -> std::io::Result<()> {
+        let inline = matches!(event, Event::Text(_) | Event::CData(_));
+        let opens = matches!(event, Event::Start(_));
+        if matches!(event, Event::End(_)) {
+            self.depth = self.depth.saturating_sub(1);
+        }
+        if !self.indent.is_empty() && !inline && self.line_break {
+            let out = self.inner.get_mut();
+            out.write_all(self.line_ending.as_bytes())?;
+            for _ in 0..self.depth {
+                out.write_all(&self.indent)?;
+            }
+        }
+        self.inner.write_event(event)?;
+        if opens {
+            self.depth += 1;
+        }
+        self.line_break = !inline;
+        Ok(())
+    }
+}
+
+/// Writes `value` as a complete XML document (declaration included) rooted at `root_name`.
+///
+/// The document is streamed into `out`, which is handed back once everything has been written.
+pub(crate) fn write_xml_document<W: Write>(
     value: &Value,
-    parent_name: &str,
-    writer: &mut Writer<Cursor<Vec<u8>>>,
-) -> Result<(), String> {
+    root_name: &str,
+    options: &XmlWriterOptions,
+    out: W,
+) -> std::io::Result<W> {
+    write_xml_nodes(std::iter::once((root_name, value)), options, out)
+}
+
+/// Writes a whole document given as `{root_name: root, ...}`, as returned by the parser.
+///
+/// Besides the root element, the map may hold `#comment` and `#pi` entries which are written
+/// before or after the root element depending on their index, or their position in the map.
+pub(crate) fn write_xml_tree<W: Write>(
+    document: &Map<String, Value>,
+    options: &XmlWriterOptions,
+    out: W,
+) -> std::io::Result<W> {
+    let root_count = document.keys().filter(|k| !is_misc_key(k)).count();
+    if root_count != 1 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Expected a single root element, found {root_count}"),
+        ));
+    }
+    write_xml_nodes(document.iter().map(|(k, v)| (k.as_str(), v)), options, out)
+}
+
+/// Reserved keys holding XML comments and processing instructions.
+fn is_misc_key(key: &str) -> bool {
+    key == "#comment" || key == "#pi"
+}
+
+fn write_xml_nodes<'a, W: Write>(
+    nodes: impl Iterator<Item = (&'a str, &'a Value)>,
+    options: &XmlWriterOptions,
+    out: W,
+) -> std::io::Result<W> {
+    let mut writer = IndentWriter::new(out, options);
+
+    writer.write_event(Event::Decl(BytesDecl::new(
+        &options.version,
+        options.encoding.as_deref(),
+        options.standalone.as_deref(),
+    )))?;
+
+    write_children("", nodes, &mut writer, &options.number_format)?;
+
+    let mut out = writer.inner.into_inner();
+    if options.trailing_newline {
+        out.write_all(options.line_ending.as_bytes())?;
+    }
+    Ok(out)
+}
+
+fn invalid_data(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+/// The items of a repeated element, or the value itself.
+fn items(value: &Value) -> &[Value] {
     match value {
-        Value::Object(obj) => {
-            let elem = BytesStart::new(parent_name);
-            writer
-                .write_event(Event::Start(elem))
-                .map_err(|e| e.to_string())?;
-            for (k, v) in obj {
-                value_to_xml(v, k, writer)?;
+        Value::Array(items) => items,
+        _ => std::slice::from_ref(value),
+    }
+}
+
+/// Position of a parsed comment or processing instruction among the child nodes of its parent.
+///
+/// Plain strings have none and are written where their key is.
+fn misc_index(value: &Value) -> Option<usize> {
+    value.get("index")?.as_u64().map(|index| index as usize)
+}
+
+/// Text of a comment or processing instruction, given as a string or as parsed.
+fn misc_text<'a>(key: &str, value: &'a Value) -> std::io::Result<&'a str> {
+    let text = match value {
+        Value::Object(node) => node.get("text"),
+        _ => Some(value),
+    };
+    text.and_then(Value::as_str).ok_or_else(|| match key {
+        "#comment" => invalid_data(format!("Invalid comment: {value}")),
+        _ => invalid_data(format!("Invalid processing instruction: {value}")),
+    })
+}
+
+/// Writes the child nodes of an element, or of the document when `parent_name` is empty.
+///
+/// Comments and processing instructions carrying an index are put back at their position
+/// among the other nodes, repeated elements included.
+fn write_children<'a, W: Write>(
+    parent_name: &str,
+    children: impl Iterator<Item = (&'a str, &'a Value)>,
+    writer: &mut IndentWriter<W>,
+    numbers: &NumberFormat,
+) -> std::io::Result<()> {
+    let children: Vec<(&str, &Value)> = children.collect();
+    let mut placed: Vec<(usize, &str, &Value)> = children
+        .iter()
+        .filter(|(key, _)| is_misc_key(key))
+        .flat_map(|(key, value)| items(value).iter().map(move |item| (*key, item)))
+        .filter_map(|(key, item)| Some((misc_index(item)?, key, item)))
+        .collect();
+    placed.sort_by_key(|(index, ..)| *index);
+    let mut placed = placed.into_iter().peekable();
+
+    let mut position = 0;
+    for (key, value) in children {
+        if key == "#text" {
+            let text = scalar_text(parent_name, value, numbers)
+                .ok_or_else(|| invalid_data(format!("Invalid text content: {value}")))?;
+            writer.write_event(Event::Text(BytesText::from_escaped(partial_escape(text))))?;
+            continue;
+        }
+        for item in items(value) {
+            if is_misc_key(key) && misc_index(item).is_some() {
+                continue;
+            }
+            while let Some((_, misc_key, misc)) = placed.next_if(|(index, ..)| *index <= position) {
+                value_to_xml(misc, misc_key, writer, numbers)?;
+                position += 1;
             }
-            writer
-                .write_event(Event::End(BytesEnd::new(parent_name)))
-                .map_err(|e| e.to_string())?;
+            value_to_xml(item, key, writer, numbers)?;
+            position += 1;
         }
+    }
+    for (_, misc_key, misc) in placed {
+        value_to_xml(misc, misc_key, writer, numbers)?;
+    }
+    Ok(())
+}
+
+/// Unescaped text content of a scalar value, `None` for objects and arrays.
+fn scalar_text<'a>(name: &str, value: &'a Value, numbers: &NumberFormat) -> Option<Cow<'a, str>> {
+    match value {
+        Value::String(s) => Some(Cow::Borrowed(s)),
+        Value::Number(n) => Some(Cow::Owned(numbers.format(name, n))),
+        Value::Bool(b) => Some(Cow::Borrowed(if *b { "true" } else { "false" })),
+        Value::Null => Some(Cow::Borrowed("")),
+        Value::Object(_) | Value::Array(_) => None,
+    }
+}
+
+fn value_to_xml<W: Write>(
+    value: &Value,
+    parent_name: &str,
+    writer: &mut IndentWriter<W>,
+    numbers: &NumberFormat,
+) -> std::io::Result<()> {
+    match value {
         Value::Array(arr) => {
             for item in arr {
-                value_to_xml(item, parent_name, writer)?;
+                value_to_xml(item, parent_name, writer, numbers)?;
             }
         }
-        Value::String(s) => {
-            writer
-                .write_event(Event::Start(BytesStart::new(parent_name)))
-                .map_err(|e| e.to_string())?;
-            writer
-                .write_event(Event::Text(BytesText::new(s)))
-                .map_err(|e| e.to_string())?;
-            writer
-                .write_event(Event::End(BytesEnd::new(parent_name)))
-                .map_err(|e| e.to_string())?;
-        }
-        Value::Number(n) => {
-            writer
-                .write_event(Event::Start(BytesStart::new(parent_name)))
-                .map_err(|e| e.to_string())?;
-            writer
-                .write_event(Event::Text(BytesText::new(&n.to_string())))
-                .map_err(|e| e.to_string())?;
-            writer
-                .write_event(Event::End(BytesEnd::new(parent_name)))
-                .map_err(|e| e.to_string())?;
-        }
-        Value::Bool(b) => {
-            writer
-                .write_event(Event::Start(BytesStart::new(parent_name)))
-                .map_err(|e| e.to_string())?;
-            writer
-                .write_event(Event::Text(BytesText::new(if *b {
-                    "true"
-                } else {
-                    "false"
-                })))
-                .map_err(|e| e.to_string())?;
-            writer
-                .write_event(Event::End(BytesEnd::new(parent_name)))
-                .map_err(|e| e.to_string())?;
+        _ if parent_name == "#comment" => {
+            let comment = misc_text(parent_name, value)?;
+            if comment.contains("--") {
+                return Err(invalid_data(format!(
+                    "Comments can not contain `--`: {comment:?}"
+                )));
+            }
+            writer.write_event(Event::Comment(BytesText::from_escaped(comment)))?;
+        }
+        _ if parent_name == "#pi" => {
+            let pi = misc_text(parent_name, value)?;
+            writer.write_event(Event::PI(BytesPI::new(pi)))?;
+        }
+        Value::Object(obj) => {
+            let mut elem = BytesStart::new(parent_name);
+            for (k, v) in obj {
+                if let Some(attr_name) = k.strip_prefix('@') {
+                    let attr_value = scalar_text(attr_name, v, numbers)
+                        .ok_or_else(|| invalid_data(format!("Invalid attribute `{k}`: {v}")))?;
+                    elem.push_attribute((attr_name, attr_value.as_ref()));
+                }
+            }
+
+            let mut children = obj.iter().filter(|(k, _)| !k.starts_with('@')).peekable();
+            if children.peek().is_none() && !obj.is_empty() {
+                // Attributes only
+                writer.write_event(Event::Empty(elem))?;
+                return Ok(());
+            }
+
+            writer.write_event(Event::Start(elem))?;
+            if obj.is_empty() {
+                // Keeps `<tag></tag>` on a single line when pretty-printing
+                writer.write_event(Event::Text(BytesText::new("")))?;
+            }
+            write_children(
+                parent_name,
+                children.map(|(k, v)| (k.as_str(), v)),
+                writer,
+                numbers,
+            )?;
+            writer.write_event(Event::End(BytesEnd::new(parent_name)))?;
         }
         Value::Null => {
-            writer
-                .write_event(Event::Empty(BytesStart::new(parent_name)))
-                .map_err(|e| e.to_string())?;
+            writer.write_event(Event::Empty(BytesStart::new(parent_name)))?;
+        }
+        Value::String(_) | Value::Number(_) | Value::Bool(_) => {
+            let text = scalar_text(parent_name, value, numbers).unwrap_or_default();
+            writer.write_event(Event::Start(BytesStart::new(parent_name)))?;
+            // Only `<`, `>` and `&` are escaped, quotes are written as-is like Ariane does
+            writer.write_event(Event::Text(BytesText::from_escaped(partial_escape(text))))?;
+            writer.write_event(Event::End(BytesEnd::new(parent_name)))?;
         }
     }
     Ok(())
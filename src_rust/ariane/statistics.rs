@@ -0,0 +1,312 @@
+use ahash::{AHashMap, AHashSet};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+use pythonize::{depythonize, pythonize};
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::fmt::Write;
+
+use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pyfunction, gen_stub_pymethods};
+
+use super::{geometry, survey, units};
+
+/// Lengths per group, in order of first appearance.
+#[derive(Clone, Debug, Default)]
+pub struct LengthTable(Vec<(String, f64)>);
+
+impl LengthTable {
+    fn add(&mut self, group: &str, length: f64) {
+        match self.0.iter_mut().find(|(name, _)| name == group) {
+            Some((_, total)) => *total += length,
+            None => self.0.push((group.to_string(), length)),
+        }
+    }
+}
+
+impl Serialize for LengthTable {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(name, length)| (name, length)))
+    }
+}
+
+/// Summary statistics of a survey. Lengths and depths are in the survey `unit`.
+#[gen_stub_pyclass]
+#[pyclass(module = "openspeleo_core._rust_lib.ariane", frozen)]
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SurveyStatistics {
+    #[pyo3(get)]
+    pub cave_name: Option<String>,
+    #[pyo3(get)]
+    pub unit: Option<String>,
+    #[pyo3(get)]
+    pub shot_count: usize,
+    #[pyo3(get)]
+    pub station_count: usize,
+    /// Independent loops of the survey network, closed by closure shots or shared stations.
+    #[pyo3(get)]
+    pub loop_count: usize,
+    /// Length of every shot.
+    #[pyo3(get)]
+    pub total_length: f64,
+    /// Length of the shots which are neither `Excluded` nor of type `CLOSURE`.
+    #[pyo3(get)]
+    pub surveyed_length: f64,
+    /// Largest horizontal distance between two stations.
+    #[pyo3(get)]
+    pub horizontal_extent: f64,
+    /// Difference between the deepest and the shallowest station.
+    #[pyo3(get)]
+    pub vertical_range: f64,
+    #[pyo3(get)]
+    pub max_depth: f64,
+    /// Surveyed length per `Section`.
+    pub length_by_section: LengthTable,
+    /// Surveyed length per explorer team.
+    pub length_by_explorer: LengthTable,
+    /// Surveyed length per survey date, oldest first.
+    pub length_by_date: LengthTable,
+}
+
+impl SurveyStatistics {
+    pub fn compute(document: &Value) -> Result<Self, String> {
+        let cave = survey::cave_file(document)?;
+        let shots = survey::shots(cave);
+
+        let mut stats = SurveyStatistics {
+            cave_name: survey::field_str(cave, "caveName").map(|name| name.into_owned()),
+            unit: survey::field_str(cave, "unit").map(|unit| unit.into_owned()),
+            shot_count: shots.len(),
+            ..SurveyStatistics::default()
+        };
+
+        for shot in &shots {
+            let length = shot
+                .get("Length")
+                .and_then(survey::scalar_f64)
+                .unwrap_or(0.0);
+            stats.total_length += length;
+            if is_excluded(shot) {
+                continue;
+            }
+            stats.surveyed_length += length;
+            let group = |field| survey::field_str(shot, field).unwrap_or_default();
+            stats.length_by_section.add(&group("Section"), length);
+            stats
+                .length_by_explorer
+                .add(&explorer_team(&group("Explorer")), length);
+            stats.length_by_date.add(&group("Date"), length);
+        }
+        stats.length_by_date.0.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let positions = geometry::station_positions(&shots);
+        stats.station_count = shots
+            .iter()
+            .filter(|shot| !is_closure_link(shot))
+            .filter_map(|shot| survey::station_id(shot, "ID"))
+            .collect::<AHashSet<_>>()
+            .len();
+        stats.loop_count = count_loops(&shots);
+
+        let depths = positions.values().map(|position| position.depth);
+        let max_depth = depths.clone().fold(f64::NEG_INFINITY, f64::max);
+        let min_depth = depths.fold(f64::INFINITY, f64::min);
+        if max_depth.is_finite() {
+            stats.max_depth = max_depth;
+            stats.vertical_range = max_depth - min_depth;
+        }
+        let points: Vec<(f64, f64)> = positions.values().map(|p| (p.x, p.y)).collect();
+        stats.horizontal_extent = diameter(points);
+        Ok(stats)
+    }
+
+    pub fn markdown(&self) -> String {
+        let unit = self.unit.as_deref().unwrap_or("");
+        let mut out = String::new();
+        match &self.cave_name {
+            Some(name) => {
+                let _ = writeln!(out, "# {name}\n");
+            }
+            None => out.push_str("# Survey statistics\n\n"),
+        }
+        out.push_str("| Statistic | Value |\n|---|---|\n");
+        for (name, value) in [
+            ("Surveyed length", self.surveyed_length),
+            ("Total length", self.total_length),
+            ("Horizontal extent", self.horizontal_extent),
+            ("Vertical range", self.vertical_range),
+            ("Maximum depth", self.max_depth),
+        ] {
+            let _ = writeln!(out, "| {name} | {value:.2} {unit} |");
+        }
+        let _ = writeln!(out, "| Shots | {} |", self.shot_count);
+        let _ = writeln!(out, "| Stations | {} |", self.station_count);
+        let _ = writeln!(out, "| Loops | {} |", self.loop_count);
+
+        for (title, group, table) in [
+            ("Length per section", "Section", &self.length_by_section),
+            ("Length per explorer", "Explorer", &self.length_by_explorer),
+            ("Length per date", "Date", &self.length_by_date),
+        ] {
+            if table.0.is_empty() {
+                continue;
+            }
+            let _ = write!(
+                out,
+                "\n## {title}\n\n| {group} | Length ({unit}) |\n|---|---|\n"
+            );
+            for (name, length) in &table.0 {
+                let _ = writeln!(out, "| {name} | {length:.2} |");
+            }
+        }
+        out
+    }
+}
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl SurveyStatistics {
+    #[getter]
+    fn length_by_section<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, &self.length_by_section)?)
+    }
+
+    #[getter]
+    fn length_by_explorer<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, &self.length_by_explorer)?)
+    }
+
+    #[getter]
+    fn length_by_date<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, &self.length_by_date)?)
+    }
+
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, self)?)
+    }
+
+    /// Renders the statistics as a `markdown` document or as `json`.
+    #[pyo3(signature = (format = "markdown"))]
+    fn report(&self, format: &str) -> PyResult<String> {
+        match format {
+            "markdown" => Ok(self.markdown()),
+            "json" => serde_json::to_string_pretty(self)
+                .map_err(|e| PyValueError::new_err(format!("Failed to serialize: {e}"))),
+            _ => Err(PyValueError::new_err(format!(
+                "Unsupported report format: `{format}`. Expected `markdown` or `json`."
+            ))),
+        }
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!(
+            "SurveyStatistics(surveyed_length={:.2}, stations={}, loops={})",
+            self.surveyed_length, self.station_count, self.loop_count
+        )
+    }
+}
+
+/// Computes the length, extent, depth and network statistics of a loaded survey, in its own
+/// unit or converted to `unit` (`m` or `ft`).
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (data, unit = None))]
+pub fn survey_statistics(
+    data: &Bound<'_, PyDict>,
+    unit: Option<&str>,
+) -> PyResult<SurveyStatistics> {
+    let mut data: Value = depythonize(data)?;
+    if let Some(unit) = unit {
+        let unit = units::LengthUnit::parse(unit).map_err(PyValueError::new_err)?;
+        units::convert_document(&mut data, unit).map_err(PyValueError::new_err)?;
+    }
+    SurveyStatistics::compute(&data).map_err(PyValueError::new_err)
+}
+
+fn is_closure(shot: &Map<String, Value>) -> bool {
+    survey::field_str(shot, "Type").is_some_and(|kind| kind == "CLOSURE")
+}
+
+/// A closure shot without length, which only links two existing stations.
+fn is_closure_link(shot: &Map<String, Value>) -> bool {
+    is_closure(shot)
+        && shot
+            .get("Length")
+            .and_then(survey::scalar_f64)
+            .is_none_or(|length| length == 0.0)
+}
+
+/// Shots left out of the surveyed length.
+fn is_excluded(shot: &Map<String, Value>) -> bool {
+    is_closure(shot) || survey::field_str(shot, "Excluded").is_some_and(|flag| flag == "true")
+}
+
+/// Explorer team of an `Explorer` field, which Ariane stores either as plain text or as
+/// `<Explorer>...</Explorer><Surveyor>...</Surveyor>`.
+fn explorer_team(text: &str) -> String {
+    let team = text
+        .split_once("<Explorer>")
+        .and_then(|(_, rest)| rest.split_once("</Explorer>"))
+        .map_or(text, |(team, _)| team);
+    team.trim().to_string()
+}
+
+/// Number of independent cycles of the station network.
+fn count_loops(shots: &[&Map<String, Value>]) -> usize {
+    let mut index: AHashMap<String, usize> = AHashMap::new();
+    let mut sets = survey::UnionFind::new(0);
+    let mut node = |station: String, sets: &mut survey::UnionFind| {
+        *index.entry(station).or_insert_with(|| sets.push())
+    };
+
+    let mut loops = 0;
+    for shot in shots {
+        let Some(id) = survey::station_id(shot, "ID") else {
+            continue;
+        };
+        for field in ["FromID", "ClosureToID"] {
+            let Some(other) = survey::station_id(shot, field).filter(|other| other != "-1") else {
+                continue;
+            };
+            let a = node(id.clone(), &mut sets);
+            let b = node(other, &mut sets);
+            if !sets.union(a, b) {
+                loops += 1;
+            }
+        }
+    }
+    loops
+}
+
+/// Largest distance between two points, compared over their convex hull.
+fn diameter(mut points: Vec<(f64, f64)>) -> f64 {
+    points.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    points.dedup();
+    if points.len() < 2 {
+        return 0.0;
+    }
+
+    // Andrew's monotone chain
+    let cross = |o: (f64, f64), a: (f64, f64), b: (f64, f64)| {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+    let mut hull: Vec<(f64, f64)> = Vec::with_capacity(points.len() * 2);
+    for pass in [points.clone(), points.into_iter().rev().collect()] {
+        let start = hull.len();
+        for point in pass {
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        hull.pop();
+    }
+
+    let mut best: f64 = 0.0;
+    for (i, a) in hull.iter().enumerate() {
+        for b in &hull[i + 1..] {
+            best = best.max((a.0 - b.0).hypot(a.1 - b.1));
+        }
+    }
+    best
+}
@@ -0,0 +1,208 @@
+use serde_json::{Map, Number, Value};
+use std::borrow::Cow;
+
+/// Returns the content of the `CaveFile` element.
+///
+/// Accepts the document returned by the loaders (`{"CaveFile": {...}}`) as well as the content
+/// of the root element itself.
+pub fn cave_file(document: &Value) -> Result<&Map<String, Value>, String> {
+    let Value::Object(map) = document else {
+        return Err("Expected a dict with a `CaveFile` root".to_string());
+    };
+    match map.get("CaveFile") {
+        Some(Value::Object(cave)) => Ok(cave),
+        Some(_) => Err("`CaveFile` must be a dict".to_string()),
+        None => Ok(map),
+    }
+}
+
+/// Mutable content of the `CaveFile` element, see [`cave_file`].
+pub fn cave_file_mut(document: &mut Value) -> Result<&mut Map<String, Value>, String> {
+    let Value::Object(map) = document else {
+        return Err("Expected a dict with a `CaveFile` root".to_string());
+    };
+    if !map.contains_key("CaveFile") {
+        return Ok(map);
+    }
+    match map.get_mut("CaveFile") {
+        Some(Value::Object(cave)) => Ok(cave),
+        _ => Err("`CaveFile` must be a dict".to_string()),
+    }
+}
+
+/// Items of a repeated element, which the parser stores as a single dict when it occurs once.
+pub fn repeated(value: Option<&Value>) -> Vec<&Map<String, Value>> {
+    match value {
+        Some(Value::Array(items)) => items.iter().filter_map(Value::as_object).collect(),
+        Some(Value::Object(item)) => vec![item],
+        _ => Vec::new(),
+    }
+}
+
+pub fn repeated_mut(value: Option<&mut Value>) -> Vec<&mut Map<String, Value>> {
+    match value {
+        Some(Value::Array(items)) => items.iter_mut().filter_map(Value::as_object_mut).collect(),
+        Some(Value::Object(item)) => vec![item],
+        _ => Vec::new(),
+    }
+}
+
+/// The `Data.SurveyData` shots, in document order.
+pub fn shots(cave: &Map<String, Value>) -> Vec<&Map<String, Value>> {
+    let data = cave.get("Data").and_then(Value::as_object);
+    repeated(data.and_then(|data| data.get("SurveyData")))
+}
+
+/// The `Layers.layerList` layers, in document order.
+pub fn layers(cave: &Map<String, Value>) -> Vec<&Map<String, Value>> {
+    let layers = cave.get("Layers").and_then(Value::as_object);
+    repeated(layers.and_then(|layers| layers.get("layerList")))
+}
+
+/// Text of a scalar value. Values are strings as parsed, but may have been cast in Python.
+pub fn scalar_str(value: &Value) -> Option<Cow<'_, str>> {
+    match value {
+        Value::String(text) => Some(Cow::Borrowed(text)),
+        Value::Number(number) => Some(Cow::Owned(number.to_string())),
+        Value::Bool(flag) => Some(Cow::Borrowed(if *flag { "true" } else { "false" })),
+        _ => None,
+    }
+}
+
+/// Numeric value of a scalar.
+pub fn scalar_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Rounds a computed value to 6 decimals, dropping the sign of zero.
+pub fn round(number: f64) -> f64 {
+    (number * 1e6).round() / 1e6 + 0.0
+}
+
+/// Text of a number rounded to 6 decimals, written as a float the way Ariane does (`3.0`).
+pub fn float_text(number: f64) -> String {
+    let text = round(number).to_string();
+    if text.contains('.') {
+        text
+    } else {
+        format!("{text}.0")
+    }
+}
+
+/// Replaces a numeric value, rounded to 6 decimals. Strings stay strings, written as floats
+/// the way Ariane does (`3.0`), and numbers stay numbers.
+pub fn set_number(value: &mut Value, number: f64) {
+    let number = round(number);
+    *value = match value {
+        Value::String(_) => Value::String(float_text(number)),
+        _ => Number::from_f64(number).map_or(Value::Null, Value::Number),
+    };
+}
+
+pub fn field_str<'a>(item: &'a Map<String, Value>, key: &str) -> Option<Cow<'a, str>> {
+    item.get(key).and_then(scalar_str)
+}
+
+/// Geodetic position of a shot, when recorded. Ariane writes `0.0` for unknown coordinates.
+pub fn shot_location(shot: &Map<String, Value>) -> Option<(f64, f64)> {
+    let latitude = shot.get("Latitude").and_then(scalar_f64)?;
+    let longitude = shot.get("Longitude").and_then(scalar_f64)?;
+    (latitude != 0.0 || longitude != 0.0).then_some((latitude, longitude))
+}
+
+/// Normalized station identifier of a field (`"78"`, `78` and `"78.0"` are the same station).
+pub fn station_id(item: &Map<String, Value>, key: &str) -> Option<String> {
+    let value = item.get(key)?;
+    match scalar_f64(value) {
+        Some(number) if number.fract() == 0.0 => Some(format!("{number:.0}")),
+        _ => scalar_str(value).map(|text| text.trim().to_string()),
+    }
+}
+
+/// Whether two scalars hold the same value, comparing numbers numerically (`5` and `5.0`).
+pub fn scalars_equal(a: &Value, b: &Value) -> bool {
+    if a == b {
+        return true;
+    }
+    match (scalar_f64(a), scalar_f64(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => match (scalar_str(a), scalar_str(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        },
+    }
+}
+
+/// `(year, month, day)` of an ISO 8601 date (`YYYY-MM-DD`), ignoring a time part.
+pub fn parse_date(text: &str) -> Option<(i64, u32, u32)> {
+    let text = text.trim();
+    let date = match text.split_once(['T', ' ']) {
+        Some((date, _)) => date,
+        None => text,
+    };
+    let mut parts = date.splitn(3, '-');
+    let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return None;
+    }
+    let (month, day) = (month.parse().ok()?, day.parse().ok()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some((year.parse().ok()?, month, day))
+}
+
+/// Current UTC date as `(year, month, day)`.
+pub fn today() -> (i64, u32, u32) {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64);
+    // Civil date from a day count, see http://howardhinnant.github.io/date_algorithms.html
+    let z = seconds.div_euclid(86_400) + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// Disjoint sets over `0..len`, used to group connected shots and stations.
+pub struct UnionFind {
+    parents: Vec<usize>,
+}
+
+impl UnionFind {
+    pub fn new(len: usize) -> Self {
+        UnionFind {
+            parents: (0..len).collect(),
+        }
+    }
+
+    /// Adds a set and returns its element.
+    pub fn push(&mut self) -> usize {
+        self.parents.push(self.parents.len());
+        self.parents.len() - 1
+    }
+
+    pub fn find(&mut self, mut i: usize) -> usize {
+        while self.parents[i] != i {
+            self.parents[i] = self.parents[self.parents[i]];
+            i = self.parents[i];
+        }
+        i
+    }
+
+    /// Joins the sets of `a` and `b`, returning `false` if they were already joined.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        self.parents[a] = b;
+        a != b
+    }
+}
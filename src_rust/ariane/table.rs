@@ -0,0 +1,243 @@
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+use pythonize::depythonize;
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::io::{BufWriter, Write};
+
+use pyo3_stub_gen::derive::gen_stub_pyfunction;
+
+use super::{geometry, survey};
+
+/// Columns exported when none are requested.
+pub const DEFAULT_COLUMNS: &[&str] = &[
+    "ID",
+    "FromID",
+    "Type",
+    "Section",
+    "Length",
+    "Azimut",
+    "Inclination",
+    "Depth",
+    "Left",
+    "Right",
+    "Up",
+    "Down",
+    "Date",
+    "Explorer",
+    "Comment",
+];
+
+/// Shot fields holding a measurement, exported as numbers.
+pub const NUMERIC_FIELDS: &[&str] = &[
+    "Length",
+    "Azimut",
+    "Inclination",
+    "Depth",
+    "DepthIn",
+    "Left",
+    "Right",
+    "Up",
+    "Down",
+    "Latitude",
+    "Longitude",
+];
+
+/// A column of the shot table: a shot field, or a value computed from the whole survey.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Column {
+    Field(String),
+    /// Position of the shot station towards the east, from the survey start.
+    X,
+    /// Position of the shot station towards the north, from the survey start.
+    Y,
+    /// Elevation of the shot station relative to the survey start (the opposite of its depth).
+    Z,
+    /// Running total of `Length`, in document order.
+    CumulativeLength,
+}
+
+impl Column {
+    /// Computed columns are lowercase, unlike Ariane fields.
+    pub fn parse(name: &str) -> Self {
+        match name {
+            "x" => Column::X,
+            "y" => Column::Y,
+            "z" => Column::Z,
+            "cumulative_length" => Column::CumulativeLength,
+            _ => Column::Field(name.to_string()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Column::Field(name) => name,
+            Column::X => "x",
+            Column::Y => "y",
+            Column::Z => "z",
+            Column::CumulativeLength => "cumulative_length",
+        }
+    }
+
+    /// Whether the values of the column are numbers.
+    pub fn is_numeric(&self) -> bool {
+        match self {
+            Column::Field(name) => NUMERIC_FIELDS.contains(&name.as_str()),
+            _ => true,
+        }
+    }
+}
+
+/// Parses the requested column names, or returns the default columns.
+pub fn parse_columns(names: Option<Vec<String>>) -> Result<Vec<Column>, String> {
+    let columns: Vec<Column> = match names {
+        Some(names) => names.iter().map(|name| Column::parse(name)).collect(),
+        None => DEFAULT_COLUMNS
+            .iter()
+            .map(|name| Column::parse(name))
+            .collect(),
+    };
+    if columns.is_empty() {
+        return Err("No column to export".to_string());
+    }
+    Ok(columns)
+}
+
+/// Value of a table cell.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Cell<'a> {
+    Empty,
+    Number(f64),
+    Text(Cow<'a, str>),
+}
+
+/// Flattens every shot of a loaded survey into a row of `columns`, in document order.
+///
+/// Numeric fields holding something else than a number keep their text. Computed values are
+/// rounded to 6 decimals; positions are in the survey unit and are empty for stations not
+/// connected to a survey start.
+pub fn shot_rows<'a>(
+    document: &'a Value,
+    columns: &[Column],
+) -> Result<Vec<Vec<Cell<'a>>>, String> {
+    let cave = survey::cave_file(document)?;
+    let shots = survey::shots(cave);
+    let positions = columns
+        .iter()
+        .any(|column| matches!(column, Column::X | Column::Y | Column::Z))
+        .then(|| geometry::station_positions(&shots));
+
+    let mut cumulative_length = 0.0;
+    let mut rows = Vec::with_capacity(shots.len());
+    for shot in &shots {
+        cumulative_length += shot
+            .get("Length")
+            .and_then(survey::scalar_f64)
+            .unwrap_or(0.0);
+        let position = positions.as_ref().and_then(|positions| {
+            survey::station_id(shot, "ID").and_then(|id| positions.get(&id).copied())
+        });
+        let row = columns
+            .iter()
+            .map(|column| match (column, position) {
+                (Column::Field(name), _) => field_cell(shot, name, column.is_numeric()),
+                (Column::X, Some(position)) => Cell::Number(survey::round(position.x)),
+                (Column::Y, Some(position)) => Cell::Number(survey::round(position.y)),
+                (Column::Z, Some(position)) => Cell::Number(survey::round(-position.depth)),
+                (Column::CumulativeLength, _) => Cell::Number(survey::round(cumulative_length)),
+                (_, None) => Cell::Empty,
+            })
+            .collect();
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+fn field_cell<'a>(shot: &'a Map<String, Value>, name: &str, numeric: bool) -> Cell<'a> {
+    let Some(value) = shot.get(name) else {
+        return Cell::Empty;
+    };
+    if numeric {
+        if let Some(number) = survey::scalar_f64(value) {
+            return Cell::Number(number);
+        }
+    }
+    match value {
+        Value::Null => Cell::Empty,
+        Value::Object(_) | Value::Array(_) => Cell::Text(Cow::Owned(value.to_string())),
+        _ => survey::scalar_str(value).map_or(Cell::Empty, Cell::Text),
+    }
+}
+
+/// Writes a CSV field, quoted when it holds the delimiter, a quote or a line break.
+fn write_csv_field<W: Write>(out: &mut W, text: &str, delimiter: char) -> std::io::Result<()> {
+    if text.contains([delimiter, '"', '\n', '\r']) {
+        write!(out, "\"{}\"", text.replace('"', "\"\""))
+    } else {
+        out.write_all(text.as_bytes())
+    }
+}
+
+/// Writes the table as CSV (RFC 4180), with a header row and CRLF line endings.
+pub fn write_csv<W: Write>(
+    mut out: W,
+    columns: &[Column],
+    rows: &[Vec<Cell<'_>>],
+    delimiter: char,
+) -> std::io::Result<W> {
+    let mut write_row = |cells: &mut dyn Iterator<Item = Cow<'_, str>>| -> std::io::Result<()> {
+        for (index, text) in cells.enumerate() {
+            if index > 0 {
+                write!(out, "{delimiter}")?;
+            }
+            write_csv_field(&mut out, &text, delimiter)?;
+        }
+        out.write_all(b"\r\n")
+    };
+
+    write_row(&mut columns.iter().map(|column| Cow::Borrowed(column.name())))?;
+    for row in rows {
+        write_row(&mut row.iter().map(|cell| match cell {
+            Cell::Empty => Cow::Borrowed(""),
+            Cell::Number(number) => Cow::Owned(number.to_string()),
+            Cell::Text(text) => Cow::Borrowed(text.as_ref()),
+        }))?;
+    }
+    Ok(out)
+}
+
+/// Writes the shots of a loaded survey to a CSV file at `path`, one row per shot.
+///
+/// `columns` are shot fields (`ID`, `Length`, `Comment`, ...) or the computed `x`, `y`, `z`
+/// (station position from the survey start, in the survey unit) and `cumulative_length`.
+/// Measurements are written as numbers and fields holding the delimiter, quotes or line
+/// breaks are quoted.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (data, path, columns = None, delimiter = ','))]
+pub fn export_shots_csv(
+    data: &Bound<'_, PyDict>,
+    path: &str,
+    columns: Option<Vec<String>>,
+    delimiter: char,
+) -> PyResult<()> {
+    if matches!(delimiter, '"' | '\n' | '\r') {
+        return Err(PyValueError::new_err(format!(
+            "Invalid delimiter: {delimiter:?}"
+        )));
+    }
+    let columns = parse_columns(columns).map_err(PyValueError::new_err)?;
+    let data: Value = depythonize(data)?;
+    let rows = shot_rows(&data, &columns).map_err(PyValueError::new_err)?;
+
+    let file = std::fs::File::create(path).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Failed to create file: {e}"))
+    })?;
+    write_csv(
+        BufWriter::with_capacity(65_536, file),
+        &columns,
+        &rows,
+        delimiter,
+    )
+    .and_then(|mut out| out.flush())
+    .map_err(|e| PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Failed to write file: {e}")))
+}
@@ -0,0 +1,491 @@
+use super::import::{self, ImportedSurvey, Reading, Trip};
+use super::units::LengthUnit;
+
+/// Commands allowed in a centerline that do not change the shots read.
+const IGNORED_COMMANDS: &[&str] = &[
+    "break",
+    "calibrate",
+    "cs",
+    "endgroup",
+    "equate",
+    "explo-date",
+    "explo-team",
+    "extend",
+    "fix",
+    "grade",
+    "group",
+    "infer",
+    "instrument",
+    "mark",
+    "sd",
+    "station",
+    "station-names",
+    "vthreshold",
+    "walls",
+];
+
+/// Field of a `data` line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    From,
+    To,
+    Length,
+    Compass,
+    Clino,
+    FromDepth,
+    ToDepth,
+    Left,
+    Right,
+    Up,
+    Down,
+    Ignore,
+    IgnoreAll,
+}
+
+impl Field {
+    fn parse(name: &str) -> Result<Self, String> {
+        Ok(match name {
+            "from" => Field::From,
+            "to" => Field::To,
+            "length" | "tape" => Field::Length,
+            "compass" | "bearing" => Field::Compass,
+            "clino" | "gradient" => Field::Clino,
+            "fromdepth" => Field::FromDepth,
+            "todepth" => Field::ToDepth,
+            "left" => Field::Left,
+            "right" => Field::Right,
+            "up" => Field::Up,
+            "down" => Field::Down,
+            "ignore" => Field::Ignore,
+            "ignoreall" => Field::IgnoreAll,
+            _ => return Err(format!("Unsupported data field: `{name}`")),
+        })
+    }
+}
+
+/// Factors converting the readings of a centerline to meters and degrees.
+#[derive(Clone, Copy, Debug)]
+struct Units {
+    length: f64,
+    dimensions: f64,
+    depth: f64,
+    compass: f64,
+    clino: f64,
+}
+
+impl Default for Units {
+    fn default() -> Self {
+        Units {
+            length: 1.0,
+            dimensions: 1.0,
+            depth: 1.0,
+            compass: 1.0,
+            clino: 1.0,
+        }
+    }
+}
+
+fn length_factor(unit: &str) -> Option<f64> {
+    Some(match unit {
+        "meter" | "meters" | "metre" | "metres" | "m" => 1.0,
+        "centimeter" | "centimeters" | "centimetre" | "centimetres" | "cm" => 0.01,
+        "feet" | "foot" | "ft" => 0.3048,
+        "inch" | "inches" | "in" => 0.0254,
+        "yard" | "yards" | "yd" | "yds" => 0.9144,
+        _ => return None,
+    })
+}
+
+fn angle_factor(unit: &str) -> Option<f64> {
+    Some(match unit {
+        "degree" | "degrees" | "deg" => 1.0,
+        "grad" | "grads" => 0.9,
+        "minute" | "minutes" | "min" => 1.0 / 60.0,
+        "mil" | "mils" => 360.0 / 6400.0,
+        _ => return None,
+    })
+}
+
+/// Splits a line into words, keeping quoted strings and bracketed values together and
+/// dropping the `#` comment.
+fn words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '#' => break,
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            text.push('"');
+                            chars.next();
+                        }
+                        Some('"') => break,
+                        Some(c) => text.push(c),
+                        None => return Err("Unterminated string".to_string()),
+                    }
+                }
+                word.get_or_insert_with(String::new).push_str(&text);
+            }
+            '[' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some(']') => break,
+                        Some(c) => text.push(c),
+                        None => return Err("Unterminated `[`".to_string()),
+                    }
+                }
+                word.get_or_insert_with(String::new).push_str(text.trim());
+            }
+            c if c.is_whitespace() => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// A `survey` block.
+struct Survey {
+    id: String,
+    title: Option<String>,
+}
+
+impl Survey {
+    fn name(&self) -> &str {
+        self.title.as_deref().unwrap_or(&self.id)
+    }
+}
+
+/// State of the `centerline` being read.
+#[derive(Default)]
+struct Centerline {
+    units: Units,
+    fields: Option<(bool, Vec<Field>)>,
+    splay: bool,
+}
+
+struct Parser<'a> {
+    file_name: &'a str,
+    surveys: Vec<Survey>,
+    name: Option<String>,
+    unit: Option<LengthUnit>,
+    centerline: Option<Centerline>,
+    trips: Vec<Trip>,
+    readings: Vec<Reading>,
+}
+
+impl Parser<'_> {
+    /// Name of a station of the current survey, qualified by the surveys nested in the first
+    /// one the way Therion refers to it (`1@inner.outer`).
+    fn station(&self, name: &str) -> String {
+        let path: Vec<&str> = self
+            .surveys
+            .iter()
+            .skip(1)
+            .rev()
+            .map(|survey| survey.id.as_str())
+            .collect();
+        match (path.is_empty(), name.contains('@')) {
+            (true, _) => name.to_string(),
+            (false, true) => format!("{name}.{}", path.join(".")),
+            (false, false) => format!("{name}@{}", path.join(".")),
+        }
+    }
+
+    fn section(&self) -> String {
+        self.surveys
+            .last()
+            .map_or(self.file_name, Survey::name)
+            .to_string()
+    }
+
+    fn trip(&mut self) -> &mut Trip {
+        if self.trips.is_empty() {
+            self.trips.push(Trip::default());
+        }
+        self.trips.last_mut().unwrap()
+    }
+
+    fn line(&mut self, words: &[String]) -> Result<(), String> {
+        let command = words[0].as_str();
+        match command {
+            "survey" => {
+                let id = words
+                    .get(1)
+                    .ok_or_else(|| "`survey` needs an identifier".to_string())?;
+                let title = words
+                    .iter()
+                    .position(|word| word == "-title")
+                    .and_then(|index| words.get(index + 1))
+                    .cloned();
+                if self.surveys.is_empty() {
+                    self.name = Some(title.clone().unwrap_or_else(|| id.clone()));
+                }
+                self.surveys.push(Survey {
+                    id: id.clone(),
+                    title,
+                });
+            }
+            "endsurvey" => {
+                self.surveys.pop();
+            }
+            "centerline" | "centreline" => {
+                self.centerline = Some(Centerline::default());
+                self.trips.push(Trip::default());
+            }
+            "endcenterline" | "endcentreline" => self.centerline = None,
+            _ if self.centerline.is_some() => self.centerline_line(words)?,
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn centerline_line(&mut self, words: &[String]) -> Result<(), String> {
+        let argument = |index: usize| {
+            words
+                .get(index)
+                .map(String::as_str)
+                .ok_or_else(|| format!("`{}` needs an argument", words[0]))
+        };
+        match words[0].as_str() {
+            "date" => {
+                // Only the start of a date range, without the time
+                let text = argument(1)?;
+                let date = text.split('@').next().unwrap_or(text);
+                let date =
+                    import::parse_date(date).ok_or_else(|| format!("`{text}` is not a date"))?;
+                self.trip().date.get_or_insert(date);
+            }
+            "team" => {
+                let name = argument(1)?.to_string();
+                self.trip().team.push(name);
+            }
+            "declination" => {
+                let value = argument(1)?;
+                let declination = if value == "-" {
+                    None
+                } else {
+                    let factor = match words.get(2) {
+                        Some(unit) => angle_factor(unit)
+                            .ok_or_else(|| format!("Unsupported angle unit: `{unit}`"))?,
+                        None => 1.0,
+                    };
+                    Some(number(value)? * factor)
+                };
+                self.trip().declination = declination;
+            }
+            "units" => self.units(&words[1..])?,
+            "data" => {
+                let style = argument(1)?;
+                let diving = match style {
+                    "normal" => false,
+                    "diving" => true,
+                    _ => return Err(format!("Unsupported data style: `{style}`")),
+                };
+                let fields = words[2..]
+                    .iter()
+                    .map(|name| Field::parse(name))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let centerline = self.centerline.as_mut().unwrap();
+                centerline.fields = Some((diving, fields));
+            }
+            "flags" => {
+                let not = words.get(1).is_some_and(|word| word == "not");
+                if words.iter().any(|word| word == "splay") {
+                    self.centerline.as_mut().unwrap().splay = !not;
+                }
+            }
+            command if IGNORED_COMMANDS.contains(&command) => (),
+            _ => self.shot(words)?,
+        }
+        Ok(())
+    }
+
+    /// `units <quantity>... [factor] <unit>`.
+    fn units(&mut self, words: &[String]) -> Result<(), String> {
+        let [quantities @ .., unit] = words else {
+            return Err("`units` needs a unit".to_string());
+        };
+        let (quantities, factor) = match quantities {
+            [quantities @ .., factor] if factor.parse::<f64>().is_ok() => {
+                (quantities, number(factor)?)
+            }
+            _ => (quantities, 1.0),
+        };
+        let units = &mut self.centerline.as_mut().unwrap().units;
+        for quantity in quantities {
+            let target = match quantity.as_str() {
+                "length" | "tape" => &mut units.length,
+                "left" | "right" | "up" | "down" | "dimensions" => &mut units.dimensions,
+                "depth" | "fromdepth" | "todepth" => &mut units.depth,
+                "compass" | "bearing" => &mut units.compass,
+                "clino" | "gradient" => &mut units.clino,
+                _ => continue,
+            };
+            let unit_factor = if matches!(
+                quantity.as_str(),
+                "compass" | "bearing" | "clino" | "gradient"
+            ) {
+                angle_factor(unit).ok_or_else(|| format!("Unsupported angle unit: `{unit}`"))?
+            } else {
+                let factor = length_factor(unit)
+                    .ok_or_else(|| format!("Unsupported length unit: `{unit}`"))?;
+                if matches!(quantity.as_str(), "length" | "tape") && self.unit.is_none() {
+                    self.unit = Some(if factor == 0.3048 {
+                        LengthUnit::Feet
+                    } else {
+                        LengthUnit::Meters
+                    });
+                }
+                factor
+            };
+            *target = factor * unit_factor;
+        }
+        Ok(())
+    }
+
+    fn shot(&mut self, words: &[String]) -> Result<(), String> {
+        let centerline = self.centerline.as_ref().unwrap();
+        let Some((diving, fields)) = &centerline.fields else {
+            return Err(format!("Unknown command: `{}`", words[0]));
+        };
+        let units = centerline.units;
+        let splay = centerline.splay;
+
+        let mut from = None;
+        let mut to = None;
+        let mut length = None;
+        let mut compass = None;
+        let mut clino = None;
+        let mut depths = (None, None);
+        let mut lrud = [None; 4];
+        let mut values = words.iter();
+        for field in fields {
+            if *field == Field::IgnoreAll {
+                break;
+            }
+            let value = values
+                .next()
+                .ok_or_else(|| format!("Expected {} values", fields.len()))?
+                .as_str();
+            let optional = |factor: f64| -> Result<Option<f64>, String> {
+                match value {
+                    "-" => Ok(None),
+                    _ => Ok(Some(number(value)? * factor)),
+                }
+            };
+            match field {
+                Field::From => from = Some(value),
+                Field::To => to = Some(value),
+                Field::Length => length = Some(number(value)? * units.length),
+                Field::Compass => compass = optional(units.compass)?,
+                Field::Clino => {
+                    clino = match value.to_ascii_lowercase().as_str() {
+                        "up" | "+v" => Some(90.0),
+                        "down" | "-v" => Some(-90.0),
+                        _ => optional(units.clino)?,
+                    }
+                }
+                Field::FromDepth => depths.0 = Some(number(value)? * units.depth),
+                Field::ToDepth => depths.1 = Some(number(value)? * units.depth),
+                Field::Left => lrud[0] = optional(units.dimensions)?,
+                Field::Right => lrud[1] = optional(units.dimensions)?,
+                Field::Up => lrud[2] = optional(units.dimensions)?,
+                Field::Down => lrud[3] = optional(units.dimensions)?,
+                Field::Ignore | Field::IgnoreAll => (),
+            }
+        }
+        let (Some(from), Some(to), Some(length)) = (from, to, length) else {
+            return Err("Data needs `from`, `to` and `length`".to_string());
+        };
+        let depths = match (*diving, depths) {
+            (true, (Some(from), Some(to))) => Some((from, to)),
+            (true, _) => return Err("Diving data needs `fromdepth` and `todepth`".to_string()),
+            (false, _) => None,
+        };
+
+        let reading = Reading {
+            from: self.station(from),
+            to: Some(self.station(to)),
+            length,
+            azimuth: import::normalize_azimuth(compass.unwrap_or(0.0)),
+            inclination: clino.unwrap_or(0.0),
+            depths,
+            lrud,
+            section: self.section(),
+            trip: self.trips.len() - 1,
+            ..Reading::default()
+        };
+        let reading = match (import::is_anonymous(from), import::is_anonymous(to)) {
+            (true, true) => return Err("The shot has no station".to_string()),
+            (true, false) => reading.reversed().into_splay(),
+            (false, true) => reading.into_splay(),
+            (false, false) if splay => reading.into_splay(),
+            (false, false) => reading,
+        };
+        self.readings.push(reading);
+        Ok(())
+    }
+}
+
+fn number(text: &str) -> Result<f64, String> {
+    text.parse()
+        .map_err(|_| format!("`{text}` is not a number"))
+}
+
+/// Encoding named by the `encoding` command, which has to come before any other command.
+pub fn declared_encoding(bytes: &[u8]) -> Option<String> {
+    let line = bytes
+        .split(|&b| b == b'\n')
+        .map(|line| String::from_utf8_lossy(line).trim().to_string())
+        .find(|line| !line.is_empty() && !line.starts_with('#'))?;
+    match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+        ["encoding", label, ..] => Some(label.to_string()),
+        _ => None,
+    }
+}
+
+/// Parses the centerlines of a Therion file, `\` continuing a line. Drawings and the other
+/// commands outside of centerlines are skipped.
+pub fn parse(text: &str, name: &str) -> Result<ImportedSurvey, String> {
+    let mut parser = Parser {
+        file_name: name,
+        surveys: Vec::new(),
+        name: None,
+        unit: None,
+        centerline: None,
+        trips: Vec::new(),
+        readings: Vec::new(),
+    };
+    let mut logical = String::new();
+    let mut first_line = 0;
+    for (index, line) in text.lines().enumerate() {
+        if logical.is_empty() {
+            first_line = index + 1;
+        }
+        if let Some(line) = line.strip_suffix('\\') {
+            logical.push_str(line);
+            logical.push(' ');
+            continue;
+        }
+        logical.push_str(line);
+        let line = std::mem::take(&mut logical);
+        let error = |e: String| format!("Line {first_line}: {e}");
+        let words = words(&line).map_err(error)?;
+        if !words.is_empty() {
+            parser.line(&words).map_err(error)?;
+        }
+    }
+
+    Ok(ImportedSurvey {
+        name: parser.name.unwrap_or_else(|| name.to_string()),
+        unit: parser.unit.unwrap_or(LengthUnit::Meters),
+        trips: parser.trips,
+        readings: parser.readings,
+    })
+}
@@ -0,0 +1,250 @@
+use super::import::{self, ImportedSurvey, Reading, Trip};
+use super::units::LengthUnit;
+
+/// Column of the shot lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Column {
+    From,
+    To,
+    Length,
+    Azimuth,
+    Inclination,
+    Left,
+    Right,
+    Up,
+    Down,
+    Comment,
+    Other,
+}
+
+/// Columns of the shot lines when the header does not name them.
+const DEFAULT_COLUMNS: &[Column] = &[
+    Column::From,
+    Column::To,
+    Column::Length,
+    Column::Azimuth,
+    Column::Inclination,
+];
+
+impl Column {
+    /// Column of a header name such as `tape`, `compass [deg]` or `Clino`.
+    fn parse(name: &str) -> Self {
+        let name: String = name
+            .trim()
+            .chars()
+            .take_while(char::is_ascii_alphabetic)
+            .collect::<String>()
+            .to_ascii_lowercase();
+        match name.as_str() {
+            "from" => Column::From,
+            "to" => Column::To,
+            "tape" | "length" | "distance" => Column::Length,
+            "compass" | "azimuth" | "bearing" => Column::Azimuth,
+            "clino" | "inclination" => Column::Inclination,
+            "left" => Column::Left,
+            "right" => Column::Right,
+            "up" => Column::Up,
+            "down" => Column::Down,
+            "comment" | "note" => Column::Comment,
+            _ => Column::Other,
+        }
+    }
+}
+
+/// Splits a CSV line, with fields optionally quoted (`""` being a quote inside a field).
+fn fields(line: &str) -> Vec<String> {
+    let separator = [',', ';', '\t']
+        .into_iter()
+        .find(|separator| line.contains(*separator))
+        .unwrap_or(',');
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            _ if c == separator && !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+        .iter()
+        .map(|field| field.trim().to_string())
+        .collect()
+}
+
+/// Header state read from the `#` comment lines.
+struct Header {
+    name: Option<String>,
+    trip: Trip,
+    unit: LengthUnit,
+    /// Angle unit, in degrees.
+    angle: f64,
+    columns: Vec<Column>,
+}
+
+impl Header {
+    /// Reads a comment line: `date`, `team`, `declination` and `units` entries, the column names
+    /// (`from, to, tape, ...`) or, first, the survey name.
+    fn read(&mut self, comment: &str) -> Result<(), String> {
+        let lower = comment.to_ascii_lowercase();
+        if lower.starts_with("topodroid") || lower.contains("created by") {
+            return Ok(());
+        }
+        let (key, value) = comment
+            .split_once([':', ',', ';', ' ', '\t'])
+            .map_or((comment, ""), |(key, value)| (key, value.trim()));
+        match key
+            .trim_end_matches([',', ';'])
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "date" => {
+                self.trip.date = Some(
+                    import::parse_date(value).ok_or_else(|| format!("`{value}` is not a date"))?,
+                );
+            }
+            "team" => self.trip.team.extend(
+                value
+                    .split([',', ';'])
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string),
+            ),
+            "declination" => {
+                let declination = value.split_whitespace().next().unwrap_or_default();
+                let declination: f64 = declination
+                    .parse()
+                    .map_err(|_| format!("`{declination}` is not a number"))?;
+                // As in PocketTopo, 0.00 is written when no declination is set
+                self.trip.declination = (declination != 0.0).then_some(declination);
+            }
+            "units" => {
+                if lower.contains("feet") || lower.split_whitespace().any(|word| word == "ft") {
+                    self.unit = LengthUnit::Feet;
+                }
+                if lower.contains("grad") {
+                    self.angle = 0.9;
+                }
+            }
+            "from" => {
+                self.columns = fields(comment)
+                    .iter()
+                    .map(|name| Column::parse(name))
+                    .collect()
+            }
+            _ => match import::parse_date(comment) {
+                Some(date) => self.trip.date = Some(date),
+                None if self.name.is_none() => self.name = Some(comment.to_string()),
+                None => (),
+            },
+        }
+        Ok(())
+    }
+
+    /// Parses a shot line. Splays have no `to` station (`-`, `.` or empty), or no `from` station
+    /// when shot towards the station.
+    fn shot(&self, values: &[String], section: &str) -> Result<Reading, String> {
+        let mut reading = Reading {
+            section: section.to_string(),
+            ..Reading::default()
+        };
+        let mut to = String::new();
+        let mut measures = [None; 3];
+        for (column, value) in self.columns.iter().zip(values) {
+            let number = || -> Result<Option<f64>, String> {
+                if value.is_empty() || value == "-" {
+                    return Ok(None);
+                }
+                value
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| format!("`{value}` is not a number"))
+            };
+            let meters = self.unit.meters();
+            match column {
+                Column::From => reading.from = value.clone(),
+                Column::To => to = value.clone(),
+                Column::Length => measures[0] = number()?.map(|length| length * meters),
+                Column::Azimuth => measures[1] = number()?.map(|azimuth| azimuth * self.angle),
+                Column::Inclination => measures[2] = number()?.map(|clino| clino * self.angle),
+                Column::Left => reading.lrud[0] = number()?.map(|left| left * meters),
+                Column::Right => reading.lrud[1] = number()?.map(|right| right * meters),
+                Column::Up => reading.lrud[2] = number()?.map(|up| up * meters),
+                Column::Down => reading.lrud[3] = number()?.map(|down| down * meters),
+                Column::Comment => {
+                    reading.comment = Some(value.clone()).filter(|comment| !comment.is_empty());
+                }
+                Column::Other => (),
+            }
+        }
+        let [Some(length), Some(azimuth), Some(inclination)] = measures else {
+            return Err("Expected a length, an azimuth and an inclination".to_string());
+        };
+        reading.length = length;
+        reading.azimuth = import::normalize_azimuth(azimuth);
+        reading.inclination = inclination;
+
+        match (
+            import::is_anonymous(&reading.from),
+            import::is_anonymous(&to),
+        ) {
+            (true, true) => Err("The shot has no station".to_string()),
+            (false, true) => Ok(reading),
+            (false, false) => Ok(Reading {
+                to: Some(to),
+                ..reading
+            }),
+            (true, false) => Ok(Reading {
+                to: Some(to),
+                ..reading
+            }
+            .reversed()
+            .into_splay()),
+        }
+    }
+}
+
+/// Parses a TopoDroid CSV export: a `#` commented header with the survey name, date, team,
+/// declination, units and column names, then one `from, to, tape, compass, clino` line per shot.
+pub fn parse(text: &str, name: &str) -> Result<ImportedSurvey, String> {
+    let mut header = Header {
+        name: None,
+        trip: Trip::default(),
+        unit: LengthUnit::Meters,
+        angle: 1.0,
+        columns: DEFAULT_COLUMNS.to_vec(),
+    };
+    let mut readings = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let error = |e: String| format!("Line {}: {e}", index + 1);
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix('#') {
+            header.read(comment.trim()).map_err(error)?;
+            continue;
+        }
+        let values = fields(line);
+        if values[0].eq_ignore_ascii_case("from") {
+            header.columns = values.iter().map(|name| Column::parse(name)).collect();
+            continue;
+        }
+        let section = header.name.as_deref().unwrap_or(name);
+        readings.push(header.shot(&values, section).map_err(error)?);
+    }
+
+    Ok(ImportedSurvey {
+        name: header.name.unwrap_or_else(|| name.to_string()),
+        unit: header.unit,
+        trips: vec![header.trip],
+        readings,
+    })
+}
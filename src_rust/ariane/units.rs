@@ -0,0 +1,130 @@
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+use pythonize::{depythonize, pythonize};
+use serde_json::{Map, Value};
+
+use pyo3_stub_gen::derive::gen_stub_pyfunction;
+
+use super::survey;
+
+/// Shot fields holding a length or a depth.
+const SHOT_LENGTH_FIELDS: &[&str] = &["Length", "Depth", "DepthIn", "Left", "Right", "Up", "Down"];
+
+/// `CaveFile` fields holding a length or a depth.
+const HEADER_LENGTH_FIELDS: &[&str] = &["firstStartAbsoluteElevation"];
+
+/// Value Ariane writes in `DepthIn` when no depth was recorded.
+const UNSET_DEPTH_IN: f64 = -1.0;
+
+/// Length unit of a survey, as written in the `unit` element of `CaveFile`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LengthUnit {
+    Meters,
+    Feet,
+}
+
+impl LengthUnit {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "m" | "meter" | "meters" | "metre" | "metres" => Ok(LengthUnit::Meters),
+            "ft" | "foot" | "feet" => Ok(LengthUnit::Feet),
+            _ => Err(format!(
+                "Unsupported unit: `{name}`. Expected `m` (meters) or `ft` (feet)."
+            )),
+        }
+    }
+
+    /// Value of the `unit` element.
+    pub fn code(self) -> &'static str {
+        match self {
+            LengthUnit::Meters => "m",
+            LengthUnit::Feet => "ft",
+        }
+    }
+
+    /// Length of the unit, in meters.
+    pub fn meters(self) -> f64 {
+        match self {
+            LengthUnit::Meters => 1.0,
+            LengthUnit::Feet => 0.3048,
+        }
+    }
+}
+
+/// Unit of a loaded survey, read from its `unit` element.
+pub fn survey_unit(cave: &Map<String, Value>) -> Result<LengthUnit, String> {
+    match survey::field_str(cave, "unit") {
+        Some(unit) => LengthUnit::parse(&unit),
+        None => Err("The survey has no `unit` element".to_string()),
+    }
+}
+
+/// Rewrites every length-bearing field of a loaded survey in `target`, along with its `unit`.
+pub fn convert_document(document: &mut Value, target: LengthUnit) -> Result<(), String> {
+    let cave = survey::cave_file_mut(document)?;
+    let source = survey_unit(cave)?;
+    if source != target {
+        let factor = source.meters() / target.meters();
+        for field in HEADER_LENGTH_FIELDS {
+            scale_field(cave, field, factor);
+        }
+        let shots = match cave.get_mut("Data") {
+            Some(Value::Object(data)) => data.get_mut("SurveyData"),
+            _ => None,
+        };
+        for shot in survey::repeated_mut(shots) {
+            convert_shot(shot, factor);
+        }
+    }
+    cave.insert("unit".to_string(), Value::String(target.code().to_string()));
+    Ok(())
+}
+
+fn convert_shot(shot: &mut Map<String, Value>, factor: f64) {
+    for field in SHOT_LENGTH_FIELDS {
+        let unset = *field == "DepthIn"
+            && shot.get(*field).and_then(survey::scalar_f64) == Some(UNSET_DEPTH_IN);
+        if !unset {
+            scale_field(shot, field, factor);
+        }
+    }
+
+    let radii = match shot.get_mut("Shape") {
+        Some(Value::Object(shape)) => match shape.get_mut("RadiusCollection") {
+            Some(Value::Object(radii)) => radii.get_mut("RadiusVector"),
+            _ => None,
+        },
+        _ => None,
+    };
+    for radius in survey::repeated_mut(radii) {
+        scale_field(radius, "length", factor);
+    }
+}
+
+/// Scales a numeric field in place, keeping strings as strings and numbers as numbers.
+fn scale_field(item: &mut Map<String, Value>, field: &str, factor: f64) {
+    let Some(value) = item.get_mut(field) else {
+        return;
+    };
+    if let Some(number) = survey::scalar_f64(value) {
+        survey::set_number(value, number * factor);
+    }
+}
+
+/// Returns a copy of a loaded survey with its lengths and depths converted to `target`.
+///
+/// The `unit` element is updated along with `Length`, `Depth`, `DepthIn`, the LRUD, the radius
+/// vectors of the shot shapes and `firstStartAbsoluteElevation`. Values loaded as strings stay
+/// strings. `target` is `m` (meters) or `ft` (feet). See `Survey.convert_units` for the typed
+/// model.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+pub fn convert_units<'py>(
+    py: Python<'py>,
+    data: &Bound<'py, PyDict>,
+    target: &str,
+) -> PyResult<Bound<'py, PyAny>> {
+    let target = LengthUnit::parse(target).map_err(PyValueError::new_err)?;
+    let mut data: Value = depythonize(data)?;
+    convert_document(&mut data, target).map_err(PyValueError::new_err)?;
+    Ok(pythonize(py, &data)?)
+}
@@ -0,0 +1,357 @@
+use ahash::{AHashMap, AHashSet};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+use pythonize::{depythonize, pythonize};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{borrow::Cow, collections::hash_map::Entry};
+
+use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pyfunction, gen_stub_pymethods};
+
+use super::survey;
+
+/// Station identifier Ariane uses for "no station" (`FromID` of a start shot, `ClosureToID`).
+const NO_STATION: &str = "-1";
+
+/// Largest depth change tolerated beyond the shot length, to absorb rounding of both values.
+const DEPTH_TOLERANCE: f64 = 0.05;
+
+const LRUD_FIELDS: &[&str] = &["Left", "Right", "Up", "Down"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The survey is inconsistent and cannot be processed reliably.
+    Error,
+    /// The survey is usable but likely contains a mistake.
+    Warning,
+}
+
+impl Severity {
+    fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// A problem found in a survey.
+#[gen_stub_pyclass]
+#[pyclass(module = "openspeleo_core._rust_lib.ariane", frozen)]
+#[derive(Clone, Debug, Serialize)]
+pub struct Diagnostic {
+    /// Stable identifier of the check (`duplicate-id`, `unknown-from-station`, ...).
+    #[pyo3(get)]
+    pub code: &'static str,
+    /// `error` or `warning`.
+    pub severity: Severity,
+    #[pyo3(get)]
+    pub message: String,
+    /// `ID` of the shot concerned, `None` when it has none.
+    #[pyo3(get)]
+    pub shot_id: Option<String>,
+    /// Field of the shot concerned, `None` when the whole shot is.
+    #[pyo3(get)]
+    pub field: Option<String>,
+}
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl Diagnostic {
+    #[getter]
+    fn severity(&self) -> &'static str {
+        self.severity.as_str()
+    }
+
+    #[getter]
+    fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, self)?)
+    }
+
+    fn __str__(&self) -> String {
+        let location = match (&self.shot_id, &self.field) {
+            (Some(id), Some(field)) => format!("shot {id}, {field}: "),
+            (Some(id), None) => format!("shot {id}: "),
+            _ => String::new(),
+        };
+        format!(
+            "{}[{}] {location}{}",
+            self.severity.as_str(),
+            self.code,
+            self.message
+        )
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!(
+            "Diagnostic(code={:?}, severity={:?}, shot_id={:?})",
+            self.code,
+            self.severity.as_str(),
+            self.shot_id
+        )
+    }
+}
+
+/// Checks a loaded survey for inconsistencies and likely mistakes.
+pub fn lint(document: &Value) -> Result<Vec<Diagnostic>, String> {
+    let cave = survey::cave_file(document)?;
+    let shots = survey::shots(cave);
+    let ids: Vec<Option<String>> = shots
+        .iter()
+        .map(|shot| survey::station_id(shot, "ID"))
+        .collect();
+    let today = survey::today();
+    let mut diagnostics = Vec::new();
+    let mut report = |severity, code, index: usize, field: Option<&str>, message| {
+        diagnostics.push(Diagnostic {
+            code,
+            severity,
+            message,
+            shot_id: ids[index].clone(),
+            field: field.map(str::to_string),
+        });
+    };
+
+    let mut stations: AHashMap<&str, &Map<String, Value>> = AHashMap::new();
+    for (index, id) in ids.iter().enumerate() {
+        let Some(id) = id else {
+            report(
+                Severity::Error,
+                "missing-id",
+                index,
+                Some("ID"),
+                "Shot has no `ID`".to_string(),
+            );
+            continue;
+        };
+        match stations.entry(id) {
+            Entry::Occupied(_) => report(
+                Severity::Error,
+                "duplicate-id",
+                index,
+                Some("ID"),
+                format!("Station `{id}` is the end of several shots"),
+            ),
+            Entry::Vacant(entry) => {
+                entry.insert(shots[index]);
+            }
+        }
+    }
+
+    for (index, shot) in shots.iter().enumerate() {
+        for (field, code) in [
+            ("FromID", "unknown-from-station"),
+            ("ClosureToID", "unknown-closure-station"),
+        ] {
+            let Some(station) = survey::station_id(shot, field) else {
+                continue;
+            };
+            if station != NO_STATION && !stations.contains_key(station.as_str()) {
+                report(
+                    Severity::Error,
+                    code,
+                    index,
+                    Some(field),
+                    format!("Station `{station}` does not exist"),
+                );
+            }
+        }
+
+        let mut number = |field: &str| -> Option<f64> {
+            let value = shot.get(field)?;
+            // "NaN" and "inf" parse as floats but would slip past every range check.
+            let number = survey::scalar_f64(value).filter(|n| n.is_finite());
+            if number.is_none() && !value.is_null() {
+                let text =
+                    survey::scalar_str(value).map_or_else(|| value.to_string(), Cow::into_owned);
+                report(
+                    Severity::Error,
+                    "invalid-number",
+                    index,
+                    Some(field),
+                    format!("`{text}` is not a number"),
+                );
+            }
+            number
+        };
+        let azimuth = number("Azimut");
+        let inclination = number("Inclination");
+        let length = number("Length");
+        let depth = number("Depth");
+        let lruds: Vec<(&str, Option<f64>)> = LRUD_FIELDS
+            .iter()
+            .map(|field| (*field, number(field)))
+            .collect();
+
+        if let Some(azimuth) = azimuth.filter(|a| !(0.0..=360.0).contains(a)) {
+            report(
+                Severity::Error,
+                "azimuth-out-of-range",
+                index,
+                Some("Azimut"),
+                format!("Azimuth {azimuth} is outside 0-360"),
+            );
+        }
+        if let Some(inclination) = inclination.filter(|i| !(-90.0..=90.0).contains(i)) {
+            report(
+                Severity::Error,
+                "inclination-out-of-range",
+                index,
+                Some("Inclination"),
+                format!("Inclination {inclination} is outside -90-90"),
+            );
+        }
+        if let Some(length) = length.filter(|l| *l < 0.0) {
+            report(
+                Severity::Error,
+                "negative-length",
+                index,
+                Some("Length"),
+                format!("Length {length} is negative"),
+            );
+        }
+        for (field, value) in lruds {
+            if let Some(value) = value.filter(|v| *v < 0.0) {
+                report(
+                    Severity::Error,
+                    "negative-lrud",
+                    index,
+                    Some(field),
+                    format!("{field} {value} is negative"),
+                );
+            }
+        }
+
+        // Closure shots only link two stations, their depth is left at 0
+        let is_closure = survey::field_str(shot, "Type").is_some_and(|kind| kind == "CLOSURE");
+        let from_depth = survey::station_id(shot, "FromID")
+            .filter(|_| !is_closure)
+            .and_then(|from| stations.get(from.as_str()))
+            .and_then(|from| from.get("Depth"))
+            .and_then(survey::scalar_f64);
+        let length = length.filter(|l| *l >= 0.0);
+        if let (Some(length), Some(depth), Some(from_depth)) = (length, depth, from_depth) {
+            let jump = (depth - from_depth).abs();
+            if jump > length + DEPTH_TOLERANCE {
+                report(
+                    Severity::Warning,
+                    "depth-jump",
+                    index,
+                    Some("Depth"),
+                    format!("Depth changes by {jump:.2} over a {length} long shot"),
+                );
+            }
+        }
+
+        if let Some(date) = survey::field_str(shot, "Date") {
+            match survey::parse_date(&date) {
+                Some(parsed) if parsed > today => report(
+                    Severity::Warning,
+                    "future-date",
+                    index,
+                    Some("Date"),
+                    format!("Date {date} is in the future"),
+                ),
+                Some(_) => (),
+                None => report(
+                    Severity::Warning,
+                    "invalid-date",
+                    index,
+                    Some("Date"),
+                    format!("`{date}` is not a YYYY-MM-DD date"),
+                ),
+            }
+        }
+    }
+
+    for (index, size) in orphan_components(&shots, &ids, &stations) {
+        report(
+            Severity::Warning,
+            "orphan-component",
+            index,
+            None,
+            format!("{size} shots are not connected to the survey start"),
+        );
+    }
+
+    Ok(diagnostics)
+}
+
+/// Groups of shots not connected to a start shot (`FromID` of `-1`), or to the first shot if
+/// there is none. Returns the index of the first shot of each group and its size.
+fn orphan_components(
+    shots: &[&Map<String, Value>],
+    ids: &[Option<String>],
+    stations: &AHashMap<&str, &Map<String, Value>>,
+) -> Vec<(usize, usize)> {
+    let index_of: AHashMap<&str, usize> = ids
+        .iter()
+        .enumerate()
+        .rev()
+        .filter_map(|(index, id)| Some((id.as_deref()?, index)))
+        .collect();
+    let mut sets = survey::UnionFind::new(shots.len());
+
+    for (index, shot) in shots.iter().enumerate() {
+        for field in ["FromID", "ClosureToID"] {
+            let other = survey::station_id(shot, field)
+                .filter(|station| stations.contains_key(station.as_str()))
+                .and_then(|station| index_of.get(station.as_str()).copied());
+            if let Some(other) = other {
+                sets.union(index, other);
+            }
+        }
+        // Duplicated stations join the component of the first shot ending there
+        if let Some(first) = ids[index]
+            .as_deref()
+            .and_then(|id| index_of.get(id).copied())
+        {
+            sets.union(index, first);
+        }
+    }
+
+    let mut starts: AHashSet<usize> = shots
+        .iter()
+        .enumerate()
+        .filter(|(_, shot)| survey::station_id(shot, "FromID").as_deref() == Some(NO_STATION))
+        .map(|(index, _)| sets.find(index))
+        .collect();
+    if starts.is_empty() && !shots.is_empty() {
+        starts.insert(sets.find(0));
+    }
+
+    let mut components: Vec<(usize, usize)> = Vec::new();
+    let mut position: AHashMap<usize, usize> = AHashMap::new();
+    for index in 0..shots.len() {
+        let root = sets.find(index);
+        if starts.contains(&root) {
+            continue;
+        }
+        match position.get(&root) {
+            Some(&i) => components[i].1 += 1,
+            None => {
+                position.insert(root, components.len());
+                components.push((index, 1));
+            }
+        }
+    }
+    components
+}
+
+/// Checks a loaded survey and returns the problems found, in document order.
+///
+/// Errors flag inconsistent data: duplicate or missing `ID`s, `FromID`/`ClosureToID`
+/// referencing a missing station, non-numeric values, azimuths outside 0-360, inclinations
+/// outside ±90 and negative lengths or LRUD. Warnings flag likely mistakes: depth changes
+/// larger than the shot length, future or malformed dates and groups of shots not connected
+/// to the survey start.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+pub fn validate(data: &Bound<'_, PyDict>) -> PyResult<Vec<Diagnostic>> {
+    let data: Value = depythonize(data)?;
+    lint(&data).map_err(PyValueError::new_err)
+}
@@ -0,0 +1,165 @@
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+use pythonize::depythonize;
+use serde_json::Value;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use pyo3_stub_gen::derive::gen_stub_pyfunction;
+
+use super::archive::{copy_attachments, open_archive, DATA_ENTRY};
+use super::declination;
+use super::serialize::{write_xml_tree, xml_write_error, XmlWriterOptions};
+use crate::mapping::key_mapping::KeyMapping;
+
+/// Writes a dict to an Ariane TML file (a zip archive holding a "Data.xml" entry).
+///
+/// The XML is streamed straight into the compressed zip entry.
+///
+/// # Arguments
+///
+/// * `data`: The dict to save, with a single root key (as returned by the loader). Comments and
+///   processing instructions kept by the loader are written back around the root element.
+/// * `path`: The path of the TML file to create.
+/// * `options`: The XML formatting options, defaults to the Ariane layout.
+/// * `mapping`: `KeyMapping` whose inverse renames the keys back to Ariane names.
+/// * `north`: Write the azimuths relative to this north (`true` or `magnetic`), see
+///   `correct_declination`.
+/// * `source`: TML file whose other entries (images, backgrounds, ...) are copied unchanged
+///   after "Data.xml", usually the file the survey was loaded from. It may be `path` itself.
+///   Without it, only "Data.xml" is written.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (data, path, options = None, mapping = None, north = None, source = None))]
+pub fn dict_to_ariane_tml_file(
+    py: Python<'_>,
+    data: &Bound<'_, PyDict>,
+    path: &str,
+    options: Option<PyRef<'_, XmlWriterOptions>>,
+    mapping: Option<&Bound<'_, KeyMapping>>,
+    north: Option<&str>,
+    source: Option<&str>,
+) -> PyResult<()> {
+    let (document, options) = prepare_document(py, data, options, mapping, north)?;
+    let mut source = source.map(open_archive).transpose()?;
+
+    write_atomically(path, |file| {
+        let mut archive = ZipWriter::new(file);
+        archive
+            .start_file(
+                DATA_ENTRY,
+                SimpleFileOptions::default().compression_method(CompressionMethod::Deflated),
+            )
+            .map_err(|e| {
+                PyErr::new::<pyo3::exceptions::PyIOError, _>(format!(
+                    "Failed to create file in zip archive: {e}"
+                ))
+            })?;
+
+        write_xml_tree(&document, &options, &mut archive).map_err(xml_write_error)?;
+        if let Some(source) = source.as_mut() {
+            copy_attachments(source, &mut archive)?;
+        }
+
+        archive.finish().map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyIOError, _>(format!(
+                "Failed to write zip archive: {e}"
+            ))
+        })?;
+        Ok(())
+    })
+}
+
+/// Writes a dict to an Ariane TMLU file, the uncompressed XML of a TML file.
+///
+/// Arguments are as in `dict_to_ariane_tml_file`.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.ariane")]
+#[pyfunction]
+#[pyo3(signature = (data, path, options = None, mapping = None, north = None))]
+pub fn dict_to_ariane_tmlu_file(
+    py: Python<'_>,
+    data: &Bound<'_, PyDict>,
+    path: &str,
+    options: Option<PyRef<'_, XmlWriterOptions>>,
+    mapping: Option<&Bound<'_, KeyMapping>>,
+    north: Option<&str>,
+) -> PyResult<()> {
+    let (document, options) = prepare_document(py, data, options, mapping, north)?;
+
+    write_atomically(path, |file| {
+        write_xml_tree(&document, &options, file).map_err(xml_write_error)?;
+        Ok(())
+    })
+}
+
+/// Writes `path` through a temporary file in the same directory, renamed over `path` only once
+/// `write` succeeded, so that a failed save leaves the existing file untouched.
+fn write_atomically(
+    path: &str,
+    write: impl FnOnce(&mut BufWriter<File>) -> PyResult<()>,
+) -> PyResult<()> {
+    let target = Path::new(path);
+    let file_name = target.file_name().ok_or_else(|| {
+        PyErr::new::<pyo3::exceptions::PyIOError, _>(format!(
+            "Failed to create file: `{path}` is not a file path"
+        ))
+    })?;
+    let temporary = target.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    let file = File::create(&temporary).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Failed to create file: {e}"))
+    })?;
+    let mut writer = BufWriter::with_capacity(65_536, file);
+    let result = write(&mut writer)
+        // The file is closed before the rename.
+        .and_then(|()| {
+            writer
+                .into_inner()
+                .map(drop)
+                .map_err(|e| e.into_error().into())
+        })
+        .and_then(|()| {
+            std::fs::rename(&temporary, target).map_err(|e| {
+                PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Failed to replace file: {e}"))
+            })
+        });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temporary);
+    }
+    result
+}
+
+/// Converts the dict to save back to Ariane names and `north`, with the options to write it.
+fn prepare_document(
+    py: Python<'_>,
+    data: &Bound<'_, PyDict>,
+    options: Option<PyRef<'_, XmlWriterOptions>>,
+    mapping: Option<&Bound<'_, KeyMapping>>,
+    north: Option<&str>,
+) -> PyResult<(serde_json::Map<String, Value>, XmlWriterOptions)> {
+    let mut value: Value = depythonize(data)?;
+    if let Some(mapping) = mapping {
+        value = mapping.get().inverse_xml(py, value)?;
+    }
+    if let Some(north) = north {
+        let north = declination::North::parse(north).map_err(PyValueError::new_err)?;
+        declination::correct_document(&mut value, north, None, None)
+            .map_err(PyValueError::new_err)?;
+    }
+    let options = options
+        .map(|o| o.clone())
+        .unwrap_or_else(XmlWriterOptions::ariane_preset);
+
+    let Value::Object(document) = value else {
+        return Err(PyValueError::new_err(
+            "Expected a dict with a single root key (e.g. `CaveFile`)",
+        ));
+    };
+    Ok((document, options))
+}
@@ -0,0 +1,50 @@
+use pyo3::{
+    prelude::*,
+    types::{PyByteArray, PyBytes, PyDict, PyList, PyMapping, PySequence, PyString, PyTuple},
+};
+
+/// A container which is not an exact `dict` or `list`, walked in generic mode.
+pub enum Container<'py> {
+    /// Items of a `dict` subclass or of a `collections.abc.Mapping`, rebuilt as a `dict`.
+    Mapping(Vec<(Bound<'py, PyAny>, Bound<'py, PyAny>)>),
+    /// Items of a `list` subclass or of a `collections.abc.Sequence`, rebuilt as a `list`.
+    Sequence(Vec<Bound<'py, PyAny>>),
+    /// Items of a tuple, rebuilt as a `tuple`.
+    Tuple(Vec<Bound<'py, PyAny>>),
+}
+
+impl<'py> Container<'py> {
+    /// Classifies `value`, returning `None` for scalars. Strings and bytes are scalars even
+    /// though they are sequences.
+    pub fn of(value: &Bound<'py, PyAny>) -> PyResult<Option<Self>> {
+        if value.is_instance_of::<PyString>()
+            || value.is_instance_of::<PyBytes>()
+            || value.is_instance_of::<PyByteArray>()
+        {
+            return Ok(None);
+        }
+
+        if let Ok(dict) = value.cast::<PyDict>() {
+            return Ok(Some(Container::Mapping(dict.iter().collect())));
+        }
+        if let Ok(tuple) = value.cast::<PyTuple>() {
+            return Ok(Some(Container::Tuple(tuple.iter().collect())));
+        }
+        if let Ok(list) = value.cast::<PyList>() {
+            return Ok(Some(Container::Sequence(list.iter().collect())));
+        }
+        if let Ok(mapping) = value.cast::<PyMapping>() {
+            let items = mapping
+                .items()?
+                .iter()
+                .map(|item| item.extract())
+                .collect::<PyResult<_>>()?;
+            return Ok(Some(Container::Mapping(items)));
+        }
+        if let Ok(sequence) = value.cast::<PySequence>() {
+            let items = sequence.try_iter()?.collect::<PyResult<_>>()?;
+            return Ok(Some(Container::Sequence(items)));
+        }
+        Ok(None)
+    }
+}
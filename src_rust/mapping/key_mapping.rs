@@ -0,0 +1,408 @@
+use ahash::AHashMap;
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyDict, PyList, PyString, PyTuple},
+};
+
+use serde_json::{Map, Value};
+
+use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pymethods};
+
+use super::containers::Container;
+use super::path_pattern::{self, MatchState, PathStep, ScopedTable, Segment};
+
+/// A mapping rule as given by the user.
+struct Rule {
+    source: String,
+    target: String,
+    /// Path the rule is restricted to, `None` for a rule applying everywhere.
+    scope: Option<Vec<Segment>>,
+}
+
+impl Rule {
+    /// The rule key as written by the user, e.g. `**.RadiusVector.length`.
+    fn pattern(&self) -> String {
+        match &self.scope {
+            Some(scope) => {
+                let scope: Vec<String> = scope.iter().map(ToString::to_string).collect();
+                format!("{}.{}", scope.join("."), self.source)
+            }
+            None => self.source.clone(),
+        }
+    }
+
+    /// Whether both rules can apply to keys of the same dict.
+    fn may_overlap(&self, other: &Rule) -> bool {
+        match (&self.scope, &other.scope) {
+            (Some(a), Some(b)) if path_pattern::is_literal(a) && path_pattern::is_literal(b) => {
+                a == b
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Lookup table for one direction of the mapping, with the new names created once.
+struct KeyTable {
+    names: ScopedTable<Py<PyString>>,
+    /// Whether scopes are matched against the renamed path rather than the input path.
+    ///
+    /// Scopes are written with source names, which the inverse direction produces.
+    match_output: bool,
+}
+
+impl KeyTable {
+    fn new(py: Python<'_>, rules: &[Rule], inverse: bool) -> Self {
+        let names = ScopedTable::new(rules.iter().map(|rule| {
+            let (from, to) = match inverse {
+                false => (&rule.source, &rule.target),
+                true => (&rule.target, &rule.source),
+            };
+            (
+                rule.scope.clone(),
+                from.clone(),
+                PyString::new(py, to).unbind(),
+            )
+        }));
+
+        KeyTable {
+            names,
+            match_output: inverse,
+        }
+    }
+}
+
+/// Compiled, invertible key mapping.
+///
+/// Keys are either plain names, renamed wherever they appear, or path patterns restricting the
+/// rule to a context: `Data.SurveyData.*.Length` or `**.RadiusVector.length`. `*` matches one
+/// key, `**` any number of keys, and list indices may be omitted. Path patterns are written
+/// with source names and take precedence over plain names.
+///
+/// The mapping is checked at construction: two keys mapping to the same name would make the
+/// inverse ambiguous and are rejected. Applying the mapping raises a `ValueError` instead of
+/// silently overwriting a value when two keys of the same dict collide after mapping.
+#[gen_stub_pyclass]
+#[pyclass(module = "openspeleo_core._rust_lib.mapping", frozen)]
+pub struct KeyMapping {
+    rules: Vec<Rule>,
+    forward: KeyTable,
+    inverse: KeyTable,
+}
+
+impl KeyMapping {
+    pub fn from_pairs(py: Python<'_>, pairs: Vec<(String, String)>) -> PyResult<Self> {
+        let rules = pairs
+            .into_iter()
+            .map(|(pattern, target)| {
+                let (scope, source) =
+                    path_pattern::parse_key_pattern(&pattern).map_err(PyValueError::new_err)?;
+                Ok(Rule {
+                    source,
+                    target,
+                    scope,
+                })
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let mut by_target: AHashMap<&str, Vec<&Rule>> = AHashMap::with_capacity(rules.len());
+        for rule in &rules {
+            let previous = by_target.entry(&rule.target).or_default();
+            if let Some(other) = previous
+                .iter()
+                .find(|other| other.source != rule.source && other.may_overlap(rule))
+            {
+                return Err(PyValueError::new_err(format!(
+                    "Mapping is not invertible: `{}` and `{}` both map to `{}`",
+                    other.pattern(),
+                    rule.pattern(),
+                    rule.target
+                )));
+            }
+            previous.push(rule);
+        }
+
+        let forward = KeyTable::new(py, &rules, false);
+        let inverse = KeyTable::new(py, &rules, true);
+        Ok(KeyMapping {
+            rules,
+            forward,
+            inverse,
+        })
+    }
+
+    /// Rewrites a scope written with source names into target names.
+    fn translate_scope(&self, py: Python<'_>, scope: &[Segment]) -> PyResult<Vec<Segment>> {
+        let names = &self.forward.names;
+        let mut states = names.start();
+        let mut translated = Vec::with_capacity(scope.len());
+
+        for segment in scope {
+            match segment {
+                Segment::Key(key) => {
+                    let name = match names.get(&states, key) {
+                        Some(name) => name.bind(py).to_str()?.to_string(),
+                        None => key.clone(),
+                    };
+                    translated.push(Segment::Key(name));
+                    states = names.step(&states, PathStep::Key(key));
+                }
+                Segment::Any => {
+                    translated.push(Segment::Any);
+                    states = names.step(&states, PathStep::Other);
+                }
+                Segment::AnyDepth => translated.push(Segment::AnyDepth),
+            }
+        }
+        Ok(translated)
+    }
+}
+
+impl KeyMapping {
+    /// Renames the element and attribute names of a parsed XML document.
+    pub fn forward_xml(&self, py: Python<'_>, document: Value) -> PyResult<Value> {
+        rename_xml(py, document, &self.forward, &self.forward.names.start())
+    }
+
+    /// Renames the element and attribute names of a parsed XML document back to source names.
+    pub fn inverse_xml(&self, py: Python<'_>, document: Value) -> PyResult<Value> {
+        rename_xml(py, document, &self.inverse, &self.inverse.names.start())
+    }
+
+    /// Same as [`KeyMapping::inverse_xml`] for the content of the root element `root_name`,
+    /// returning the renamed root name along with its content.
+    pub fn inverse_xml_element(
+        &self,
+        py: Python<'_>,
+        root_name: &str,
+        content: Value,
+    ) -> PyResult<(String, Value)> {
+        let states = self.inverse.names.start();
+        rename_xml_element(py, &self.inverse, &states, root_name.to_string(), content)
+    }
+}
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl KeyMapping {
+    #[new]
+    fn new(py: Python<'_>, mapping: &Bound<'_, PyDict>) -> PyResult<Self> {
+        let pairs = mapping
+            .iter()
+            .map(|(source, target)| Ok((source.extract()?, target.extract()?)))
+            .collect::<PyResult<_>>()?;
+        Self::from_pairs(py, pairs)
+    }
+
+    /// Renames the keys of `data` from source to target names, recursing into dicts and lists.
+    ///
+    /// `generic_containers` also walks subclasses, tuples and abstract containers, as
+    /// `apply_key_mapping` does.
+    #[pyo3(signature = (data, generic_containers = false))]
+    fn forward(
+        &self,
+        py: Python<'_>,
+        data: &Bound<'_, PyAny>,
+        generic_containers: bool,
+    ) -> PyResult<Py<PyAny>> {
+        let states = self.forward.names.start();
+        map_keys(py, data, &self.forward, &states, generic_containers)
+    }
+
+    /// Renames the keys of `data` from target back to source names.
+    #[pyo3(signature = (data, generic_containers = false))]
+    fn inverse(
+        &self,
+        py: Python<'_>,
+        data: &Bound<'_, PyAny>,
+        generic_containers: bool,
+    ) -> PyResult<Py<PyAny>> {
+        let states = self.inverse.names.start();
+        map_keys(py, data, &self.inverse, &states, generic_containers)
+    }
+
+    /// Returns the mapping going the other way.
+    ///
+    /// Path patterns are rewritten with the target names of the keys they go through.
+    fn inverted(&self, py: Python<'_>) -> PyResult<Self> {
+        let pairs = self
+            .rules
+            .iter()
+            .map(|rule| {
+                let inverted = Rule {
+                    source: rule.target.clone(),
+                    target: rule.source.clone(),
+                    scope: match &rule.scope {
+                        Some(scope) => Some(self.translate_scope(py, scope)?),
+                        None => None,
+                    },
+                };
+                Ok((inverted.pattern(), inverted.target))
+            })
+            .collect::<PyResult<_>>()?;
+        Self::from_pairs(py, pairs)
+    }
+
+    /// Returns the mapping as a `{pattern: target}` dict.
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        for rule in &self.rules {
+            dict.set_item(rule.pattern(), &rule.target)?;
+        }
+        Ok(dict)
+    }
+
+    fn __len__(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!("KeyMapping({} keys)", self.rules.len())
+    }
+}
+
+fn map_keys(
+    py: Python<'_>,
+    data: &Bound<'_, PyAny>,
+    table: &KeyTable,
+    states: &[MatchState],
+    generic: bool,
+) -> PyResult<Py<PyAny>> {
+    if let Ok(dict) = data.cast_exact::<PyDict>() {
+        map_dict(py, dict.iter(), table, states, generic)
+    } else if let Ok(list) = data.cast_exact::<PyList>() {
+        let result = PyList::empty(py);
+        let item_states = table.names.step(states, PathStep::Index);
+        for item in list {
+            result.append(map_keys(py, &item, table, &item_states, generic)?)?;
+        }
+        Ok(result.into_any().unbind())
+    } else if generic {
+        let map_items = |items: Vec<Bound<'_, PyAny>>| {
+            let item_states = table.names.step(states, PathStep::Index);
+            items
+                .iter()
+                .map(|item| map_keys(py, item, table, &item_states, generic))
+                .collect::<PyResult<Vec<_>>>()
+        };
+        match Container::of(data)? {
+            Some(Container::Mapping(items)) => {
+                map_dict(py, items.into_iter(), table, states, generic)
+            }
+            Some(Container::Sequence(items)) => {
+                Ok(PyList::new(py, map_items(items)?)?.into_any().unbind())
+            }
+            Some(Container::Tuple(items)) => {
+                Ok(PyTuple::new(py, map_items(items)?)?.into_any().unbind())
+            }
+            None => Ok(data.clone().unbind()),
+        }
+    } else {
+        Ok(data.clone().unbind())
+    }
+}
+
+fn map_dict<'py>(
+    py: Python<'py>,
+    items: impl Iterator<Item = (Bound<'py, PyAny>, Bound<'py, PyAny>)>,
+    table: &KeyTable,
+    states: &[MatchState],
+    generic: bool,
+) -> PyResult<Py<PyAny>> {
+    let result = PyDict::new(py);
+    for (key, value) in items {
+        let name = key.cast_exact::<PyString>().ok();
+        let renamed = match &name {
+            Some(name) => table.names.get(states, name.to_str()?),
+            None => None,
+        };
+        let mapped_key = match renamed {
+            Some(renamed) => renamed.bind(py).clone().into_any(),
+            None => key.clone(),
+        };
+
+        if result.contains(&mapped_key)? {
+            return Err(PyValueError::new_err(format!(
+                "Key collision: `{key}` maps to `{mapped_key}`, which is already present"
+            )));
+        }
+
+        let child_states = match (&name, table.names.matcher.is_empty()) {
+            (_, true) => Vec::new(),
+            (None, false) => table.names.step(states, PathStep::Other),
+            (Some(name), false) => {
+                let path_name = match (table.match_output, renamed) {
+                    (true, Some(renamed)) => renamed.bind(py).to_str()?,
+                    _ => name.to_str()?,
+                };
+                table.names.step(states, PathStep::Key(path_name))
+            }
+        };
+        let value = map_keys(py, &value, table, &child_states, generic)?;
+        result.set_item(mapped_key, value)?;
+    }
+    Ok(result.into_any().unbind())
+}
+
+/// Renames the keys of a parsed XML value. Attributes keep their `@` prefix and the reserved
+/// `#text`, `#comment` and `#pi` keys are left untouched.
+fn rename_xml(
+    py: Python<'_>,
+    value: Value,
+    table: &KeyTable,
+    states: &[MatchState],
+) -> PyResult<Value> {
+    match value {
+        Value::Object(map) => {
+            let mut result = Map::with_capacity(map.len());
+            for (key, value) in map {
+                let (new_key, value) = match key.strip_prefix('@') {
+                    Some(attr) => match table.names.get(states, attr) {
+                        Some(renamed) => (format!("@{}", renamed.bind(py).to_str()?), value),
+                        None => (key, value),
+                    },
+                    None if key.starts_with('#') => (key, value),
+                    None => rename_xml_element(py, table, states, key, value)?,
+                };
+                if result.contains_key(&new_key) {
+                    return Err(PyValueError::new_err(format!(
+                        "Key collision: an element or attribute maps to `{new_key}`, \
+                         which is already present"
+                    )));
+                }
+                result.insert(new_key, value);
+            }
+            Ok(Value::Object(result))
+        }
+        Value::Array(items) => {
+            let item_states = table.names.step(states, PathStep::Index);
+            items
+                .into_iter()
+                .map(|item| rename_xml(py, item, table, &item_states))
+                .collect::<PyResult<_>>()
+                .map(Value::Array)
+        }
+        value => Ok(value),
+    }
+}
+
+fn rename_xml_element(
+    py: Python<'_>,
+    table: &KeyTable,
+    states: &[MatchState],
+    name: String,
+    value: Value,
+) -> PyResult<(String, Value)> {
+    let renamed = match table.names.get(states, &name) {
+        Some(renamed) => Some(renamed.bind(py).to_str()?.to_string()),
+        None => None,
+    };
+    let path_name = match (table.match_output, &renamed) {
+        (true, Some(renamed)) => renamed.as_str(),
+        _ => name.as_str(),
+    };
+    let child_states = table.names.step(states, PathStep::Key(path_name));
+    let value = rename_xml(py, value, table, &child_states)?;
+    Ok((renamed.unwrap_or(name), value))
+}
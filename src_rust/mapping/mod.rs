@@ -1,15 +1,29 @@
 use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyDictMethods, PyList, PyListMethods};
+use pyo3::types::{PyDict, PyDictMethods, PyList, PyListMethods, PyTuple};
 use pyo3_stub_gen::derive::gen_stub_pyfunction;
 
+use containers::Container;
+
+mod containers;
+pub(crate) mod key_mapping;
+mod path_pattern;
+mod transform;
+
+/// Renames the keys of `data` found in `mapping`, recursing into dicts and lists.
+///
+/// Only exact `dict` and `list` objects are walked by default. With `generic_containers`,
+/// dict and list subclasses, tuples and `collections.abc.Mapping`/`Sequence` objects are
+/// walked too: mappings and sequences come back as `dict` and `list`, tuples as `tuple`.
 #[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.mapping")]
 #[pyfunction]
+#[pyo3(signature = (data, mapping, generic_containers = false))]
 pub fn apply_key_mapping(
     py: Python,
     data: Bound<'_, PyAny>,
     mapping: Bound<'_, PyDict>,
+    generic_containers: bool,
 ) -> PyResult<Py<PyAny>> {
-    apply_key_mapping_optimized(py, &data, &mapping)
+    apply_key_mapping_optimized(py, &data, &mapping, generic_containers)
 }
 
 #[inline]
@@ -17,6 +31,7 @@ fn apply_key_mapping_optimized(
     py: Python,
     data: &Bound<'_, PyAny>,
     mapping: &Bound<'_, PyDict>,
+    generic: bool,
 ) -> PyResult<Py<PyAny>> {
     // Step 3: Fast type dispatch using raw type pointer comparison
     let data_type = unsafe { pyo3::ffi::Py_TYPE(data.as_ptr()) };
@@ -54,8 +69,9 @@ fn apply_key_mapping_optimized(
                 ) || std::ptr::eq(
                     value_type as *const _,
                     &raw const pyo3::ffi::PyList_Type as *const _,
-                ) {
-                    apply_key_mapping_optimized(py, &value, mapping)?
+                ) || generic
+                {
+                    apply_key_mapping_optimized(py, &value, mapping, generic)?
                 } else {
                     value.unbind()
                 };
@@ -81,8 +97,9 @@ fn apply_key_mapping_optimized(
             ) || std::ptr::eq(
                 item_type as *const _,
                 &raw const pyo3::ffi::PyList_Type as *const _,
-            ) {
-                apply_key_mapping_optimized(py, &item, mapping)?
+            ) || generic
+            {
+                apply_key_mapping_optimized(py, &item, mapping, generic)?
             } else {
                 item.unbind()
             };
@@ -91,6 +108,32 @@ fn apply_key_mapping_optimized(
         }
 
         Ok(result.unbind().into())
+    } else if generic {
+        // Slow path: subclasses, tuples and abstract containers
+        let map_item =
+            |item: &Bound<'_, PyAny>| apply_key_mapping_optimized(py, item, mapping, generic);
+        match Container::of(data)? {
+            Some(Container::Mapping(items)) => {
+                let result = PyDict::new(py);
+                for (key, value) in items {
+                    let mapped_key = match mapping.get_item(&key) {
+                        Ok(Some(mapped)) => mapped,
+                        _ => key,
+                    };
+                    result.set_item(mapped_key, map_item(&value)?)?;
+                }
+                Ok(result.into_any().unbind())
+            }
+            Some(Container::Sequence(items)) => {
+                let items = items.iter().map(map_item).collect::<PyResult<Vec<_>>>()?;
+                Ok(PyList::new(py, items)?.into_any().unbind())
+            }
+            Some(Container::Tuple(items)) => {
+                let items = items.iter().map(map_item).collect::<PyResult<Vec<_>>>()?;
+                Ok(PyTuple::new(py, items)?.into_any().unbind())
+            }
+            None => Ok(data.clone().unbind()),
+        }
     } else {
         // Return primitive values as-is
         Ok(data.clone().unbind())
@@ -100,5 +143,8 @@ fn apply_key_mapping_optimized(
 #[pymodule]
 pub fn mapping(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(apply_key_mapping, m)?)?;
+    m.add_function(wrap_pyfunction!(transform::transform, m)?)?;
+    m.add_class::<key_mapping::KeyMapping>()?;
+    m.add_class::<transform::Transform>()?;
     Ok(())
 }
@@ -0,0 +1,226 @@
+use ahash::AHashMap;
+
+/// One segment of a path pattern such as `Data.SurveyData.*.Length`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Segment {
+    /// A literal dict key.
+    Key(String),
+    /// `*`: exactly one key or list index.
+    Any,
+    /// `**`: any number of keys or list indices, including none.
+    AnyDepth,
+}
+
+impl std::fmt::Display for Segment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Segment::Key(key) => f.write_str(key),
+            Segment::Any => f.write_str("*"),
+            Segment::AnyDepth => f.write_str("**"),
+        }
+    }
+}
+
+/// Splits a key pattern into the scope it applies to and the key it targets.
+///
+/// A plain key (no `.`) has no scope and applies everywhere.
+pub fn parse_key_pattern(pattern: &str) -> Result<(Option<Vec<Segment>>, String), String> {
+    let Some((scope, key)) = pattern.rsplit_once('.') else {
+        return Ok((None, pattern.to_string()));
+    };
+    if key.is_empty() || key == "*" || key == "**" {
+        return Err(format!(
+            "Invalid path pattern `{pattern}`: the last segment must be a key name"
+        ));
+    }
+
+    let scope = scope
+        .split('.')
+        .map(|segment| match segment {
+            "" => Err(format!("Invalid path pattern `{pattern}`: empty segment")),
+            "*" => Ok(Segment::Any),
+            "**" => Ok(Segment::AnyDepth),
+            key => Ok(Segment::Key(key.to_string())),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((Some(scope), key.to_string()))
+}
+
+/// Whether a scope only contains literal keys.
+pub fn is_literal(scope: &[Segment]) -> bool {
+    scope
+        .iter()
+        .all(|segment| matches!(segment, Segment::Key(_)))
+}
+
+/// A step taken while walking down a document.
+#[derive(Clone, Copy, Debug)]
+pub enum PathStep<'a> {
+    Key(&'a str),
+    /// A list item. Indices are optional in patterns: `Data.SurveyData.Length` and
+    /// `Data.SurveyData.*.Length` both match the `Length` of every shot of the list.
+    Index,
+    /// A dict key which is not a string, only matched by wildcards.
+    Other,
+}
+
+/// Progress of a scope: `pos` segments of scope `scope` have been matched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MatchState {
+    scope: u32,
+    pos: u32,
+}
+
+/// Matches a set of scopes incrementally while the document is walked.
+///
+/// The walk carries the states reached for the current path, so every scope is evaluated in
+/// the same recursive pass that maps the keys.
+#[derive(Clone, Debug, Default)]
+pub struct ScopeMatcher {
+    scopes: Vec<Vec<Segment>>,
+}
+
+impl ScopeMatcher {
+    pub fn new(scopes: Vec<Vec<Segment>>) -> Self {
+        ScopeMatcher { scopes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scopes.is_empty()
+    }
+
+    /// States at the root of the document.
+    pub fn start(&self) -> Vec<MatchState> {
+        let mut states = (0..self.scopes.len() as u32)
+            .map(|scope| MatchState { scope, pos: 0 })
+            .collect();
+        self.close(&mut states);
+        states
+    }
+
+    /// States after moving from the current path down into `step`.
+    pub fn step(&self, states: &[MatchState], step: PathStep<'_>) -> Vec<MatchState> {
+        let mut next = Vec::with_capacity(states.len());
+        let mut push = |state: MatchState| {
+            if !next.contains(&state) {
+                next.push(state);
+            }
+        };
+
+        for &state in states {
+            if let PathStep::Index = step {
+                push(state);
+            }
+            let Some(segment) = self.scopes[state.scope as usize].get(state.pos as usize) else {
+                continue;
+            };
+            let advanced = MatchState {
+                pos: state.pos + 1,
+                ..state
+            };
+            match (segment, step) {
+                (Segment::AnyDepth, _) => push(state),
+                (Segment::Any, _) => push(advanced),
+                (Segment::Key(key), PathStep::Key(name)) if key == name => push(advanced),
+                _ => (),
+            }
+        }
+
+        self.close(&mut next);
+        next
+    }
+
+    /// Indices of the scopes fully matched by the current path.
+    pub fn matched<'a>(&'a self, states: &'a [MatchState]) -> impl Iterator<Item = usize> + 'a {
+        states
+            .iter()
+            .filter(|state| state.pos as usize == self.scopes[state.scope as usize].len())
+            .map(|state| state.scope as usize)
+    }
+
+    /// Adds the states reachable by matching `**` with an empty path.
+    fn close(&self, states: &mut Vec<MatchState>) {
+        let mut i = 0;
+        while i < states.len() {
+            let state = states[i];
+            if let Some(Segment::AnyDepth) =
+                self.scopes[state.scope as usize].get(state.pos as usize)
+            {
+                let skipped = MatchState {
+                    pos: state.pos + 1,
+                    ..state
+                };
+                if !states.contains(&skipped) {
+                    states.push(skipped);
+                }
+            }
+            i += 1;
+        }
+    }
+}
+
+/// Values attached to keys, either everywhere or within a scope.
+///
+/// Scoped entries take precedence over global ones, and earlier scoped entries over later ones.
+#[derive(Debug)]
+pub struct ScopedTable<T> {
+    pub global: AHashMap<String, T>,
+    /// Scoped entries, aligned with the scopes of `matcher`.
+    pub scoped: Vec<(String, T)>,
+    pub matcher: ScopeMatcher,
+}
+
+impl<T> ScopedTable<T> {
+    pub fn new(entries: impl IntoIterator<Item = (Option<Vec<Segment>>, String, T)>) -> Self {
+        let mut global = AHashMap::default();
+        let mut scoped = Vec::new();
+        let mut scopes = Vec::new();
+
+        for (scope, key, value) in entries {
+            match scope {
+                Some(scope) => {
+                    scoped.push((key, value));
+                    scopes.push(scope);
+                }
+                None => {
+                    global.entry(key).or_insert(value);
+                }
+            }
+        }
+
+        ScopedTable {
+            global,
+            scoped,
+            matcher: ScopeMatcher::new(scopes),
+        }
+    }
+
+    /// Entry for `key` in a dict reached with `states`.
+    pub fn get(&self, states: &[MatchState], key: &str) -> Option<&T> {
+        self.matched(states)
+            .find(|(scoped_key, _)| scoped_key == key)
+            .map(|(_, value)| value)
+            .or_else(|| self.global.get(key))
+    }
+
+    /// Scoped entries applying to a dict reached with `states`.
+    pub fn matched<'a: 's, 's>(
+        &'a self,
+        states: &'s [MatchState],
+    ) -> impl Iterator<Item = &'a (String, T)> + 's {
+        self.matcher.matched(states).map(|i| &self.scoped[i])
+    }
+
+    /// States at the root of the document, empty when there is no scope to track.
+    pub fn start(&self) -> Vec<MatchState> {
+        self.matcher.start()
+    }
+
+    /// States after moving down into `step`, skipping the work when there is no scope.
+    pub fn step(&self, states: &[MatchState], step: PathStep<'_>) -> Vec<MatchState> {
+        match self.matcher.is_empty() {
+            true => Vec::new(),
+            false => self.matcher.step(states, step),
+        }
+    }
+}
@@ -0,0 +1,369 @@
+use pyo3::{
+    exceptions::{PyTypeError, PyValueError},
+    prelude::*,
+    types::{PyBool, PyDate, PyDict, PyFloat, PyInt, PyList, PyString},
+};
+
+use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pyfunction, gen_stub_pymethods};
+
+use super::path_pattern::{self, MatchState, PathStep, ScopedTable};
+
+/// Type a scalar value is cast to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Cast {
+    /// Whole numbers only, `12.0` is accepted but `12.7` is an error.
+    Int,
+    Float,
+    Bool,
+    Str,
+    /// ISO 8601 date (`YYYY-MM-DD`), as written by Ariane.
+    Date,
+}
+
+impl Cast {
+    fn parse(name: &str) -> PyResult<Self> {
+        match name {
+            "int" => Ok(Cast::Int),
+            "float" => Ok(Cast::Float),
+            "bool" => Ok(Cast::Bool),
+            "str" => Ok(Cast::Str),
+            "date" => Ok(Cast::Date),
+            _ => Err(PyValueError::new_err(format!(
+                "Unsupported cast: `{name}`. Expected `int`, `float`, `bool`, `str` or `date`."
+            ))),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Cast::Int => "int",
+            Cast::Float => "float",
+            Cast::Bool => "bool",
+            Cast::Str => "str",
+            Cast::Date => "date",
+        }
+    }
+}
+
+/// What happens to the value of a key.
+struct KeyTransform {
+    rename: Option<Py<PyString>>,
+    cast: Option<Cast>,
+    factor: Option<f64>,
+    drop: bool,
+    /// Inserted when the key is missing from a dict of the rule scope.
+    default: Option<Py<PyAny>>,
+}
+
+impl KeyTransform {
+    fn extract(pattern: &str, spec: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let mut transform = KeyTransform {
+            rename: None,
+            cast: None,
+            factor: None,
+            drop: false,
+            default: None,
+        };
+
+        if let Ok(name) = spec.cast::<PyString>() {
+            transform.rename = Some(name.clone().unbind());
+            return Ok(transform);
+        }
+        let Ok(spec) = spec.cast::<PyDict>() else {
+            return Err(PyTypeError::new_err(format!(
+                "Unexpected spec for `{pattern}`: expected a new name or a dict, got `{spec}`"
+            )));
+        };
+
+        for (option, value) in spec.iter() {
+            match option.extract::<&str>()? {
+                "rename" => transform.rename = Some(value.cast_into::<PyString>()?.unbind()),
+                "cast" => transform.cast = Some(Cast::parse(value.extract()?)?),
+                "factor" => transform.factor = Some(value.extract()?),
+                "drop" => transform.drop = value.extract()?,
+                "default" => transform.default = Some(value.unbind()),
+                option => {
+                    return Err(PyValueError::new_err(format!(
+                        "Unsupported option `{option}` for `{pattern}`. \
+                         Expected `rename`, `cast`, `factor`, `drop` or `default`."
+                    )))
+                }
+            }
+        }
+
+        if transform.drop && spec.len() > 1 {
+            return Err(PyValueError::new_err(format!(
+                "`drop` cannot be combined with other options for `{pattern}`"
+            )));
+        }
+        if transform.factor.is_some()
+            && !matches!(transform.cast, None | Some(Cast::Int | Cast::Float))
+        {
+            return Err(PyValueError::new_err(format!(
+                "`factor` requires a numeric cast for `{pattern}`"
+            )));
+        }
+        Ok(transform)
+    }
+
+    fn converts(&self) -> bool {
+        self.cast.is_some() || self.factor.is_some()
+    }
+
+    /// Casts and scales a scalar value. `None` is kept as is.
+    fn convert(&self, py: Python<'_>, key: &str, value: &Bound<'_, PyAny>) -> PyResult<Py<PyAny>> {
+        if value.is_none() {
+            return Ok(value.clone().unbind());
+        }
+        let error = |cast: &str| {
+            PyValueError::new_err(format!("Cannot cast `{value}` to {cast} for key `{key}`"))
+        };
+
+        if let Some(factor) = self.factor {
+            let number = to_f64(value).ok_or_else(|| error("float"))? * factor;
+            return match self.cast {
+                Some(Cast::Int) => {
+                    let number = integral(number).ok_or_else(|| error("int"))?;
+                    Ok(PyInt::new(py, number).into_any().unbind())
+                }
+                _ => Ok(PyFloat::new(py, number).into_any().unbind()),
+            };
+        }
+
+        let Some(cast) = self.cast else {
+            return Ok(value.clone().unbind());
+        };
+        let converted: Option<PyResult<Bound<'_, PyAny>>> = match cast {
+            Cast::Int => to_i64(value).map(|n| Ok(PyInt::new(py, n).into_any())),
+            Cast::Float => to_f64(value).map(|n| Ok(PyFloat::new(py, n).into_any())),
+            Cast::Bool => to_bool(value).map(|b| Ok(PyBool::new(py, b).to_owned().into_any())),
+            Cast::Str => Some(value.str().map(|s| s.into_any())),
+            Cast::Date => {
+                to_date(value).map(|(y, m, d)| PyDate::new(py, y, m, d).map(|d| d.into_any()))
+            }
+        };
+        match converted {
+            Some(converted) => Ok(converted?.unbind()),
+            None => Err(error(cast.name())),
+        }
+    }
+}
+
+fn to_f64(value: &Bound<'_, PyAny>) -> Option<f64> {
+    if value.is_instance_of::<PyBool>() {
+        return None;
+    }
+    let number: f64 = match value.cast::<PyString>() {
+        Ok(text) => text.to_str().ok()?.trim().parse().ok()?,
+        Err(_) => value.extract().ok()?,
+    };
+    // `nan` and `inf` are not values a survey field can hold
+    number.is_finite().then_some(number)
+}
+
+fn to_i64(value: &Bound<'_, PyAny>) -> Option<i64> {
+    if value.is_instance_of::<PyBool>() {
+        return None;
+    }
+    if value.is_instance_of::<PyInt>() {
+        return value.extract().ok();
+    }
+    if let Ok(text) = value.cast::<PyString>() {
+        if let Ok(number) = text.to_str().ok()?.trim().parse() {
+            return Some(number);
+        }
+    }
+    // Floats and strings such as `12.0` are accepted, `12.7` is not
+    to_f64(value).and_then(integral)
+}
+
+/// `number` as an integer, `None` when it has a fractional part or is out of range.
+fn integral(number: f64) -> Option<i64> {
+    let in_range = (i64::MIN as f64..i64::MAX as f64).contains(&number);
+    (in_range && number.fract() == 0.0).then_some(number as i64)
+}
+
+fn to_bool(value: &Bound<'_, PyAny>) -> Option<bool> {
+    if let Ok(flag) = value.cast::<PyBool>() {
+        return Some(flag.is_true());
+    }
+    if let Ok(text) = value.cast::<PyString>() {
+        return match text.to_str().ok()?.trim().to_ascii_lowercase().as_str() {
+            "true" | "yes" | "1" => Some(true),
+            "false" | "no" | "0" => Some(false),
+            _ => None,
+        };
+    }
+    match value.extract::<i64>().ok()? {
+        0 => Some(false),
+        1 => Some(true),
+        _ => None,
+    }
+}
+
+fn to_date(value: &Bound<'_, PyAny>) -> Option<(i32, u8, u8)> {
+    let text = value.cast::<PyString>().ok()?.to_str().ok()?.trim();
+    // A time part (`2011-08-03T10:00:00`) is ignored
+    let date = match text.split_once(['T', ' ']) {
+        Some((date, _)) => date,
+        None => text,
+    };
+    let mut parts = date.splitn(3, '-');
+    let year = parts.next()?;
+    let (month, day) = (parts.next()?, parts.next()?);
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return None;
+    }
+    Some((year.parse().ok()?, month.parse().ok()?, day.parse().ok()?))
+}
+
+/// Compiled transform spec: per key or path pattern, rename, cast, scale, drop or set a default.
+///
+/// Each spec value is either a new name or a dict with the options `rename`, `cast` (`int`,
+/// `float`, `bool`, `str` or `date`), `factor`, `drop` and `default`. Keys accept the path
+/// patterns of `KeyMapping`; `default` requires one, since a copy of its value is added to
+/// every dict of the scope missing the key. Casts apply to scalar values and to the items of a
+/// list value, `None` is left untouched; `nan` and infinite numbers are rejected.
+#[gen_stub_pyclass]
+#[pyclass(module = "openspeleo_core._rust_lib.mapping", frozen)]
+pub struct Transform {
+    table: ScopedTable<KeyTransform>,
+}
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl Transform {
+    #[new]
+    fn new(spec: &Bound<'_, PyDict>) -> PyResult<Self> {
+        let entries = spec
+            .iter()
+            .map(|(pattern, spec)| {
+                let pattern: String = pattern.extract()?;
+                let (scope, key) =
+                    path_pattern::parse_key_pattern(&pattern).map_err(PyValueError::new_err)?;
+                let transform = KeyTransform::extract(&pattern, &spec)?;
+                if scope.is_none() && transform.default.is_some() {
+                    return Err(PyValueError::new_err(format!(
+                        "`default` requires a path pattern, got `{pattern}`"
+                    )));
+                }
+                Ok((scope, key, transform))
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(Transform {
+            table: ScopedTable::new(entries),
+        })
+    }
+
+    /// Applies the transform to `data` in a single pass over the dict and list tree.
+    fn apply(&self, py: Python<'_>, data: &Bound<'_, PyAny>) -> PyResult<Py<PyAny>> {
+        transform_value(py, data, &self.table, &self.table.start(), None)
+    }
+
+    fn __len__(&self) -> usize {
+        self.table.global.len() + self.table.scoped.len()
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!("Transform({} keys)", self.__len__())
+    }
+}
+
+/// Applies a transform spec to a dict or list tree.
+#[gen_stub_pyfunction(module = "openspeleo_core._rust_lib.mapping")]
+#[pyfunction]
+pub fn transform(
+    py: Python<'_>,
+    data: &Bound<'_, PyAny>,
+    spec: &Bound<'_, PyDict>,
+) -> PyResult<Py<PyAny>> {
+    Transform::new(spec)?.apply(py, data)
+}
+
+/// Walks `value`, converting it with `conversion` if it is a scalar or a list of scalars.
+fn transform_value(
+    py: Python<'_>,
+    value: &Bound<'_, PyAny>,
+    table: &ScopedTable<KeyTransform>,
+    states: &[MatchState],
+    conversion: Option<(&str, &KeyTransform)>,
+) -> PyResult<Py<PyAny>> {
+    if let Ok(dict) = value.cast_exact::<PyDict>() {
+        transform_dict(py, dict, table, states)
+    } else if let Ok(list) = value.cast_exact::<PyList>() {
+        let result = PyList::empty(py);
+        let item_states = table.step(states, PathStep::Index);
+        for item in list {
+            result.append(transform_value(py, &item, table, &item_states, conversion)?)?;
+        }
+        Ok(result.into_any().unbind())
+    } else {
+        match conversion {
+            Some((key, transform)) => transform.convert(py, key, value),
+            None => Ok(value.clone().unbind()),
+        }
+    }
+}
+
+fn transform_dict(
+    py: Python<'_>,
+    dict: &Bound<'_, PyDict>,
+    table: &ScopedTable<KeyTransform>,
+    states: &[MatchState],
+) -> PyResult<Py<PyAny>> {
+    let result = PyDict::new(py);
+
+    for (key, value) in dict.iter() {
+        let name = key.cast_exact::<PyString>().ok();
+        let (name, transform) = match &name {
+            Some(name) => {
+                let name = name.to_str()?;
+                (Some(name), table.get(states, name))
+            }
+            None => (None, None),
+        };
+
+        if transform.is_some_and(|t| t.drop) {
+            continue;
+        }
+        let new_key = match transform.and_then(|t| t.rename.as_ref()) {
+            Some(rename) => rename.bind(py).clone().into_any(),
+            None => key.clone(),
+        };
+        if result.contains(&new_key)? {
+            return Err(PyValueError::new_err(format!(
+                "Key collision: `{key}` maps to `{new_key}`, which is already present"
+            )));
+        }
+
+        let child_states = match name {
+            Some(name) => table.step(states, PathStep::Key(name)),
+            None => table.step(states, PathStep::Other),
+        };
+        let conversion = match (name, transform) {
+            (Some(name), Some(transform)) if transform.converts() => Some((name, transform)),
+            _ => None,
+        };
+        let value = transform_value(py, &value, table, &child_states, conversion)?;
+        result.set_item(new_key, value)?;
+    }
+
+    for (key, transform) in table.matched(states) {
+        let Some(default) = &transform.default else {
+            continue;
+        };
+        let new_key = match &transform.rename {
+            Some(rename) => rename.bind(py).clone().into_any(),
+            None => PyString::new(py, key).into_any(),
+        };
+        if !dict.contains(key)? && !result.contains(&new_key)? {
+            // Each dict gets its own copy, a mutable default would otherwise be shared
+            let default = py
+                .import("copy")?
+                .call_method1("deepcopy", (default.bind(py),))?;
+            result.set_item(new_key, default)?;
+        }
+    }
+
+    Ok(result.into_any().unbind())
+}
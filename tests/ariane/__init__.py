@@ -0,0 +1,9 @@
+HAND_SURVEY = "tests/artifacts/hand_survey.tml"
+
+
+def survey_shots(data):
+    return data["CaveFile"]["Data"]["SurveyData"]
+
+
+def find_shot(data, shot_id):
+    return next(shot for shot in survey_shots(data) if shot["ID"] == shot_id)
@@ -0,0 +1,111 @@
+import tempfile
+import unittest
+import zipfile
+from pathlib import Path
+
+import pytest
+from openspeleo_core import ariane_core
+
+from tests.ariane import HAND_SURVEY
+
+# Not a valid image, only the bytes matter
+IMAGE = b"\x89PNG\r\n\x1a\n" + bytes(range(256)) * 4
+NOTES = "Sketch scanned by the second team\n" * 20
+
+
+class TestTmlAttachments(unittest.TestCase):
+    def setUp(self):
+        self._tmpdir = tempfile.TemporaryDirectory()
+        self.dir = Path(self._tmpdir.name)
+
+        # Survey with attachments, as some TML files carry background images
+        self.path = self.dir / "attachments.tml"
+        with (
+            zipfile.ZipFile(HAND_SURVEY) as source,
+            zipfile.ZipFile(self.path, "w") as archive,
+        ):
+            archive.writestr("Data.xml", source.read("Data.xml"), zipfile.ZIP_DEFLATED)
+            archive.writestr("images/background.png", IMAGE, zipfile.ZIP_STORED)
+            archive.writestr("notes.txt", NOTES, zipfile.ZIP_DEFLATED)
+
+    def tearDown(self):
+        self._tmpdir.cleanup()
+
+    def test_list_entries(self):
+        entries = ariane_core.list_ariane_tml_entries(self.path)
+        assert [entry.name for entry in entries] == [
+            "Data.xml",
+            "images/background.png",
+            "notes.txt",
+        ]
+
+        image = entries[1]
+        assert isinstance(image, ariane_core.TmlEntry)
+        assert image.size == image.compressed_size == len(IMAGE)
+        assert image.compression == "stored"
+
+        notes = entries[2]
+        assert notes.size == len(NOTES)
+        assert notes.compressed_size < notes.size
+        assert notes.compression == "deflated"
+
+    def test_read_entry(self):
+        path = self.path
+        assert ariane_core.read_ariane_tml_entry(path, "images/background.png") == (
+            IMAGE
+        )
+        assert ariane_core.read_ariane_tml_entry(path, "notes.txt") == NOTES.encode()
+
+        with pytest.raises(KeyError, match="missing.png"):
+            ariane_core.read_ariane_tml_entry(path, "missing.png")
+        with pytest.raises(FileNotFoundError):
+            ariane_core.list_ariane_tml_entries(self.dir / "missing.tml")
+
+    def test_save_keeps_attachments(self):
+        data = ariane_core.load_ariane_tml_file_to_dict(self.path)
+        data["CaveFile"]["caveName"] = "Renamed"
+
+        # The file being overwritten can be its own source
+        ariane_core.dict_to_ariane_tml_file(data, self.path, source=self.path)
+
+        entries = ariane_core.list_ariane_tml_entries(self.path)
+        assert [(entry.name, entry.compression) for entry in entries] == [
+            ("Data.xml", "deflated"),
+            ("images/background.png", "stored"),
+            ("notes.txt", "deflated"),
+        ]
+        with zipfile.ZipFile(self.path) as archive:
+            assert archive.read("images/background.png") == IMAGE
+            assert archive.read("notes.txt") == NOTES.encode()
+
+        reloaded_data = ariane_core.load_ariane_tml_file_to_dict(self.path)
+        assert reloaded_data == data
+
+    def test_save_to_another_file(self):
+        data = ariane_core.load_ariane_tml_file_to_dict(self.path)
+        output_path = self.dir / "output.tml"
+        ariane_core.dict_to_ariane_tml_file(data, output_path)
+
+        entries = ariane_core.list_ariane_tml_entries(output_path)
+        assert [entry.name for entry in entries] == ["Data.xml"]
+
+        ariane_core.dict_to_ariane_tml_file(data, output_path, source=self.path)
+        entries = ariane_core.list_ariane_tml_entries(output_path)
+        assert [entry.name for entry in entries] == [
+            "Data.xml",
+            "images/background.png",
+            "notes.txt",
+        ]
+
+    def test_save_without_source_drops_attachments(self):
+        data = ariane_core.load_ariane_tml_file_to_dict(self.path)
+
+        # The attachments are never taken from the file being overwritten implicitly
+        ariane_core.dict_to_ariane_tml_file(data, self.path)
+        entries = ariane_core.list_ariane_tml_entries(self.path)
+        assert [entry.name for entry in entries] == ["Data.xml"]
+        assert ariane_core.load_ariane_tml_file_to_dict(self.path) == data
+
+
+if __name__ == "__main__":
+    unittest.main()
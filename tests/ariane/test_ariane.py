@@ -1,13 +1,18 @@
+import io
 import json
+import tempfile
 import unittest
+import warnings
 import zipfile
 from pathlib import Path
+from xml.etree import ElementTree
 
 import pytest
 import xmltodict
 from deepdiff import DeepDiff
 from openspeleo_core import ariane_core
 from openspeleo_core import legacy
+from openspeleo_core.mapping import KeyMapping
 from parameterized import parameterized
 
 DEBUG = False
@@ -57,7 +62,6 @@ class TestCaseConversion(unittest.TestCase):
             ("tests/artifacts/hand_survey.tml",),
             ("tests/artifacts/test_simple.mini.tml",),
             ("tests/artifacts/test_simple.tml",),
-            # ("tests/artifacts/test_simple.tmlu",),
             ("tests/artifacts/test_with_walls.tml",),
             ("tests/artifacts/test_large.tml",),
         ]
@@ -74,3 +78,538 @@ class TestCaseConversion(unittest.TestCase):
 
         diff = DeepDiff(produced_data, expected_data, ignore_order=True)
         assert diff == {}, f"Round trip transformation failed: {diff}"
+
+    @parameterized.expand(
+        [
+            ("tests/artifacts/demo.xml",),
+            ("tests/artifacts/Data.xml",),
+        ]
+    )
+    def test_dict_to_xml_str_preserves_element_order(self, filepath):
+        with Path(filepath).open("r") as xml_file:
+            xml_str = xml_file.read()
+
+        data = ariane_core.xml_str_to_dict(xml_str, keep_null=True)
+        root_name = next(iter(data))
+        produced_xml = ariane_core.dict_to_xml_str(data[root_name], root_name)
+
+        expected_tags = [
+            (el.tag, list(el.attrib)) for el in ElementTree.fromstring(xml_str).iter()
+        ]
+        produced_tags = [
+            (el.tag, list(el.attrib))
+            for el in ElementTree.fromstring(produced_xml.encode()).iter()
+        ]
+        assert produced_tags == expected_tags
+
+    def test_dict_to_xml_str_preserves_attribute_order(self):
+        xml_str = (
+            '<Layers><layerList visible="true" name="Walls" color="0xFF0000">'
+            '<item z="1" a="2" m="3"/></layerList></Layers>'
+        )
+        data = ariane_core.xml_str_to_dict(xml_str)
+        produced_xml = ariane_core.dict_to_xml_str(data["Layers"], "Layers")
+
+        produced_attributes = [
+            list(el.attrib.items())
+            for el in ElementTree.fromstring(produced_xml.encode()).iter()
+        ]
+        assert produced_attributes == [
+            [],
+            [("visible", "true"), ("name", "Walls"), ("color", "0xFF0000")],
+            [("z", "1"), ("a", "2"), ("m", "3")],
+        ]
+
+    def test_dict_to_xml_str_reproduces_ariane_layout(self):
+        with Path("tests/artifacts/demo.xml").open("r") as xml_file:
+            xml_str = xml_file.read()
+
+        data = ariane_core.xml_str_to_dict(xml_str, keep_null=True)
+        options = ariane_core.XmlWriterOptions(
+            indent_size=4, standalone="yes", trailing_newline=True
+        )
+        assert ariane_core.dict_to_xml_str(data["CaveFile"], "CaveFile", options) == (
+            xml_str
+        )
+
+        produced_xml = ariane_core.dict_to_xml_str(
+            data["CaveFile"], "CaveFile", ariane_core.XmlWriterOptions.ariane()
+        )
+        assert produced_xml.startswith(
+            '<?xml version="1.0" encoding="UTF-8" standalone="yes"?>\n<CaveFile>\n'
+        )
+        assert produced_xml.endswith("</CaveFile>\n")
+
+    def test_dict_to_xml_str_formatting_options(self):
+        data = {"Shot": {"Length": "1.0", "Comment": "it's <ok>"}, "Empty": None}
+
+        assert ariane_core.dict_to_xml_str(data, "CaveFile") == (
+            '<?xml version="1.0" encoding="utf-8"?><CaveFile><Shot>'
+            "<Length>1.0</Length><Comment>it's &lt;ok&gt;</Comment></Shot>"
+            "<Empty/></CaveFile>"
+        )
+
+        options = ariane_core.XmlWriterOptions(
+            indent_char="\t",
+            indent_size=1,
+            version="1.1",
+            encoding=None,
+            trailing_newline=True,
+            line_ending="crlf",
+        )
+        assert ariane_core.dict_to_xml_str(data, "CaveFile", options) == (
+            '<?xml version="1.1"?>\r\n<CaveFile>\r\n\t<Shot>\r\n'
+            "\t\t<Length>1.0</Length>\r\n\t\t<Comment>it's &lt;ok&gt;</Comment>\r\n"
+            "\t</Shot>\r\n\t<Empty/>\r\n</CaveFile>\r\n"
+        )
+
+        # Only the indentation follows the line ending, the content is kept as-is
+        data = {"#comment": "a\r\nb", "Comment": "c\nd", "Note": "e\r\nf"}
+        options = ariane_core.XmlWriterOptions(indent_size=1, line_ending="crlf")
+        assert ariane_core.dict_to_xml_str(data, "Shot", options) == (
+            '<?xml version="1.0" encoding="utf-8"?>\r\n<Shot>\r\n <!--a\r\nb-->\r\n'
+            " <Comment>c\nd</Comment>\r\n <Note>e\r\nf</Note>\r\n</Shot>"
+        )
+
+    def test_xml_writer_options_validation(self):
+        with pytest.raises(ValueError, match="Unsupported line ending"):
+            ariane_core.XmlWriterOptions(line_ending="cr")
+
+        with pytest.raises(ValueError, match="Unsupported indent character"):
+            ariane_core.XmlWriterOptions(indent_char="-")
+
+    def test_dict_to_xml_file_and_stream(self):
+        data = {"caveName": "DEMO", "Data": {"SurveyData": [{"ID": "1"}, {"ID": "2"}]}}
+        options = ariane_core.XmlWriterOptions.ariane()
+        expected_xml = ariane_core.dict_to_xml_str(data, "CaveFile", options)
+
+        with tempfile.TemporaryDirectory() as tmpdir:
+            filepath = Path(tmpdir) / "Data.xml"
+            ariane_core.dict_to_xml_file(data, filepath, "CaveFile", options)
+            assert filepath.read_text(encoding="utf-8") == expected_xml
+
+        stream = io.BytesIO()
+        ariane_core.dict_to_xml_stream(data, stream, "CaveFile", options)
+        assert stream.getvalue().decode("utf-8") == expected_xml
+
+        crlf_options = ariane_core.XmlWriterOptions(indent_size=4, line_ending="crlf")
+        stream = io.BytesIO()
+        ariane_core.dict_to_xml_stream(data, stream, "CaveFile", crlf_options)
+        assert stream.getvalue().decode("utf-8") == ariane_core.dict_to_xml_str(
+            data, "CaveFile", crlf_options
+        )
+
+    def test_dict_to_xml_stream_propagates_errors(self):
+        with pytest.raises(TypeError):
+            ariane_core.dict_to_xml_stream({"a": "b"}, io.StringIO(), "CaveFile")
+
+    def test_dict_to_ariane_tml_file_round_trip(self):
+        filepath = "tests/artifacts/hand_survey.tml"
+        data = ariane_core.load_ariane_tml_file_to_dict(filepath)
+
+        with tempfile.TemporaryDirectory() as tmpdir:
+            output_path = Path(tmpdir) / "output.tml"
+            ariane_core.dict_to_ariane_tml_file(data, output_path)
+
+            with zipfile.ZipFile(output_path, "r") as zip_file:
+                assert zip_file.namelist() == ["Data.xml"]
+                assert zip_file.getinfo("Data.xml").compress_type == (
+                    zipfile.ZIP_DEFLATED
+                )
+
+            reloaded_data = ariane_core.load_ariane_tml_file_to_dict(output_path)
+
+        diff = DeepDiff(reloaded_data, data, ignore_order=True)
+        assert diff == {}, f"Round trip transformation failed: {diff}"
+
+    def test_load_ariane_file_to_dict_detects_format(self):
+        data = ariane_core.load_ariane_tml_file_to_dict(
+            "tests/artifacts/hand_survey.tml"
+        )
+
+        with tempfile.TemporaryDirectory() as tmpdir:
+            # The format is read from the content, whatever the extension
+            tmlu_path = Path(tmpdir) / "survey.tml"
+            ariane_core.dict_to_ariane_tmlu_file(data, tmlu_path)
+            assert not zipfile.is_zipfile(tmlu_path)
+            assert tmlu_path.read_bytes().startswith(b"<?xml")
+
+            tml_path = Path(tmpdir) / "survey.tmlu"
+            ariane_core.dict_to_ariane_tml_file(data, tml_path)
+
+            for path in [tmlu_path, tml_path]:
+                reloaded_data = ariane_core.load_ariane_file_to_dict(path)
+                diff = DeepDiff(reloaded_data, data, ignore_order=True)
+                assert diff == {}, f"Round trip transformation failed: {diff}"
+
+        with pytest.raises(FileNotFoundError):
+            ariane_core.load_ariane_file_to_dict("does_not_exists.tmlu")
+
+    def test_load_ariane_file_to_dict_tmlu(self):
+        with zipfile.ZipFile("tests/artifacts/test_simple.tml", "r") as zip_file:
+            xml_bytes = zip_file.read("Data.xml")
+        expected_data = legacy.remove_none_values(xmltodict.parse(xml_bytes))
+
+        with tempfile.TemporaryDirectory() as tmpdir:
+            # A TMLU file is the "Data.xml" of a TML file
+            tmlu_path = Path(tmpdir) / "test_simple.tmlu"
+            tmlu_path.write_bytes(xml_bytes)
+
+            produced_data = ariane_core.load_ariane_file_to_dict(tmlu_path)
+            diff = DeepDiff(produced_data, expected_data, ignore_order=True)
+            assert diff == {}, f"Round trip transformation failed: {diff}"
+
+            # Only `load_ariane_file_to_dict` tells the formats apart
+            with pytest.raises(OSError, match="Failed to open zip archive"):
+                ariane_core.load_ariane_tml_file_to_dict(tmlu_path)
+
+    def test_dict_to_ariane_tml_file_requires_single_root(self):
+        with tempfile.TemporaryDirectory() as tmpdir, pytest.raises(ValueError):
+            ariane_core.dict_to_ariane_tml_file(
+                {"a": {}, "b": {}}, Path(tmpdir) / "output.tml"
+            )
+
+    def test_dict_to_ariane_file_keeps_existing_file_on_error(self):
+        data = ariane_core.load_ariane_tml_file_to_dict(
+            "tests/artifacts/hand_survey.tml"
+        )
+        # Fails while the XML is being written, after the file was opened
+        invalid_data = {"CaveFile": {"#comment": "a -- b"}}
+
+        with tempfile.TemporaryDirectory() as tmpdir:
+            for save in [
+                ariane_core.dict_to_ariane_tml_file,
+                ariane_core.dict_to_ariane_tmlu_file,
+            ]:
+                output_path = Path(tmpdir) / "output"
+                save(data, output_path)
+                saved_bytes = output_path.read_bytes()
+
+                with pytest.raises(ValueError, match="XML generation error"):
+                    save(invalid_data, output_path)
+                assert output_path.read_bytes() == saved_bytes
+                assert [p.name for p in Path(tmpdir).iterdir()] == ["output"]
+
+    def test_dict_to_xml_str_number_format(self):
+        data = {
+            "Length": 3,
+            "Azimut": 333.0,
+            "Depth": 0.1234,
+            "ID": 12.0,
+            "Latitude": 1e-7,
+            "Count": 5,
+            "Ratio": 1e22,
+        }
+        number_format = ariane_core.NumberFormat(
+            fields={"Length": 2, "Azimut": "float", "Depth": 3, "ID": "int"}
+        )
+        options = ariane_core.XmlWriterOptions(number_format=number_format)
+
+        assert ariane_core.dict_to_xml_str(data, "Shot", options) == (
+            '<?xml version="1.0" encoding="utf-8"?><Shot>'
+            "<Length>3.00</Length><Azimut>333.0</Azimut><Depth>0.123</Depth>"
+            "<ID>12</ID><Latitude>0.0000001</Latitude><Count>5</Count>"
+            "<Ratio>10000000000000000000000.0</Ratio></Shot>"
+        )
+        assert number_format.style_for("Length") == 2
+        assert number_format.style_for("Unknown") == "auto"
+
+    def test_dict_to_xml_str_ariane_number_format(self):
+        data = {"Azimut": 333, "Depth": 0.0, "FromID": 7, "ID": 8.0, "Length": 2.16}
+        produced_xml = ariane_core.dict_to_xml_str(
+            data, "SurveyData", ariane_core.XmlWriterOptions.ariane()
+        )
+        assert "<Azimut>333.0</Azimut>" in produced_xml
+        assert "<Depth>0.0</Depth>" in produced_xml
+        assert "<FromID>7</FromID>" in produced_xml
+        assert "<ID>8</ID>" in produced_xml
+        assert "<Length>2.16</Length>" in produced_xml
+
+    def test_number_format_validation(self):
+        with pytest.raises(ValueError, match="Unsupported number style"):
+            ariane_core.NumberFormat(fields={"Length": "scientific"})
+        for flag in [True, False]:
+            with pytest.raises(ValueError, match="Unsupported number style"):
+                ariane_core.NumberFormat(fields={"Length": flag})
+
+    def test_xml_str_to_dict_cdata_as_text(self):
+        xml_str = (
+            "<SurveyData><Comment><![CDATA[Restriction, <tight> & silty]]></Comment>"
+            "<Name>A<![CDATA[1]]></Name></SurveyData>"
+        )
+        assert ariane_core.xml_str_to_dict(xml_str) == {
+            "SurveyData": {"Comment": "Restriction, <tight> & silty", "Name": "A1"}
+        }
+
+    def test_xml_str_to_dict_comments_and_pi(self):
+        xml_str = (
+            '<?xml version="1.0" encoding="utf-8"?>'
+            "<!-- exported by a third party tool -->"
+            '<?xml-stylesheet href="survey.xsl"?>'
+            "<CaveFile><!-- header --><caveName>DEMO</caveName>"
+            "<Data><SurveyData><ID>1</ID><!-- first --><!-- shot --></SurveyData>"
+            "</Data></CaveFile>"
+        )
+
+        assert ariane_core.xml_str_to_dict(xml_str) == {
+            "CaveFile": {"caveName": "DEMO", "Data": {"SurveyData": {"ID": "1"}}}
+        }
+
+        produced_data = ariane_core.xml_str_to_dict(
+            xml_str, keep_comments=True, keep_processing_instructions=True
+        )
+        # The index is the position among the sibling nodes
+        assert produced_data == {
+            "#comment": {"index": 0, "text": " exported by a third party tool "},
+            "#pi": {"index": 1, "text": 'xml-stylesheet href="survey.xsl"'},
+            "CaveFile": {
+                "#comment": {"index": 0, "text": " header "},
+                "caveName": "DEMO",
+                "Data": {
+                    "SurveyData": {
+                        "ID": "1",
+                        "#comment": [
+                            {"index": 1, "text": " first "},
+                            {"index": 2, "text": " shot "},
+                        ],
+                    }
+                },
+            },
+        }
+
+        produced_xml = ariane_core.dict_to_xml_str(
+            produced_data["CaveFile"], "CaveFile"
+        )
+        assert produced_xml == (
+            '<?xml version="1.0" encoding="utf-8"?>'
+            "<CaveFile><!-- header --><caveName>DEMO</caveName>"
+            "<Data><SurveyData><ID>1</ID><!-- first --><!-- shot --></SurveyData>"
+            "</Data></CaveFile>"
+        )
+
+    def test_comments_keep_document_order(self):
+        xml_str = (
+            '<?xml version="1.0" encoding="utf-8"?>'
+            "<!-- a --><Data><!-- b --><Shot>1</Shot><!-- c --><Shot>2</Shot>"
+            "<?survey check?><Name>N<!-- d --></Name><!-- e --></Data><!-- f -->"
+        )
+        data = ariane_core.xml_str_to_dict(
+            xml_str, keep_comments=True, keep_processing_instructions=True
+        )
+        assert data["Data"]["Shot"] == ["1", "2"]
+        assert data["Data"]["Name"] == {
+            "#text": "N",
+            "#comment": {"index": 0, "text": " d "},
+        }
+
+        with tempfile.TemporaryDirectory() as tmpdir:
+            output_path = Path(tmpdir) / "output.tmlu"
+            options = ariane_core.XmlWriterOptions()
+            ariane_core.dict_to_ariane_tmlu_file(data, output_path, options)
+            assert output_path.read_text(encoding="utf-8") == xml_str
+
+    def test_dict_to_xml_str_attributes_and_text(self):
+        xml_str = '<Layer name="A &quot;B&quot;" visible="true">text<style/></Layer>'
+        data = ariane_core.xml_str_to_dict(xml_str, keep_null=True)
+        assert data == {
+            "Layer": {
+                "@name": 'A "B"',
+                "@visible": "true",
+                "#text": "text",
+                "style": None,
+            }
+        }
+        assert ariane_core.dict_to_xml_str(data["Layer"], "Layer") == (
+            '<?xml version="1.0" encoding="utf-8"?>'
+            '<Layer name="A &quot;B&quot;" visible="true">text<style/></Layer>'
+        )
+
+    def test_xml_str_to_dict_empty_elements_with_attributes(self):
+        xml_str = '<Layers><layerList name="Walls"/><style/></Layers>'
+        assert ariane_core.xml_str_to_dict(xml_str, keep_null=True) == {
+            "Layers": {"layerList": {"@name": "Walls"}, "style": None}
+        }
+        assert ariane_core.xml_str_to_dict(xml_str, keep_null=False) == {
+            "Layers": {"layerList": {"@name": "Walls"}}
+        }
+
+    def test_xml_str_to_dict_mixed_content(self):
+        # Text is kept alongside child elements, before or after them
+        xml_str = "<Note>see<b>this</b></Note>"
+        assert ariane_core.xml_str_to_dict(xml_str) == {
+            "Note": {"#text": "see", "b": "this"}
+        }
+        xml_str = "<Note><b>this</b>page</Note>"
+        assert ariane_core.xml_str_to_dict(xml_str) == {
+            "Note": {"b": "this", "#text": "page"}
+        }
+        # Runs of text around children keep a separator
+        xml_str = "<Note>see <b>this</b> page</Note>"
+        assert ariane_core.xml_str_to_dict(xml_str) == {
+            "Note": {"#text": "see page", "b": "this"}
+        }
+        assert ariane_core.xml_str_to_dict("<Note>see <b/> page</Note>") == {
+            "Note": {"#text": "see page", "b": None}
+        }
+
+    def test_dict_to_xml_str_invalid_comment(self):
+        with pytest.raises(ValueError, match="XML generation error"):
+            ariane_core.dict_to_xml_str({"#comment": "a -- b"}, "CaveFile")
+
+    def test_dict_to_ariane_tml_file_keeps_comments(self):
+        data = {
+            "#comment": " exported by a third party tool ",
+            "CaveFile": {"#comment": " header ", "caveName": "DEMO"},
+        }
+
+        with tempfile.TemporaryDirectory() as tmpdir:
+            output_path = Path(tmpdir) / "output.tml"
+            ariane_core.dict_to_ariane_tml_file(data, output_path)
+            reloaded_data = ariane_core.load_ariane_tml_file_to_dict(
+                output_path, keep_comments=True
+            )
+
+        assert reloaded_data == {
+            "#comment": {"index": 0, "text": " exported by a third party tool "},
+            "CaveFile": {
+                "#comment": {"index": 0, "text": " header "},
+                "caveName": "DEMO",
+            },
+        }
+
+    def test_xml_bytes_to_dict_latin1(self):
+        xml_bytes = (
+            '<?xml version="1.0" encoding="ISO-8859-1"?>'
+            "<CaveFile><caveName>Grotte de l'Éléphant</caveName>"
+            "<Explorer>José Núñez</Explorer></CaveFile>"
+        ).encode("latin-1")
+        data = ariane_core.xml_bytes_to_dict(xml_bytes)
+        assert data == {
+            "CaveFile": {
+                "caveName": "Grotte de l'Éléphant",
+                "Explorer": "José Núñez",
+            }
+        }
+
+    @parameterized.expand(
+        [
+            ("windows-1252", "cp1252", "\u2018Œuvre\u2019 \u20ac"),
+            ("ISO-8859-15", "iso8859_15", "Œuvre \u20ac"),
+        ]
+    )
+    def test_xml_bytes_to_dict_single_byte_encodings(self, label, codec, note):
+        xml_bytes = (
+            f'<?xml version="1.0" encoding="{label}"?><Cave><Note>{note}</Note></Cave>'
+        ).encode(codec)
+        assert ariane_core.xml_bytes_to_dict(xml_bytes) == {"Cave": {"Note": note}}
+
+    def test_xml_bytes_to_dict_bom(self):
+        xml_str = '<?xml version="1.0"?><Cave><Name>Cénote</Name></Cave>'
+        expected = {"Cave": {"Name": "Cénote"}}
+        for codec in ("utf-8-sig", "utf-16", "utf-16-le", "utf-16-be"):
+            # UTF-16 without BOM is detected from the leading `<?`
+            xml_bytes = xml_str.encode(codec)
+            assert ariane_core.xml_bytes_to_dict(xml_bytes) == expected, codec
+
+    def test_xml_bytes_to_dict_utf16_label_without_bom(self):
+        # A declaration readable as single bytes means the document is not UTF-16
+        for label in ("UTF-16", "UTF-16LE", "UTF-16BE"):
+            xml_bytes = (
+                f'<?xml version="1.0" encoding="{label}"?>'
+                "<Cave><Name>Cénote</Name></Cave>"
+            ).encode("utf-8")
+            data = ariane_core.xml_bytes_to_dict(xml_bytes)
+            assert data == {"Cave": {"Name": "Cénote"}}, label
+
+    def test_xml_bytes_to_dict_invalid_utf8(self):
+        xml_bytes = b"<Cave><Name>Bad \xff\xfe byte</Name></Cave>"
+        with pytest.raises(ValueError, match="lossy=True"):
+            ariane_core.xml_bytes_to_dict(xml_bytes)
+
+        with warnings.catch_warnings(record=True) as caught:
+            warnings.simplefilter("always")
+            data = ariane_core.xml_bytes_to_dict(xml_bytes, lossy=True)
+
+        assert data == {"Cave": {"Name": "Bad \ufffd\ufffd byte"}}
+        assert len(caught) == 1
+        assert issubclass(caught[0].category, UnicodeWarning)
+        assert "0xFF" in str(caught[0].message)
+
+    def test_load_ariane_tml_file_to_dict_latin1(self):
+        xml_bytes = (
+            '<?xml version="1.0" encoding="ISO-8859-1"?>'
+            "<CaveFile><caveName>Gouffre Bérger</caveName></CaveFile>"
+        ).encode("latin-1")
+
+        with tempfile.TemporaryDirectory() as tmpdir:
+            tml_path = Path(tmpdir) / "latin1.tml"
+            with zipfile.ZipFile(tml_path, "w") as archive:
+                archive.writestr("Data.xml", xml_bytes)
+            data = ariane_core.load_ariane_tml_file_to_dict(tml_path)
+
+        assert data == {"CaveFile": {"caveName": "Gouffre Bérger"}}
+
+    def test_xml_str_to_dict_with_mapping(self):
+        xml_str = (
+            '<CaveFile><Data><SurveyData><Azimut>12.0</Azimut><Length>5.0</Length>'
+            '<Shape><RadiusCollection><RadiusVector><angle>0.0</angle>'
+            "<length>1.5</length></RadiusVector></RadiusCollection></Shape>"
+            '</SurveyData></Data><Layers><layerList name="Walls" visible="true"/>'
+            "</Layers></CaveFile>"
+        )
+        mapping = KeyMapping(
+            {
+                "Azimut": "azimuth",
+                "CaveFile.Data.SurveyData.Length": "length",
+                "**.RadiusVector.length": "radius",
+                "name": "layer_name",
+                "CaveFile": "cave",
+            }
+        )
+        data = ariane_core.xml_str_to_dict(xml_str, mapping=mapping)
+        assert data == {
+            "cave": {
+                "Data": {
+                    "SurveyData": {
+                        "azimuth": "12.0",
+                        "length": "5.0",
+                        "Shape": {
+                            "RadiusCollection": {
+                                "RadiusVector": {"angle": "0.0", "radius": "1.5"}
+                            }
+                        },
+                    }
+                },
+                "Layers": {"layerList": {"@layer_name": "Walls", "@visible": "true"}},
+            }
+        }
+
+        assert ariane_core.dict_to_xml_str(
+            data["cave"], "cave", mapping=mapping
+        ) == f'<?xml version="1.0" encoding="utf-8"?>{xml_str}'
+
+    def test_load_ariane_tml_file_to_dict_with_mapping(self):
+        mapping = {"Azimut": "azimuth", "Depth": "depth", "SurveyData": "shots"}
+        data = ariane_core.load_ariane_tml_file_to_dict(
+            "tests/artifacts/test_simple.tml", mapping=mapping
+        )
+        expected = KeyMapping(mapping).forward(
+            ariane_core.load_ariane_tml_file_to_dict("tests/artifacts/test_simple.tml")
+        )
+        assert data == expected
+        assert "shots" in data["CaveFile"]["Data"]
+
+        with tempfile.TemporaryDirectory() as tmpdir:
+            output_path = Path(tmpdir) / "output.tml"
+            ariane_core.dict_to_ariane_tml_file(data, output_path, mapping=mapping)
+            reloaded_data = ariane_core.load_ariane_tml_file_to_dict(output_path)
+
+        assert "SurveyData" in reloaded_data["CaveFile"]["Data"]
+        assert KeyMapping(mapping).forward(reloaded_data) == data
+
+    def test_xml_str_to_dict_mapping_collision(self):
+        with pytest.raises(ValueError, match="Key collision"):
+            ariane_core.xml_str_to_dict(
+                "<Shot><Azimut>1</Azimut><azimuth>2</azimuth></Shot>",
+                mapping={"Azimut": "azimuth"},
+            )
@@ -0,0 +1,84 @@
+import importlib.util
+import math
+import unittest
+
+import pytest
+from openspeleo_core import ariane_core
+
+from tests.ariane import HAND_SURVEY
+
+MEASUREMENTS = ["Length", "Azimut", "Inclination", "Depth"]
+LRUD = ["Left", "Right", "Up", "Down"]
+
+
+class TestShotArrays(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.data = ariane_core.load_ariane_tml_file_to_dict(HAND_SURVEY)
+        cls.shots = cls.data["CaveFile"]["Data"]["SurveyData"]
+
+    @unittest.skipIf(
+        importlib.util.find_spec("numpy") is not None, "NumPy is installed"
+    )
+    def test_missing_numpy(self):
+        with pytest.raises(ImportError):
+            ariane_core.shot_arrays(self.data)
+
+    def test_arrays(self):
+        np = pytest.importorskip("numpy")
+
+        arrays = ariane_core.shot_arrays(self.data)
+        assert list(arrays) == ["ID", *MEASUREMENTS, *LRUD, "x", "y", "z"]
+        assert arrays["ID"].dtype == object
+        assert arrays["ID"].tolist() == [shot["ID"] for shot in self.shots]
+        for name in [*MEASUREMENTS, *LRUD, "x", "y", "z"]:
+            array = arrays[name]
+            assert array.dtype == np.float64
+            assert array.flags["C_CONTIGUOUS"]
+            assert array.shape == (len(self.shots),)
+
+        assert arrays["Length"].tolist() == [
+            float(shot["Length"]) for shot in self.shots
+        ]
+        for name in LRUD:
+            assert arrays[name].tolist() == [float(shot[name]) for shot in self.shots]
+
+        root = [shot["FromID"] for shot in self.shots].index("-1")
+        assert (arrays["x"][root], arrays["y"][root]) == (0.0, 0.0)
+        assert arrays["z"][root] == -float(self.shots[root]["Depth"])
+
+    def test_missing_values(self):
+        np = pytest.importorskip("numpy")
+
+        data = {
+            "CaveFile": {
+                "unit": "m",
+                "Data": {
+                    "SurveyData": [
+                        {"ID": "0", "FromID": "-1", "Length": "0", "Depth": "0"},
+                        {"ID": "1", "FromID": "0", "Length": "n/a", "Depth": "2"},
+                    ]
+                },
+            }
+        }
+        arrays = ariane_core.shot_arrays(data)
+        assert arrays["Depth"].tolist() == [0.0, 2.0]
+        assert math.isnan(arrays["Length"][1])
+        assert np.isnan(arrays["Left"]).all()
+
+    def test_loader(self):
+        np = pytest.importorskip("numpy")
+
+        arrays = ariane_core.load_ariane_tml_file_to_arrays(HAND_SURVEY, unit="ft")
+        expected = ariane_core.shot_arrays(ariane_core.convert_units(self.data, "ft"))
+        assert list(arrays) == list(expected)
+        assert arrays["ID"].tolist() == expected["ID"].tolist()
+        for name in list(arrays)[1:]:
+            np.testing.assert_array_equal(arrays[name], expected[name])
+
+        with pytest.raises(FileNotFoundError):
+            ariane_core.load_ariane_tml_file_to_arrays("missing.tml")
+
+
+if __name__ == "__main__":
+    unittest.main()
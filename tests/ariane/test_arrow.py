@@ -0,0 +1,177 @@
+import ctypes
+import tempfile
+import unittest
+from pathlib import Path
+
+import pytest
+from openspeleo_core import ariane_core
+
+from tests.ariane import HAND_SURVEY
+
+
+class ArrowSchema(ctypes.Structure):
+    pass
+
+
+ArrowSchema._fields_ = [
+    ("format", ctypes.c_char_p),
+    ("name", ctypes.c_char_p),
+    ("metadata", ctypes.c_void_p),
+    ("flags", ctypes.c_int64),
+    ("n_children", ctypes.c_int64),
+    ("children", ctypes.POINTER(ctypes.POINTER(ArrowSchema))),
+    ("dictionary", ctypes.c_void_p),
+    ("release", ctypes.c_void_p),
+    ("private_data", ctypes.c_void_p),
+]
+
+
+class ArrowArray(ctypes.Structure):
+    pass
+
+
+ArrowArray._fields_ = [
+    ("length", ctypes.c_int64),
+    ("null_count", ctypes.c_int64),
+    ("offset", ctypes.c_int64),
+    ("n_buffers", ctypes.c_int64),
+    ("n_children", ctypes.c_int64),
+    ("buffers", ctypes.POINTER(ctypes.c_void_p)),
+    ("children", ctypes.POINTER(ctypes.POINTER(ArrowArray))),
+    ("dictionary", ctypes.c_void_p),
+    ("release", ctypes.c_void_p),
+    ("private_data", ctypes.c_void_p),
+]
+
+
+def _capsule_struct(capsule, name, struct):
+    get_pointer = ctypes.pythonapi.PyCapsule_GetPointer
+    get_pointer.restype = ctypes.c_void_p
+    get_pointer.argtypes = [ctypes.py_object, ctypes.c_char_p]
+    return struct.from_address(get_pointer(capsule, name))
+
+
+class TestShotTable(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.data = ariane_core.load_ariane_tml_file_to_dict(HAND_SURVEY)
+        cls.shots = cls.data["CaveFile"]["Data"]["SurveyData"]
+
+    def test_columns(self):
+        table = ariane_core.shots_to_arrow(self.data)
+        assert table.num_rows == len(table) == len(self.shots)
+        assert table.column_names[:5] == ["ID", "FromID", "Type", "Section", "Length"]
+        assert repr(table) == f"ShotTable(rows={len(self.shots)}, columns=15)"
+
+        table = ariane_core.shots_to_arrow(self.data, columns=["ID", "x", "Depth"])
+        assert table.column_names == ["ID", "x", "Depth"]
+        with pytest.raises(ValueError, match="No column"):
+            ariane_core.shots_to_arrow(self.data, columns=[])
+
+    def test_c_data_interface(self):
+        table = ariane_core.shots_to_arrow(self.data, columns=["ID", "Length", "z"])
+
+        # The capsules own the exported structures and must outlive them
+        schema_capsule = table.__arrow_c_schema__()
+        schema = _capsule_struct(schema_capsule, b"arrow_schema", ArrowSchema)
+        assert schema.format == b"+s"
+        children = [schema.children[i].contents for i in range(schema.n_children)]
+        assert [(child.name, child.format) for child in children] == [
+            (b"ID", b"l"),
+            (b"Length", b"g"),
+            (b"z", b"g"),
+        ]
+
+        schema_capsule, array_capsule = table.__arrow_c_array__()
+        array = _capsule_struct(array_capsule, b"arrow_array", ArrowArray)
+        assert (array.length, array.n_children) == (len(self.shots), 3)
+        schema = _capsule_struct(schema_capsule, b"arrow_schema", ArrowSchema)
+        assert schema.n_children == 3
+
+        stream = table.__arrow_c_stream__()
+        is_valid = ctypes.pythonapi.PyCapsule_IsValid
+        is_valid.argtypes = [ctypes.py_object, ctypes.c_char_p]
+        assert is_valid(stream, b"arrow_array_stream")
+
+    def test_station_ids(self):
+        data = {
+            "CaveFile": {
+                "unit": "m",
+                "Data": {
+                    "SurveyData": [
+                        {"ID": "1", "FromID": "-1"},
+                        {"ID": "2.0", "FromID": 1},
+                        {"ID": "A3", "FromID": "2"},
+                        {"ID": "4", "FromID": "2.5"},
+                    ]
+                },
+            }
+        }
+        table = ariane_core.shots_to_arrow(data, columns=["ID", "FromID"])
+        schema_capsule, array_capsule = table.__arrow_c_array__()
+        array = _capsule_struct(array_capsule, b"arrow_array", ArrowArray)
+
+        # Stations which are not integers are null
+        columns = []
+        for index in range(array.n_children):
+            child = array.children[index].contents
+            validity = ctypes.c_uint8.from_address(child.buffers[0])
+            values = (ctypes.c_int64 * child.length).from_address(child.buffers[1])
+            columns.append(
+                [
+                    value if validity.value >> row & 1 else None
+                    for row, value in enumerate(values)
+                ]
+            )
+        assert columns == [[1, 2, None, 4], [-1, 1, 2, None]]
+
+        schema = _capsule_struct(schema_capsule, b"arrow_schema", ArrowSchema)
+        formats = [schema.children[i].contents.format for i in range(2)]
+        assert formats == [b"l", b"l"]
+
+    def test_pyarrow(self):
+        pa = pytest.importorskip("pyarrow")
+
+        table = ariane_core.shots_to_arrow(self.data, columns=["ID", "Length", "Date"])
+        batch = pa.record_batch(table)
+        assert batch.schema.field("Length").type == pa.float64()
+        assert batch.schema.metadata == {b"unit": b"m"}
+        assert batch.schema.field("ID").type == pa.int64()
+        assert batch.column("ID").to_pylist() == [
+            int(shot["ID"]) for shot in self.shots
+        ]
+        assert batch.column("Length").to_pylist() == [
+            float(shot["Length"]) for shot in self.shots
+        ]
+        assert pa.table(table).num_rows == len(self.shots)
+
+    def test_loader(self):
+        table = ariane_core.load_ariane_tml_file_to_arrow(HAND_SURVEY, unit="ft")
+        assert table.num_rows == len(self.shots)
+
+        with tempfile.TemporaryDirectory() as tmpdir:
+            loaded = Path(tmpdir) / "loaded.parquet"
+            converted = Path(tmpdir) / "converted.parquet"
+            table.to_parquet(str(loaded))
+            data = ariane_core.convert_units(self.data, "ft")
+            ariane_core.shots_to_arrow(data).to_parquet(str(converted))
+            assert loaded.read_bytes() == converted.read_bytes()
+
+        with pytest.raises(FileNotFoundError):
+            ariane_core.load_ariane_tml_file_to_arrow("missing.tml")
+
+    def test_parquet(self):
+        table = ariane_core.shots_to_arrow(self.data)
+        with tempfile.TemporaryDirectory() as tmpdir:
+            path = Path(tmpdir) / "shots.parquet"
+            for compression in ["snappy", "uncompressed"]:
+                table.to_parquet(str(path), compression=compression)
+                content = path.read_bytes()
+                assert content[:4] == content[-4:] == b"PAR1"
+
+            with pytest.raises(ValueError, match="Unsupported compression"):
+                table.to_parquet(str(path), compression="zstd")
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,223 @@
+import datetime
+import math
+import tempfile
+import unittest
+from pathlib import Path
+
+import pytest
+from openspeleo_core import ariane_core
+from openspeleo_core.ariane_core import GeomagneticModel
+
+from tests.ariane import HAND_SURVEY, survey_shots
+
+
+def _angle_delta(a, b):
+    return (float(a) - float(b) + 180.0) % 360.0 - 180.0
+
+
+class TestMagneticDeclination(unittest.TestCase):
+    def test_reference_values(self):
+        # Test values published with the WMM2015 and WMM2020 reports
+        for latitude, longitude, year, expected in [
+            (80.0, 0.0, 2015.0, -3.85),
+            (0.0, 120.0, 2015.0, 0.57),
+            (-80.0, 240.0, 2015.0, 69.81),
+            (80.0, 0.0, 2017.5, -2.75),
+            (80.0, 0.0, 2020.0, -1.28),
+            (0.0, 120.0, 2020.0, 0.16),
+            (-80.0, 240.0, 2020.0, 69.36),
+            (80.0, 0.0, 2022.5, 0.01),
+        ]:
+            declination = ariane_core.magnetic_declination(latitude, longitude, year)
+            assert declination == pytest.approx(expected, abs=0.01)
+
+        declination = ariane_core.magnetic_declination(80.0, 0.0, 2020.0, 100_000.0)
+        assert declination == pytest.approx(-1.70, abs=0.01)
+
+    def test_date_types(self):
+        expected = ariane_core.magnetic_declination(20.26, -87.49, 2024.0)
+        for date in [datetime.date(2024, 1, 1), "2024-01-01", "2024-01-01T10:00"]:
+            assert ariane_core.magnetic_declination(20.26, -87.49, date) == expected
+
+        with pytest.raises(ValueError, match="Invalid date"):
+            ariane_core.magnetic_declination(20.26, -87.49, "01/01/2024")
+        with pytest.raises(TypeError):
+            ariane_core.magnetic_declination(20.26, -87.49, None)
+
+    def test_models(self):
+        models = GeomagneticModel.embedded()
+        assert [(model.name, model.epoch) for model in models] == [
+            ("WMM-2015", 2015.0),
+            ("WMM-2020", 2020.0),
+        ]
+        # Each release applies from its epoch on
+        wmm2015 = models[0].declination(0.0, 120.0, 2019.0)
+        assert ariane_core.magnetic_declination(0.0, 120.0, 2019.0) == wmm2015
+        wmm2020 = models[1].declination(0.0, 120.0, 2020.0)
+        assert ariane_core.magnetic_declination(0.0, 120.0, 2020.0) == wmm2020
+
+        # The model is still chosen by date among the given ones
+        for year, expected in [(2019.0, wmm2015), (2020.0, wmm2020)]:
+            declination = ariane_core.magnetic_declination(
+                0.0, 120.0, year, models=models
+            )
+            assert declination == expected
+        with pytest.raises(ValueError, match="No geomagnetic model is valid at 2019"):
+            ariane_core.magnetic_declination(0.0, 120.0, 2019.0, models=models[1:])
+        with pytest.raises(ValueError, match="WMM-2020 from 2020 to 2025"):
+            models[1].declination(0.0, 120.0, 2019.0)
+
+    def test_validity_window(self):
+        wmm2015, wmm2020 = GeomagneticModel.embedded()
+        assert (wmm2015.valid_until, wmm2020.valid_until) == (2020.0, 2025.0)
+
+        for year in [2015.0, 2024.99]:
+            ariane_core.magnetic_declination(20.26, -87.49, year)
+
+        # Dates outside every embedded release are rejected, not extrapolated
+        with pytest.raises(ValueError) as error:
+            ariane_core.magnetic_declination(20.26, -87.49, 2014.5)
+        assert str(error.value) == (
+            "No geomagnetic model is valid at 2014.50 "
+            "(WMM-2015 from 2015 to 2020, WMM-2020 from 2020 to 2025)"
+        )
+        with pytest.raises(ValueError, match="No geomagnetic model is valid"):
+            ariane_core.magnetic_declination(20.26, -87.49, "2026-01-01")
+
+    def test_model_from_file(self):
+        with tempfile.TemporaryDirectory() as tmpdir:
+            path = Path(tmpdir) / "dipole.cof"
+            path.write_text(
+                "    2000.0            DIPOLE        01/01/2000\n"
+                "  1  0  -30000.0       0.0        0.0        0.0\n"
+                "  1  1       0.0    3000.0        0.0        0.0\n"
+                "999999999999999999999999999999999999999999999999\n"
+            )
+            model = GeomagneticModel.from_file(str(path))
+            assert (model.name, model.epoch) == ("DIPOLE", 2000.0)
+            # A positive h11 deviates compasses westwards on the prime meridian
+            expected = -math.degrees(math.atan2(3000.0, 30000.0))
+            assert model.declination(0.0, 0.0, 2000.0) == pytest.approx(expected)
+            assert model.declination(0.0, 90.0, 2000.0) == pytest.approx(0.0, abs=1e-9)
+            declination = ariane_core.magnetic_declination(
+                0.0, 0.0, 2000.0, models=[model]
+            )
+            assert declination == pytest.approx(expected)
+
+            path.write_text("    2000.0  BROKEN\n  1  0  x\n")
+            with pytest.raises(ValueError, match="Invalid coefficient line"):
+                GeomagneticModel.from_file(str(path))
+
+
+class TestCorrectDeclination(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.data = ariane_core.load_ariane_tml_file_to_dict(HAND_SURVEY)
+
+    def test_to_true_north(self):
+        corrected = ariane_core.correct_declination(self.data, "true")
+        assert corrected["CaveFile"]["useMagneticAzimuth"] == "false"
+
+        entrance = next(
+            shot for shot in survey_shots(self.data) if float(shot["Latitude"]) != 0.0
+        )
+        declination = ariane_core.magnetic_declination(
+            float(entrance["Latitude"]), float(entrance["Longitude"]), entrance["Date"]
+        )
+        for shot, corrected_shot in zip(
+            survey_shots(self.data), survey_shots(corrected), strict=True
+        ):
+            delta = _angle_delta(corrected_shot["Azimut"], shot["Azimut"])
+            assert delta == pytest.approx(declination, abs=1e-5)
+            assert 0.0 <= float(corrected_shot["Azimut"]) < 360.0
+
+    def test_round_trip(self):
+        corrected = ariane_core.correct_declination(self.data, "true")
+        assert ariane_core.correct_declination(corrected, "true") == corrected
+
+        back = ariane_core.correct_declination(corrected, "magnetic")
+        assert back["CaveFile"]["useMagneticAzimuth"] == "true"
+        for shot, back_shot in zip(
+            survey_shots(self.data), survey_shots(back), strict=True
+        ):
+            delta = _angle_delta(back_shot["Azimut"], shot["Azimut"])
+            assert delta == pytest.approx(0.0, abs=1e-5)
+
+    def test_location(self):
+        data = {
+            "CaveFile": {
+                "useMagneticAzimuth": "true",
+                "Data": {
+                    "SurveyData": [
+                        {"ID": "1", "Azimut": "10.0", "Date": "2016-06-01"},
+                        {"ID": "2", "Azimut": "10.0", "Date": "2023-06-01"},
+                    ]
+                },
+            }
+        }
+        with pytest.raises(ValueError, match="no located shot"):
+            ariane_core.correct_declination(data, "true")
+        with pytest.raises(ValueError, match="must be given together"):
+            ariane_core.correct_declination(data, "true", latitude=45.0)
+        with pytest.raises(ValueError, match="Unsupported north reference"):
+            ariane_core.correct_declination(data, "grid")
+
+        corrected = ariane_core.correct_declination(
+            data, "true", latitude=45.0, longitude=5.0
+        )
+        # Each shot is corrected at its own date
+        for shot, date in zip(
+            survey_shots(corrected), ["2016-06-01", "2023-06-01"], strict=True
+        ):
+            declination = ariane_core.magnetic_declination(45.0, 5.0, date)
+            assert float(shot["Azimut"]) == pytest.approx(10.0 + declination, abs=1e-5)
+
+    def test_models(self):
+        data = {
+            "CaveFile": {
+                "Data": {
+                    "SurveyData": [
+                        {"ID": "1", "Azimut": "10.0", "Date": "2019-06-01"},
+                        {"ID": "2", "Azimut": "10.0", "Date": "2026-06-01"},
+                    ]
+                },
+            }
+        }
+        # A shot dated outside every model is rejected rather than extrapolated
+        with pytest.raises(ValueError, match="No geomagnetic model is valid at 2026"):
+            ariane_core.correct_declination(data, "true", latitude=45.0, longitude=5.0)
+
+        # Each shot uses the given model in effect at its date
+        wmm2015, wmm2020 = GeomagneticModel.embedded()
+        survey_shots(data)[1]["Date"] = "2021-06-01"
+        corrected = ariane_core.correct_declination(
+            data, "true", latitude=45.0, longitude=5.0, models=[wmm2020, wmm2015]
+        )
+        for shot, model, date in zip(
+            survey_shots(corrected),
+            [wmm2015, wmm2020],
+            ["2019-06-01", "2021-06-01"],
+            strict=True,
+        ):
+            declination = model.declination(45.0, 5.0, date)
+            assert float(shot["Azimut"]) == pytest.approx(10.0 + declination, abs=1e-5)
+        with pytest.raises(ValueError, match="No geomagnetic model is valid at 2019"):
+            ariane_core.correct_declination(
+                data, "true", latitude=45.0, longitude=5.0, models=[wmm2020]
+            )
+
+    def test_load_and_write(self):
+        loaded = ariane_core.load_ariane_tml_file_to_dict(HAND_SURVEY, north="true")
+        assert loaded == ariane_core.correct_declination(self.data, "true")
+
+        with tempfile.TemporaryDirectory() as tmpdir:
+            path = Path(tmpdir) / "survey.tml"
+            ariane_core.dict_to_ariane_tml_file(loaded, path, north="magnetic")
+            written = ariane_core.load_ariane_tml_file_to_dict(path)
+
+        assert written["CaveFile"]["useMagneticAzimuth"] == "true"
+        assert not ariane_core.diff_surveys(self.data, written)
+
+
+if __name__ == "__main__":
+    unittest.main()
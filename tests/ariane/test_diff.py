@@ -0,0 +1,115 @@
+import copy
+import unittest
+
+from openspeleo_core import ariane_core
+
+from tests.ariane import survey_shots
+
+
+class TestSurveyDiff(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        with open("tests/artifacts/demo.xml") as f:  # noqa: PTH123
+            cls.base = ariane_core.xml_str_to_dict(f.read(), keep_null=False)
+
+    def setUp(self):
+        self.data = copy.deepcopy(self.base)
+
+    def test_identical(self):
+        diff = ariane_core.diff_surveys(self.base, self.data)
+        assert not diff
+        assert diff.report() == "No differences"
+        assert diff.shots_modified == []
+
+    def test_numeric_formatting_is_not_a_change(self):
+        survey_shots(self.data)[0]["Length"] = "0"
+        survey_shots(self.data)[0]["ID"] = 78
+        assert not ariane_core.diff_surveys(self.base, self.data)
+
+    def test_shots_added_removed_modified(self):
+        shots = survey_shots(self.data)
+        removed = shots.pop(0)
+        shots[0]["Length"] = "12.5"
+        shots[0]["Shape"]["RadiusCollection"]["RadiusVector"][1]["length"] = "2.0"
+        added = copy.deepcopy(shots[0])
+        added["ID"] = "200"
+        shots.append(added)
+
+        diff = ariane_core.diff_surveys(self.base, self.data)
+        assert [shot["ID"] for shot in diff.shots_removed] == [removed["ID"]]
+        assert [shot["ID"] for shot in diff.shots_added] == ["200"]
+        assert diff.shots_modified == [
+            {
+                "id": shots[0]["ID"],
+                "changes": [
+                    {"field": "Length", "old": "0.0", "new": "12.5"},
+                    {
+                        "field": "Shape.RadiusCollection.RadiusVector[1].length",
+                        "old": "0.0",
+                        "new": "2.0",
+                    },
+                ],
+            }
+        ]
+
+        report = diff.report()
+        assert "Shots added (1): 200" in report
+        assert f"Shots removed (1): {removed['ID']}" in report
+        assert "    Length: '0.0' -> '12.5'" in report
+
+    def test_section_rename(self):
+        old_name = survey_shots(self.data)[0]["Section"]
+        for shot in survey_shots(self.data):
+            if shot["Section"] == old_name:
+                shot["Section"] = "Main Line - Renamed"
+
+        diff = ariane_core.diff_surveys(self.base, self.data)
+        assert diff.sections_renamed == [
+            {"old": old_name, "new": "Main Line - Renamed"}
+        ]
+        assert diff.shots_modified == []
+
+    def test_partial_section_change_is_a_shot_change(self):
+        shots = survey_shots(self.data)
+        shots[0]["Section"] = shots[1]["Section"]
+
+        diff = ariane_core.diff_surveys(self.base, self.data)
+        assert diff.sections_renamed == []
+        assert diff.shots_modified[0]["changes"][0]["field"] == "Section"
+
+    def test_header_and_layers(self):
+        cave = self.data["CaveFile"]
+        cave["caveName"] = "DEMO CAVE 2"
+        cave["unit"] = "ft"
+        cave["useMagneticAzimuth"] = "false"
+        layers = cave["Layers"]["layerList"]
+        layers[0]["visible"] = "false"
+        layers.pop(1)
+        layers.append({"name": "Survey", "visible": "true"})
+
+        diff = ariane_core.diff_surveys(self.base, self.data)
+        assert diff.header == [
+            {"field": "caveName", "old": "DEMO CAVE", "new": "DEMO CAVE 2"},
+            {"field": "unit", "old": "m", "new": "ft"},
+            {"field": "useMagneticAzimuth", "old": "true", "new": "false"},
+        ]
+        assert diff.layers_added == ["Survey"]
+        assert diff.layers_removed == ["Default"]
+        assert diff.layers_modified == [
+            {
+                "name": "Overlay",
+                "changes": [{"field": "visible", "old": "true", "new": "false"}],
+            }
+        ]
+        assert diff.to_dict()["layers_added"] == ["Survey"]
+        assert "  caveName: 'DEMO CAVE' -> 'DEMO CAVE 2'" in diff.report()
+
+    def test_accepts_cave_file_content(self):
+        data = copy.deepcopy(self.data["CaveFile"])
+        data["caveName"] = "OTHER"
+        diff = ariane_core.diff_surveys(self.base["CaveFile"], data)
+        assert len(diff.header) == 1
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,93 @@
+import tempfile
+import unittest
+import xml.etree.ElementTree as ET
+from pathlib import Path
+
+import pytest
+from openspeleo_core import ariane_core
+
+from tests.ariane import HAND_SURVEY
+
+GPX = "{http://www.topografix.com/GPX/1/1}"
+
+
+def _point(element):
+    return (
+        float(element.get("lat")),
+        float(element.get("lon")),
+        float(element.find(f"{GPX}ele").text),
+    )
+
+
+class TestGpx(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.data = ariane_core.load_ariane_tml_file_to_dict(HAND_SURVEY)
+        cls.shots = cls.data["CaveFile"]["Data"]["SurveyData"]
+
+    def test_document(self):
+        root = ET.fromstring(ariane_core.dict_to_gpx_str(self.data))
+        assert root.tag == f"{GPX}gpx"
+        assert root.get("version") == "1.1"
+        assert root.find(f"{GPX}metadata/{GPX}name").text == "Carrie's Loft"
+
+    def test_waypoints(self):
+        root = ET.fromstring(ariane_core.dict_to_gpx_str(self.data))
+        waypoints = root.findall(f"{GPX}wpt")
+        # Only the entrance has coordinates
+        assert len(waypoints) == 1
+        assert waypoints[0].find(f"{GPX}name").text == "START"
+        assert _point(waypoints[0]) == pytest.approx(
+            (20.2591549, -87.48651031, 0.0), abs=1e-8
+        )
+
+    def test_tracks(self):
+        root = ET.fromstring(ariane_core.dict_to_gpx_str(self.data))
+        tracks = root.findall(f"{GPX}trk")
+        sections = []
+        for shot in self.shots:
+            if shot["Type"] != "CLOSURE" and shot["Section"] not in sections:
+                sections.append(shot["Section"])
+        assert [track.find(f"{GPX}name").text for track in tracks] == sections
+
+        # Track points are station positions, rounded to 8 decimals
+        stations = {
+            (round(position["latitude"], 8), round(position["longitude"], 8))
+            for position in ariane_core.station_coordinates(self.data).values()
+        }
+        points = [_point(point) for point in root.iter(f"{GPX}trkpt")]
+        assert {(lat, lon) for lat, lon, _ in points} <= stations
+
+        depths = [
+            float(shot["Depth"]) for shot in self.shots if shot["Type"] != "CLOSURE"
+        ]
+        assert min(ele for _, _, ele in points) == pytest.approx(-max(depths))
+
+    def test_location(self):
+        data = ariane_core.load_ariane_tml_file_to_dict(
+            "tests/artifacts/test_simple.tml"
+        )
+        with pytest.raises(ValueError, match="no located shot"):
+            ariane_core.dict_to_gpx_str(data)
+
+        root = ET.fromstring(
+            ariane_core.dict_to_gpx_str(data, latitude=45.0, longitude=3.0)
+        )
+        assert not root.findall(f"{GPX}wpt")
+        first = next(root.iter(f"{GPX}trkpt"))
+        assert _point(first)[:2] == pytest.approx((45.0, 3.0))
+
+    def test_file(self):
+        with tempfile.TemporaryDirectory() as tmpdir:
+            path = Path(tmpdir) / "survey.gpx"
+            ariane_core.dict_to_gpx_file(self.data, path)
+            assert path.read_text(encoding="utf-8") == ariane_core.dict_to_gpx_str(
+                self.data
+            )
+
+            with pytest.raises(OSError):
+                ariane_core.dict_to_gpx_file(self.data, Path(tmpdir) / "a" / "b.gpx")
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,252 @@
+import tempfile
+import unittest
+import warnings
+from pathlib import Path
+
+import pytest
+from openspeleo_core import ariane_core
+
+from tests.ariane import survey_shots
+
+POCKETTOPO = "tests/artifacts/pockettopo.txt"
+TOPODROID = "tests/artifacts/topodroid.csv"
+THERION = "tests/artifacts/therion.th"
+
+
+# Shot of each station, the first one ending there
+def _by_name(data):
+    stations = {}
+    for shot in survey_shots(data):
+        stations.setdefault(shot["Name"], shot)
+    return stations
+
+
+def _lrud(shot):
+    return [float(shot[field]) for field in ["Left", "Right", "Up", "Down"]]
+
+
+def _radii(shot):
+    vectors = shot["Shape"]["RadiusCollection"]["RadiusVector"]
+    return sorted((float(v["angle"]), float(v["length"])) for v in vectors)
+
+
+class TestPocketTopo(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.data = ariane_core.load_pockettopo_txt_file_to_dict(POCKETTOPO)
+        cls.stations = _by_name(cls.data)
+
+    def test_structure(self):
+        cave = self.data["CaveFile"]
+        assert cave["caveName"] == "pockettopo"
+        assert cave["unit"] == "m"
+        # The trips set a declination, azimuths are corrected to true north
+        assert cave["useMagneticAzimuth"] == "false"
+        assert ariane_core.validate(self.data) == []
+
+        start = survey_shots(self.data)[0]
+        assert (start["ID"], start["FromID"], start["Type"]) == ("0", "-1", "START")
+        assert start["Name"] == "1.0"
+        assert start["Section"] == "Series 1"
+
+    def test_legs(self):
+        # The three readings of the first leg are averaged
+        leg = self.stations["1.1"]
+        assert leg["FromID"] == self.stations["1.0"]["ID"]
+        assert float(leg["Length"]) == pytest.approx(10.0)
+        assert float(leg["Azimut"]) == pytest.approx(47.5)
+        assert float(leg["Inclination"]) == pytest.approx(0.0, abs=1e-9)
+
+        climb = self.stations["1.2"]
+        assert float(climb["Depth"]) == pytest.approx(-2.0)
+        assert climb["Comment"] == "Climb to the upper level"
+        assert climb["Date"] == "2024-03-09"
+        assert self.stations["2.0"]["Date"] == "2024-03-16"
+        assert self.stations["2.0"]["Section"] == "Series 2"
+
+    def test_loop_closure(self):
+        closure = next(s for s in survey_shots(self.data) if s["Type"] == "CLOSURE")
+        assert closure["ClosureToID"] == self.stations["1.0"]["ID"]
+        assert float(closure["Length"]) == 0.0
+        leg = next(s for s in survey_shots(self.data) if s["ID"] == closure["FromID"])
+        assert leg["Type"] == "REAL"
+        assert leg["Name"] == "1.0"
+
+    def test_splays(self):
+        assert _lrud(self.stations["1.0"]) == pytest.approx([2.0, 1.5, 0.0, 0.0])
+        radii = _radii(self.stations["1.0"])
+        assert radii == pytest.approx([(90.0, 1.5), (270.0, 2.0)])
+        assert _lrud(self.stations["1.1"]) == pytest.approx([0.0, 0.0, 3.0, 0.5])
+        # Stations without splays keep the empty cross-section Ariane writes
+        assert _radii(self.stations["1.2"]) == [
+            (0.0, 0.0),
+            (90.0, 0.0),
+            (180.0, 0.0),
+            (270.0, 0.0),
+        ]
+
+
+class TestTopoDroid(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.data = ariane_core.load_topodroid_csv_file_to_dict(TOPODROID)
+        cls.stations = _by_name(cls.data)
+
+    def test_header(self):
+        cave = self.data["CaveFile"]
+        assert cave["caveName"] == "Dry Sump"
+        assert cave["unit"] == "ft"
+        for shot in survey_shots(self.data):
+            assert shot["Date"] == "2024-05-11"
+            assert shot["Explorer"] == "Alice Martin, Bob Chen"
+            assert shot["Section"] == "Dry Sump"
+        assert ariane_core.validate(self.data) == []
+        # `declination 0.00` is what TopoDroid writes when none is set
+        assert cave["useMagneticAzimuth"] == "true"
+
+    def test_shots(self):
+        assert list(self.stations) == ["1", "2", "3"]
+        assert float(self.stations["2"]["Length"]) == pytest.approx(10.1)
+        assert self.stations["2"]["Comment"] == "Entrance, sloping"
+        assert float(self.stations["3"]["Depth"]) == pytest.approx(3.472964)
+
+        # LRUD columns are at the `from` station, splays give the other ones
+        assert _lrud(self.stations["1"]) == pytest.approx([2.0, 3.0, 0.0, 0.0])
+        lrud = _lrud(self.stations["2"])
+        assert lrud == pytest.approx([4.0, 5.0, 0.0, 0.0], abs=1e-4)
+        # A splay shot towards the station is turned around
+        assert _lrud(self.stations["3"]) == pytest.approx([0.0, 6.0, 0.0, 0.0])
+
+    def test_unit(self):
+        data = ariane_core.load_topodroid_csv_file_to_dict(TOPODROID, unit="m")
+        assert data["CaveFile"]["unit"] == "m"
+        assert float(_by_name(data)["2"]["Length"]) == pytest.approx(10.1 * 0.3048)
+
+
+class TestTherion(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        with warnings.catch_warnings(record=True) as caught:
+            warnings.simplefilter("always")
+            cls.data = ariane_core.load_therion_th_file_to_dict(THERION)
+        cls.warnings = [str(w.message) for w in caught]
+        cls.stations = _by_name(cls.data)
+
+    def test_centerlines(self):
+        cave = self.data["CaveFile"]
+        assert cave["caveName"] == "Blue Sump"
+        assert cave["unit"] == "m"
+
+        assert self.stations["1"]["Date"] == "2024-06-01"
+        assert self.stations["1"]["Explorer"] == "Ana Lopez, Jo Park"
+        assert self.stations["1"]["Section"] == "Blue Sump"
+        assert float(self.stations["1"]["Azimut"]) == pytest.approx(1.5)
+        assert float(self.stations["1"]["Depth"]) == pytest.approx(0.868241)
+        assert _lrud(self.stations["1"]) == pytest.approx([0.8, 1.2, 2.0, 0.0])
+
+    def test_declination_warning(self):
+        # Only the first centerline sets a declination
+        assert len(self.warnings) == 1
+        assert self.warnings[0].startswith("Trip(s) 2 (2024-06-02) set no declination")
+
+    def test_nested_diving_survey(self):
+        # Stations of nested surveys are qualified the way Therion refers to them
+        start = self.stations["a@dive"]
+        assert start["Type"] == "START"
+        assert start["Section"] == "Dive line"
+        assert start["Date"] == "2024-06-02"
+        assert _lrud(start) == pytest.approx([3.0, 3.0, 0.0, 0.0])
+
+        leg = self.stations["b@dive"]
+        assert float(leg["Length"]) == pytest.approx(20 * 0.3048)
+        assert float(leg["Depth"]) == pytest.approx(10 * 0.3048)
+        assert float(self.stations["c@dive"]["Depth"]) == pytest.approx(10 * 0.3048)
+
+
+class TestImportErrors(unittest.TestCase):
+    def setUp(self):
+        self._tmpdir = tempfile.TemporaryDirectory()
+        self.dir = Path(self._tmpdir.name)
+
+    def tearDown(self):
+        self._tmpdir.cleanup()
+
+    def _write(self, name, text, encoding="utf-8"):
+        path = self.dir / name
+        path.write_text(text, encoding=encoding)
+        return path
+
+    def test_invalid_files(self):
+        path = self._write("bad.txt", "TRIP\nDATA\n1.0\t1.1\t45.0\tflat\t10.0\n")
+        with pytest.raises(ValueError, match="Line 3: `flat` is not a number"):
+            ariane_core.load_pockettopo_txt_file_to_dict(path)
+
+        path = self._write("empty.csv", "# Empty survey\n")
+        with pytest.raises(ValueError, match="no shot"):
+            ariane_core.load_topodroid_csv_file_to_dict(path)
+
+        path = self._write(
+            "bad.th",
+            "centerline\n  data interleaved from to length\nendcenterline\n",
+        )
+        with pytest.raises(ValueError, match="Line 2: Unsupported data style"):
+            ariane_core.load_therion_th_file_to_dict(path)
+
+        with pytest.raises(FileNotFoundError):
+            ariane_core.load_therion_th_file_to_dict(self.dir / "missing.th")
+
+    def test_mixed_declinations(self):
+        text = (
+            "TRIP\nDATE 2024-03-09\nDECLINATION\t2.50\n"
+            "DATA\n1.0\t1.1\t45.0\t0.0\t10.0\t>\n"
+            "TRIP\nDATE 2024-03-16\nDECLINATION\t0.00\n"
+            "DATA\n1.1\t1.2\t90.0\t0.0\t5.0\t>\n"
+        )
+        path = self._write("mixed.txt", text)
+        with warnings.catch_warnings(record=True) as caught:
+            warnings.simplefilter("always")
+            data = ariane_core.load_pockettopo_txt_file_to_dict(path)
+
+        assert [str(w.message) for w in caught] == [
+            "Trip(s) 2 (2024-03-16) set no declination while other trips do: their "
+            "azimuths are taken as relative to true north, uncorrected"
+        ]
+        assert issubclass(caught[0].category, UserWarning)
+        assert float(_by_name(data)["1.1"]["Azimut"]) == pytest.approx(47.5)
+        assert float(_by_name(data)["1.2"]["Azimut"]) == pytest.approx(90.0)
+
+    def test_encodings(self):
+        # PocketTopo writes the Windows code page
+        path = self._write(
+            "cp1252.txt",
+            'TRIP\nDATA\n1.0\t1.1\t45.0\t0.0\t10.0\t>\t"Étroiture – 2 m"\n',
+            encoding="cp1252",
+        )
+        data = ariane_core.load_pockettopo_txt_file_to_dict(path)
+        assert _by_name(data)["1.1"]["Comment"] == "Étroiture – 2 m"
+
+        centerline = (
+            'survey s -title "Gouffre Bérénice"\n'
+            "centerline\ndata normal from to length compass clino\n0 1 5.0 0.0 0.0\n"
+            "endcenterline\nendsurvey\n"
+        )
+        path = self._write(
+            "latin1.th", "encoding iso8859-1\n" + centerline, encoding="latin-1"
+        )
+        data = ariane_core.load_therion_th_file_to_dict(path)
+        assert data["CaveFile"]["caveName"] == "Gouffre Bérénice"
+
+        # The declared encoding wins over the Windows-1252 fallback
+        path = self._write(
+            "declared.th", "encoding utf-8\n" + centerline, encoding="latin-1"
+        )
+        with pytest.raises(ValueError, match="Text decoding error: Invalid UTF-8"):
+            ariane_core.load_therion_th_file_to_dict(path)
+
+        path = self._write("cp1251.th", "encoding cp1251\n" + centerline)
+        with pytest.raises(ValueError, match="Unsupported encoding: `cp1251`"):
+            ariane_core.load_therion_th_file_to_dict(path)
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,136 @@
+import copy
+import tempfile
+import unittest
+from pathlib import Path
+
+from openspeleo_core import ariane_core
+
+from tests.ariane import find_shot, survey_shots
+
+
+def _new_shot(data, shot_id, from_id, length):
+    shot = copy.deepcopy(find_shot(data, "4"))
+    shot.update({"ID": shot_id, "FromID": from_id, "Length": length})
+    return shot
+
+
+class TestSurveyMerge(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        with open("tests/artifacts/demo.xml") as f:  # noqa: PTH123
+            cls.base = ariane_core.xml_str_to_dict(f.read(), keep_null=False)
+
+    def setUp(self):
+        self.ours = copy.deepcopy(self.base)
+        self.theirs = copy.deepcopy(self.base)
+
+    def merge(self):
+        return ariane_core.merge_surveys(self.base, self.ours, self.theirs)
+
+    def test_unchanged(self):
+        result = self.merge()
+        assert result.merged == self.base
+        assert result.conflicts == []
+        assert result.renumbered == []
+        assert not result.has_conflicts
+
+    def test_non_conflicting_changes(self):
+        self.ours["CaveFile"]["caveName"] = "DEMO CAVE 2"
+        find_shot(self.ours, "78")["Length"] = "10.0"
+        find_shot(self.theirs, "78")["Depth"] = "-3.0"
+        survey_shots(self.theirs).remove(find_shot(self.theirs, "2"))
+        survey_shots(self.theirs).append(_new_shot(self.theirs, "90", "89", "4.0"))
+
+        result = self.merge()
+        assert not result.has_conflicts
+        merged = result.merged
+        assert merged["CaveFile"]["caveName"] == "DEMO CAVE 2"
+        assert find_shot(merged, "78")["Length"] == "10.0"
+        assert find_shot(merged, "78")["Depth"] == "-3.0"
+        ids = [shot["ID"] for shot in survey_shots(merged)]
+        assert ids == ["78", "89", "1", "3", "4", "90"]
+
+    def test_renumbers_colliding_additions(self):
+        survey_shots(self.ours).append(_new_shot(self.ours, "90", "89", "4.0"))
+        survey_shots(self.theirs).append(_new_shot(self.theirs, "90", "4", "7.0"))
+        survey_shots(self.theirs).append(_new_shot(self.theirs, "91", "90", "2.0"))
+
+        result = self.merge()
+        assert not result.has_conflicts
+        assert result.renumbered == [{"old": "90", "new": "92"}]
+        shots = {shot["ID"]: shot for shot in survey_shots(result.merged)}
+        assert shots["90"]["FromID"] == "89"
+        assert (shots["92"]["FromID"], shots["92"]["Length"]) == ("4", "7.0")
+        assert shots["91"]["FromID"] == "92"
+
+    def test_identical_additions_are_kept_once(self):
+        survey_shots(self.ours).append(_new_shot(self.ours, "90", "89", "4.0"))
+        survey_shots(self.theirs).append(_new_shot(self.theirs, "90", "89", "4.0"))
+
+        result = self.merge()
+        assert result.renumbered == []
+        assert [shot["ID"] for shot in survey_shots(result.merged)].count("90") == 1
+
+    def test_conflicts(self):
+        find_shot(self.ours, "78")["Length"] = "10.0"
+        find_shot(self.theirs, "78")["Length"] = "11.0"
+        find_shot(self.ours, "1")["Depth"] = "-1.0"
+        survey_shots(self.theirs).remove(find_shot(self.theirs, "1"))
+        self.ours["CaveFile"]["unit"] = "ft"
+        self.theirs["CaveFile"]["unit"] = "yd"
+
+        result = self.merge()
+        assert result.has_conflicts
+        conflicts = result.conflicts
+        assert conflicts[0] == {
+            "kind": "edit",
+            "element": "header",
+            "id": None,
+            "field": "unit",
+            "base": "m",
+            "ours": "ft",
+            "theirs": "yd",
+        }
+        assert conflicts[1] == {
+            "kind": "edit",
+            "element": "shot",
+            "id": "78",
+            "field": "Length",
+            "base": "0.0",
+            "ours": "10.0",
+            "theirs": "11.0",
+        }
+        assert (conflicts[2]["kind"], conflicts[2]["id"]) == ("edit_delete", "1")
+        assert conflicts[2]["theirs"] is None
+        assert len(conflicts) == 3
+
+        merged = result.merged
+        assert merged["CaveFile"]["unit"] == "ft"
+        assert find_shot(merged, "78")["Length"] == "10.0"
+        assert find_shot(merged, "1")["Depth"] == "-1.0"
+
+    def test_layers(self):
+        layers = self.theirs["CaveFile"]["Layers"]["layerList"]
+        layers[0]["visible"] = "false"
+        layers.append({"name": "Survey", "visible": "true"})
+
+        merged = self.merge().merged
+        names = [layer["name"] for layer in merged["CaveFile"]["Layers"]["layerList"]]
+        assert names == ["Overlay", "Default", "Survey"]
+        assert merged["CaveFile"]["Layers"]["layerList"][0]["visible"] == "false"
+
+    def test_merged_survey_is_writable(self):
+        survey_shots(self.ours).append(_new_shot(self.ours, "90", "89", "4.0"))
+        survey_shots(self.theirs).append(_new_shot(self.theirs, "90", "4", "7.0"))
+        merged = self.merge().merged
+
+        with tempfile.TemporaryDirectory() as tmpdir:
+            path = Path(tmpdir) / "merged.tml"
+            ariane_core.dict_to_ariane_tml_file(merged, path)
+            reloaded = ariane_core.load_ariane_tml_file_to_dict(path)
+
+        assert not ariane_core.diff_surveys(merged, reloaded)
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,159 @@
+import copy
+import math
+import unittest
+import warnings
+
+import pytest
+from openspeleo_core import ariane_core
+
+from tests.ariane import HAND_SURVEY, survey_shots
+
+
+class TestUtm(unittest.TestCase):
+    def test_central_meridian(self):
+        # On the central meridian, the northing is the scaled meridian arc
+        utm = ariane_core.wgs84_to_utm(45.0, 3.0)
+        assert (utm.zone, utm.hemisphere) == (31, "N")
+        assert utm.easting == pytest.approx(500_000.0, abs=1e-3)
+        assert utm.northing == pytest.approx(4_982_950.400, abs=1e-3)
+        assert utm.convergence == pytest.approx(0.0)
+        assert utm.scale_factor == pytest.approx(0.9996)
+
+        utm = ariane_core.wgs84_to_utm(-45.0, 3.0)
+        assert utm.hemisphere == "S"
+        assert utm.northing == pytest.approx(5_017_049.600, abs=1e-3)
+
+    def test_known_point(self):
+        utm = ariane_core.wgs84_to_utm(48.8583, 2.2945)
+        assert (utm.zone, utm.hemisphere) == (31, "N")
+        assert utm.easting == pytest.approx(448_251.9, abs=0.1)
+        assert utm.northing == pytest.approx(5_411_943.8, abs=0.1)
+        assert utm.to_dict()["zone"] == 31
+
+    def test_zones(self):
+        for latitude, longitude, zone in [
+            (20.26, -87.49, 16),
+            (0.0, -180.0, 1),
+            (0.0, 179.9, 60),
+            (60.0, 5.0, 32),  # Norway
+            (78.0, 15.0, 33),  # Svalbard
+            (78.0, 8.0, 31),
+        ]:
+            assert ariane_core.wgs84_to_utm(latitude, longitude).zone == zone
+
+        forced = ariane_core.wgs84_to_utm(45.0, 5.0, zone=31)
+        assert forced.zone == 31
+        assert forced.easting > 500_000.0
+
+        with pytest.raises(ValueError, match="Invalid UTM zone"):
+            ariane_core.wgs84_to_utm(45.0, 5.0, zone=61)
+        with pytest.raises(ValueError, match="outside the UTM range"):
+            ariane_core.wgs84_to_utm(85.0, 5.0)
+
+    def test_convergence(self):
+        # East of the central meridian, true north points west of grid north
+        origin = ariane_core.wgs84_to_utm(45.0, 5.0, zone=31)
+        north = ariane_core.wgs84_to_utm(45.01, 5.0, zone=31)
+        grid_azimuth = math.degrees(
+            math.atan2(north.easting - origin.easting, north.northing - origin.northing)
+        )
+        assert origin.convergence > 0.0
+        assert grid_azimuth == pytest.approx(-origin.convergence, abs=0.01)
+
+    def test_round_trip(self):
+        for latitude, longitude in [(20.2591549, -87.4865103), (-33.9, 18.4)]:
+            utm = ariane_core.wgs84_to_utm(latitude, longitude)
+            assert utm.to_wgs84() == pytest.approx((latitude, longitude), abs=1e-9)
+            assert ariane_core.utm_to_wgs84(
+                utm.easting, utm.northing, utm.zone, utm.hemisphere
+            ) == pytest.approx((latitude, longitude), abs=1e-9)
+
+        with pytest.raises(ValueError, match="Unsupported hemisphere"):
+            ariane_core.utm_to_wgs84(500_000.0, 0.0, 31, "E")
+
+
+class TestLocalFrame(unittest.TestCase):
+    def test_round_trip(self):
+        east, north, up = ariane_core.wgs84_to_local(45.001, 3.001, 45.0, 3.0, 10.0)
+        assert east == pytest.approx(78.85, abs=0.01)
+        assert north == pytest.approx(111.13, abs=0.01)
+        assert up == pytest.approx(10.0, abs=0.01)
+        assert ariane_core.local_to_wgs84(east, north, up, 45.0, 3.0) == pytest.approx(
+            (45.001, 3.001, 10.0)
+        )
+
+
+class TestStationCoordinates(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.data = ariane_core.load_ariane_tml_file_to_dict(HAND_SURVEY)
+
+    def test_wgs84(self):
+        coordinates = ariane_core.station_coordinates(self.data)
+        assert coordinates["0"] == pytest.approx(
+            {"latitude": 20.2591549, "longitude": -87.48651031166666, "altitude": 0.0}
+        )
+        shots = self.data["CaveFile"]["Data"]["SurveyData"]
+        assert set(coordinates) == {shot["ID"] for shot in shots}
+        for shot in shots:
+            if shot["Type"] == "CLOSURE":
+                continue
+            altitude = coordinates[shot["ID"]]["altitude"]
+            assert altitude == pytest.approx(-float(shot["Depth"]))
+
+    def test_local(self):
+        local = ariane_core.station_coordinates(self.data, "local")
+        assert local["0"] == pytest.approx({"east": 0.0, "north": 0.0, "up": 0.0})
+
+        # Azimuths are corrected to true north before placing the stations
+        corrected = ariane_core.correct_declination(self.data, "true")
+        assert ariane_core.station_coordinates(corrected, "local") == local
+
+    def test_utm(self):
+        coordinates = ariane_core.station_coordinates(self.data)
+        utm = ariane_core.station_coordinates(self.data, "utm")
+        for station, position in utm.items():
+            assert (position["zone"], position["hemisphere"]) == (16, "N")
+            geodetic = coordinates[station]
+            assert ariane_core.utm_to_wgs84(
+                position["easting"], position["northing"], 16, "N"
+            ) == pytest.approx((geodetic["latitude"], geodetic["longitude"]))
+
+        forced = ariane_core.station_coordinates(self.data, "utm", zone=15)
+        assert {position["zone"] for position in forced.values()} == {15}
+
+    def test_location(self):
+        data = ariane_core.load_ariane_tml_file_to_dict(
+            "tests/artifacts/test_simple.tml"
+        )
+        with pytest.raises(ValueError, match="no located shot"):
+            ariane_core.station_coordinates(data)
+        with pytest.raises(ValueError, match="Unsupported coordinate system"):
+            ariane_core.station_coordinates(data, "lambert93")
+
+        coordinates = ariane_core.station_coordinates(
+            data, latitude=45.0, longitude=3.0
+        )
+        start = coordinates["0"]
+        assert (start["latitude"], start["longitude"]) == pytest.approx((45.0, 3.0))
+
+
+    def test_undated_magnetic_survey(self):
+        data = copy.deepcopy(self.data)
+        for shot in survey_shots(data):
+            shot.pop("Date", None)
+
+        with warnings.catch_warnings(record=True) as caught:
+            warnings.simplefilter("always")
+            coordinates = ariane_core.station_coordinates(data, "local")
+            ariane_core.dict_to_gpx_str(data)
+
+        assert len(caught) == 2
+        assert all(issubclass(w.category, UserWarning) for w in caught)
+        assert "no dated shot" in str(caught[0].message)
+        # The magnetic azimuths are used without correction
+        data["CaveFile"]["useMagneticAzimuth"] = "false"
+        assert coordinates == ariane_core.station_coordinates(data, "local")
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,110 @@
+import json
+import math
+import unittest
+
+import pytest
+from openspeleo_core import ariane_core
+
+
+def _shot(shot_id, from_id, azimuth, length, depth, **fields):
+    shot = {
+        "ID": shot_id,
+        "FromID": from_id,
+        "Azimut": azimuth,
+        "Length": length,
+        "Depth": depth,
+        "Inclination": "0.0",
+        "Type": "REAL",
+        "Excluded": "false",
+        "Section": "Main",
+        "Explorer": "<Explorer>Team A</Explorer><Surveyor>Jane</Surveyor>",
+        "Date": "2024-04-07",
+        "ClosureToID": "-1",
+    }
+    shot.update(fields)
+    return shot
+
+
+class TestSurveyStatistics(unittest.TestCase):
+    def test_small_survey(self):
+        data = {
+            "CaveFile": {
+                "caveName": "Small",
+                "unit": "m",
+                "Data": {
+                    "SurveyData": [
+                        _shot("0", "-1", "0.0", "0.0", "0.0", Type="START"),
+                        _shot("1", "0", "90.0", "10.0", "0.0"),
+                        _shot(
+                            "2",
+                            "1",
+                            "0.0",
+                            "5.0",
+                            "3.0",
+                            Section="Side",
+                            Date="2024-04-06",
+                            Explorer="Team B",
+                        ),
+                        _shot("3", "2", "180.0", "2.0", "3.0", Excluded="true"),
+                        _shot(
+                            "4",
+                            "2",
+                            "0.0",
+                            "0.0",
+                            "0.0",
+                            Type="CLOSURE",
+                            ClosureToID="0",
+                        ),
+                    ]
+                },
+            }
+        }
+
+        stats = ariane_core.survey_statistics(data)
+        assert (stats.cave_name, stats.unit) == ("Small", "m")
+        assert stats.shot_count == 5
+        assert stats.station_count == 4
+        assert stats.loop_count == 1
+        assert stats.total_length == 17.0
+        assert stats.surveyed_length == 15.0
+        assert math.isclose(stats.horizontal_extent, math.hypot(10, 4))
+        assert (stats.max_depth, stats.vertical_range) == (3.0, 3.0)
+        assert stats.length_by_section == {"Main": 10.0, "Side": 5.0}
+        assert stats.length_by_explorer == {"Team A": 10.0, "Team B": 5.0}
+        assert stats.length_by_date == {"2024-04-06": 5.0, "2024-04-07": 10.0}
+
+    def test_hand_survey(self):
+        data = ariane_core.load_ariane_tml_file_to_dict(
+            "tests/artifacts/hand_survey.tml"
+        )
+        stats = ariane_core.survey_statistics(data)
+        assert stats.shot_count == 90
+        assert stats.station_count == 88
+        assert stats.loop_count == 2
+        assert math.isclose(stats.surveyed_length, 774.52)
+        assert math.isclose(stats.max_depth, 23.06)
+        assert list(stats.length_by_explorer) == ["Cambrian Foundation"]
+        assert len(stats.length_by_section) == 6
+
+    def test_reports(self):
+        data = ariane_core.load_ariane_tml_file_to_dict(
+            "tests/artifacts/hand_survey.tml"
+        )
+        stats = ariane_core.survey_statistics(data)
+
+        markdown = stats.report()
+        assert markdown.startswith("# Carrie's Loft\n")
+        assert "| Surveyed length | 774.52 m |" in markdown
+        assert "| Loops | 2 |" in markdown
+        assert "| Cambrian Foundation | 774.52 |" in markdown
+
+        report = json.loads(stats.report(format="json"))
+        assert report == json.loads(json.dumps(stats.to_dict()))
+        assert report["station_count"] == 88
+
+        with pytest.raises(ValueError, match="Unsupported report format"):
+            stats.report(format="html")
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,101 @@
+import csv
+import tempfile
+import unittest
+from pathlib import Path
+
+import pytest
+from openspeleo_core import ariane_core
+
+from tests.ariane import HAND_SURVEY
+
+
+def _survey(*shots):
+    return {"CaveFile": {"unit": "m", "Data": {"SurveyData": list(shots)}}}
+
+
+class TestExportShotsCsv(unittest.TestCase):
+    def setUp(self):
+        self._tmpdir = tempfile.TemporaryDirectory()
+        self.path = Path(self._tmpdir.name) / "shots.csv"
+
+    def tearDown(self):
+        self._tmpdir.cleanup()
+
+    def _read(self, **kwargs):
+        with self.path.open(newline="", encoding="utf-8") as f:
+            return list(csv.reader(f, **kwargs))
+
+    def test_default_columns(self):
+        data = ariane_core.load_ariane_tml_file_to_dict(HAND_SURVEY)
+        ariane_core.export_shots_csv(data, self.path)
+        rows = self._read()
+
+        assert rows[0][:6] == ["ID", "FromID", "Type", "Section", "Length", "Azimut"]
+        shots = data["CaveFile"]["Data"]["SurveyData"]
+        assert len(rows) == len(shots) + 1
+        for row, shot in zip(rows[1:], shots, strict=True):
+            record = dict(zip(rows[0], row, strict=True))
+            assert record["ID"] == shot["ID"]
+            assert float(record["Length"]) == float(shot["Length"])
+            assert record["Comment"] == shot.get("Comment", "")
+
+    def test_quoting(self):
+        comment = 'Sump, "dive"\nline 2'
+        data = _survey(
+            {"ID": "1", "FromID": "0", "Length": "2.50", "Comment": comment},
+            {"ID": "2", "FromID": "1", "Length": 3, "Comment": "ok"},
+        )
+        ariane_core.export_shots_csv(data, self.path, columns=["ID", "Comment"])
+        assert self._read() == [["ID", "Comment"], ["1", comment], ["2", "ok"]]
+
+        text = self.path.read_bytes()
+        assert b'"Sump, ""dive""\nline 2"' in text
+        assert text.endswith(b"\r\n")
+
+    def test_numbers(self):
+        data = _survey(
+            {"ID": "1", "Length": "2.50", "Depth": "n/a", "Azimut": 10},
+            {"ID": "2"},
+        )
+        ariane_core.export_shots_csv(
+            data, self.path, columns=["Length", "Depth", "Azimut", "Missing"]
+        )
+        assert self._read()[1:] == [["2.5", "n/a", "10", ""], ["", "", "", ""]]
+
+    def test_computed_columns(self):
+        data = _survey(
+            {"ID": "0", "FromID": "-1", "Length": "0.0", "Depth": "0.0"},
+            {"ID": "1", "FromID": "0", "Length": "10.0", "Azimut": "90.0"},
+            {"ID": "2", "FromID": "1", "Length": "5.0", "Azimut": "0", "Depth": "3.0"},
+            {"ID": "9", "FromID": "8", "Length": "1.0", "Azimut": "0.0"},
+        )
+        ariane_core.export_shots_csv(
+            data,
+            self.path,
+            columns=["ID", "x", "y", "z", "cumulative_length"],
+            delimiter=";",
+        )
+        rows = self._read(delimiter=";")
+        assert rows[0] == ["ID", "x", "y", "z", "cumulative_length"]
+        values = {
+            row[0]: [float(value) if value else None for value in row[1:]]
+            for row in rows[1:]
+        }
+        assert values["0"] == [0.0, 0.0, 0.0, 0.0]
+        assert values["1"] == [10.0, 0.0, 0.0, 10.0]
+        assert values["2"] == pytest.approx([10.0, 4.0, -3.0, 15.0])
+        # Station 8 does not exist, so station 9 starts a new traverse at the origin
+        assert values["9"] == [0.0, 0.0, 0.0, 16.0]
+
+    def test_errors(self):
+        data = _survey({"ID": "1"})
+        with pytest.raises(ValueError, match="No column"):
+            ariane_core.export_shots_csv(data, self.path, columns=[])
+        with pytest.raises(ValueError, match="Invalid delimiter"):
+            ariane_core.export_shots_csv(data, self.path, delimiter='"')
+        with pytest.raises(OSError, match="Failed to create file"):
+            ariane_core.export_shots_csv(data, self.path / "missing" / "shots.csv")
+
+
+if __name__ == "__main__":
+    unittest.main()
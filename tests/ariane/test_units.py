@@ -0,0 +1,135 @@
+import math
+import unittest
+
+import pytest
+from openspeleo_core import ariane_core
+
+from tests.ariane import HAND_SURVEY, survey_shots
+
+
+class TestConvertUnits(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.data = ariane_core.load_ariane_tml_file_to_dict(HAND_SURVEY)
+
+    def test_meters_to_feet(self):
+        converted = ariane_core.convert_units(self.data, "ft")
+        assert converted["CaveFile"]["unit"] == "ft"
+        assert self.data["CaveFile"]["unit"] == "m"
+
+        shot = survey_shots(self.data)[2]
+        feet = survey_shots(converted)[2]
+        for field in ["Length", "Depth", "Left", "Right", "Up", "Down"]:
+            assert isinstance(feet[field], str)
+            assert math.isclose(
+                float(feet[field]), float(shot[field]) / 0.3048, abs_tol=1e-6
+            ), field
+
+        radius = shot["Shape"]["RadiusCollection"]["RadiusVector"][0]
+        radius_ft = feet["Shape"]["RadiusCollection"]["RadiusVector"][0]
+        assert math.isclose(
+            float(radius_ft["length"]), float(radius["length"]) / 0.3048, abs_tol=1e-6
+        )
+        assert feet["Azimut"] == shot["Azimut"]
+        assert feet["ID"] == shot["ID"]
+
+    def test_round_trip(self):
+        converted = ariane_core.convert_units(self.data, "ft")
+        back = ariane_core.convert_units(converted, "m")
+        assert not ariane_core.diff_surveys(self.data, back)
+
+    def test_formatting(self):
+        data = {
+            "CaveFile": {
+                "unit": "ft",
+                "firstStartAbsoluteElevation": "100.0",
+                "Data": {
+                    "SurveyData": {
+                        "ID": "1",
+                        "Length": "10.0",
+                        "Depth": 3,
+                        "DepthIn": "-1.0",
+                        "Left": "0.0",
+                    }
+                },
+            }
+        }
+        converted = ariane_core.convert_units(data, "meters")
+        assert converted["CaveFile"]["unit"] == "m"
+        assert converted["CaveFile"]["firstStartAbsoluteElevation"] == "30.48"
+        shot = converted["CaveFile"]["Data"]["SurveyData"]
+        assert shot["Length"] == "3.048"
+        assert shot["Depth"] == pytest.approx(0.9144)
+        assert shot["DepthIn"] == "-1.0"
+        assert shot["Left"] == "0.0"
+
+    def test_same_unit_is_unchanged(self):
+        assert ariane_core.convert_units(self.data, "m") == self.data
+
+    def test_errors(self):
+        with pytest.raises(ValueError, match="Unsupported unit"):
+            ariane_core.convert_units(self.data, "yd")
+        with pytest.raises(ValueError, match="no `unit` element"):
+            ariane_core.convert_units({"CaveFile": {}}, "m")
+
+    def test_load_in_unit(self):
+        loaded = ariane_core.load_ariane_tml_file_to_dict(HAND_SURVEY, unit="ft")
+        assert loaded == ariane_core.convert_units(self.data, "ft")
+
+        loaded = ariane_core.load_ariane_tml_file_to_dict(
+            HAND_SURVEY, unit="ft", mapping={"Length": "length_ft"}
+        )
+        assert "length_ft" in survey_shots(loaded)[0]
+
+    def test_statistics_in_unit(self):
+        meters = ariane_core.survey_statistics(self.data)
+        feet = ariane_core.survey_statistics(self.data, unit="ft")
+        assert feet.unit == "ft"
+        assert math.isclose(
+            feet.surveyed_length, meters.surveyed_length / 0.3048, rel_tol=1e-6
+        )
+
+
+
+class TestSurveyModel(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.data = ariane_core.load_ariane_tml_file_to_dict(HAND_SURVEY)
+
+    def test_shots(self):
+        survey = ariane_core.Survey(self.data)
+        assert survey.unit == "m"
+        assert survey.name == self.data["CaveFile"]["caveName"]
+        assert len(survey) == len(survey_shots(self.data))
+
+        shot, typed = survey_shots(self.data)[2], survey.shots[2]
+        assert typed.id == shot["ID"]
+        assert typed.length == pytest.approx(float(shot["Length"]))
+        assert typed.azimuth == pytest.approx(float(shot["Azimut"]))
+        assert survey.to_dict() == self.data
+
+        with pytest.raises(ValueError, match="no `unit` element"):
+            ariane_core.Survey({"CaveFile": {}})
+
+    def test_convert_units(self):
+        survey = ariane_core.Survey(self.data)
+        feet = ariane_core.convert_units(survey, "ft")
+        assert isinstance(feet, ariane_core.Survey)
+        assert (survey.unit, feet.unit) == ("m", "ft")
+        assert feet.to_dict() == ariane_core.convert_units(self.data, "ft")
+
+        for shot, shot_ft in zip(survey.shots, feet.shots, strict=True):
+            for field in ["length", "depth", "left", "right", "up", "down"]:
+                assert getattr(shot_ft, field) == pytest.approx(
+                    getattr(shot, field) / 0.3048
+                ), field
+            assert shot_ft.azimuth == shot.azimuth
+
+        # Loading in a unit gives the same survey
+        assert ariane_core.Survey(self.data, unit="ft").to_dict() == feet.to_dict()
+        with pytest.raises(ValueError, match="Unsupported unit"):
+            survey.convert_units("yd")
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,148 @@
+import contextlib
+import copy
+import io
+import json
+import tempfile
+import unittest
+from pathlib import Path
+
+from openspeleo_core import ariane_core
+from openspeleo_core.commands.validate import validate
+
+from tests.ariane import find_shot, survey_shots
+
+
+def _codes(diagnostics):
+    return [(d.code, d.shot_id, d.field) for d in diagnostics]
+
+
+class TestValidate(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.base = ariane_core.load_ariane_tml_file_to_dict(
+            "tests/artifacts/hand_survey.tml"
+        )
+
+    def setUp(self):
+        self.data = copy.deepcopy(self.base)
+
+    def test_valid_surveys(self):
+        for name in ["hand_survey", "test_simple", "test_with_walls"]:
+            data = ariane_core.load_ariane_tml_file_to_dict(
+                f"tests/artifacts/{name}.tml"
+            )
+            assert ariane_core.validate(data) == [], name
+
+    def test_station_references(self):
+        shot = find_shot(self.data, "3")
+        shot["FromID"] = "500"
+        shot["ClosureToID"] = "501"
+        find_shot(self.data, "4")["ID"] = "2"
+
+        diagnostics = ariane_core.validate(self.data)
+        assert _codes(diagnostics)[:3] == [
+            ("duplicate-id", "2", "ID"),
+            ("unknown-from-station", "3", "FromID"),
+            ("unknown-closure-station", "3", "ClosureToID"),
+        ]
+        assert all(d.is_error for d in diagnostics[:3])
+        assert diagnostics[1].message == "Station `500` does not exist"
+
+    def test_measurements(self):
+        shot = find_shot(self.data, "3")
+        shot["Azimut"] = "361.0"
+        shot["Inclination"] = "-91.0"
+        shot["Length"] = "-1.0"
+        shot["Left"] = "-0.5"
+        shot["Up"] = "n/a"
+
+        assert _codes(ariane_core.validate(self.data)) == [
+            ("invalid-number", "3", "Up"),
+            ("azimuth-out-of-range", "3", "Azimut"),
+            ("inclination-out-of-range", "3", "Inclination"),
+            ("negative-length", "3", "Length"),
+            ("negative-lrud", "3", "Left"),
+        ]
+
+    def test_non_finite_numbers(self):
+        shot = find_shot(self.data, "3")
+        shot["Length"] = "NaN"
+        shot["Azimut"] = "inf"
+        shot["Inclination"] = "-Infinity"
+
+        assert _codes(ariane_core.validate(self.data)) == [
+            ("invalid-number", "3", "Azimut"),
+            ("invalid-number", "3", "Inclination"),
+            ("invalid-number", "3", "Length"),
+        ]
+
+    def test_warnings(self):
+        shot = find_shot(self.data, "3")
+        shot["Depth"] = str(float(shot["Depth"]) + float(shot["Length"]) + 1)
+        shot["Date"] = "2999-01-01"
+        find_shot(self.data, "4")["Date"] = "01/02/2024"
+
+        diagnostics = ariane_core.validate(self.data)
+        codes = _codes(diagnostics)
+        assert ("depth-jump", "3", "Depth") in codes
+        assert ("future-date", "3", "Date") in codes
+        assert ("invalid-date", "4", "Date") in codes
+        assert all(d.severity == "warning" for d in diagnostics)
+
+    def test_orphan_component(self):
+        shots = survey_shots(self.data)
+        orphan = copy.deepcopy(find_shot(self.data, "4"))
+        orphan.update({"ID": "1000", "FromID": "999"})
+        child = copy.deepcopy(orphan)
+        child.update({"ID": "1001", "FromID": "1000"})
+        shots.extend([orphan, child])
+
+        diagnostics = ariane_core.validate(self.data)
+        assert _codes(diagnostics) == [
+            ("unknown-from-station", "1000", "FromID"),
+            ("orphan-component", "1000", None),
+        ]
+        assert diagnostics[1].to_dict() == {
+            "code": "orphan-component",
+            "severity": "warning",
+            "message": "2 shots are not connected to the survey start",
+            "shot_id": "1000",
+            "field": None,
+        }
+
+    def test_cli(self):
+        find_shot(self.data, "3")["Length"] = "-1.0"
+
+        with tempfile.TemporaryDirectory() as tmpdir:
+            path = Path(tmpdir) / "survey.tml"
+            ariane_core.dict_to_ariane_tml_file(self.data, path)
+
+            out = io.StringIO()
+            with contextlib.redirect_stdout(out):
+                assert validate(["-i", str(path)]) == 1
+            assert out.getvalue() == (
+                "error[negative-length] shot 3, Length: Length -1 is negative\n"
+            )
+
+            out = io.StringIO()
+            with contextlib.redirect_stdout(out):
+                assert validate(["-i", str(path), "--format", "json"]) == 1
+            assert json.loads(out.getvalue())[0]["code"] == "negative-length"
+
+            path = Path(tmpdir) / "survey.tmlu"
+            ariane_core.dict_to_ariane_tmlu_file(self.data, path)
+            with contextlib.redirect_stdout(io.StringIO()):
+                assert validate(["-i", str(path)]) == 1
+
+            # The format does not depend on the extension
+            path = Path(tmpdir) / "survey.xml"
+            ariane_core.dict_to_ariane_tmlu_file(self.data, path)
+            with contextlib.redirect_stdout(io.StringIO()):
+                assert validate(["-i", str(path)]) == 1
+
+        with contextlib.redirect_stdout(io.StringIO()):
+            assert validate(["-i", "tests/artifacts/hand_survey.tml"]) == 0
+
+
+if __name__ == "__main__":
+    unittest.main()
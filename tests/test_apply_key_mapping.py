@@ -1,6 +1,11 @@
 import unittest
+from collections import OrderedDict
+from collections import defaultdict
+from collections import namedtuple
+from types import MappingProxyType
 
 import pytest
+from openspeleo_core.mapping import KeyMapping
 from openspeleo_core.mapping import apply_key_mapping
 
 
@@ -89,5 +94,56 @@ class TestApplyKeyMapping(unittest.TestCase):
         assert apply_key_mapping(data, mapping) == data
 
 
+    def test_subclasses_passed_through_by_default(self):
+        data = {"Shape": OrderedDict(Azimuth="0.0"), "Shots": ({"Azimuth": "1.0"},)}
+        mapping = {"Azimuth": "Bearing"}
+        assert apply_key_mapping(data, mapping) == data
+
+    def test_generic_containers(self):
+        class ShotList(list):
+            pass
+
+        Shot = namedtuple("Shot", ["station", "data"])  # noqa: PYI024
+
+        data = OrderedDict(
+            Azimuth="90.0",
+            Shape=defaultdict(list, {"Azimuth": "0.0"}),
+            Shots=ShotList([{"Azimuth": "1.0"}, MappingProxyType({"Azimuth": "2.0"})]),
+            Pair=({"Azimuth": "3.0"}, "Azimuth"),
+            Named=Shot("A1", {"Azimuth": "4.0"}),
+        )
+        mapping = {"Azimuth": "Bearing"}
+        expected_output = {
+            "Bearing": "90.0",
+            "Shape": {"Bearing": "0.0"},
+            "Shots": [{"Bearing": "1.0"}, {"Bearing": "2.0"}],
+            "Pair": ({"Bearing": "3.0"}, "Azimuth"),
+            "Named": ("A1", {"Bearing": "4.0"}),
+        }
+
+        result = apply_key_mapping(data, mapping, generic_containers=True)
+        assert result == expected_output
+        assert type(result) is dict
+        assert type(result["Shots"]) is list
+        assert type(result["Pair"]) is tuple
+
+        result = apply_key_mapping(
+            data, KeyMapping(mapping), generic_containers=True
+        )
+        assert result == expected_output
+        assert type(result["Pair"]) is tuple
+
+    def test_generic_containers_tuple_root(self):
+        data = ({"Azimuth": "0.0"}, [{"Azimuth": "1.0"}])
+        assert apply_key_mapping(
+            data, {"Azimuth": "Bearing"}, generic_containers=True
+        ) == ({"Bearing": "0.0"}, [{"Bearing": "1.0"}])
+
+        with pytest.raises(TypeError, match="Unexpected type received for `data`"):
+            apply_key_mapping(
+                "Azimuth", {"Azimuth": "Bearing"}, generic_containers=True
+            )
+
+
 if __name__ == "__main__":
     unittest.main()
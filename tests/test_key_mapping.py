@@ -0,0 +1,180 @@
+import unittest
+
+import pytest
+from openspeleo_core.mapping import KeyMapping
+from openspeleo_core.mapping import apply_key_mapping
+
+ARIANE_TO_INTERNAL = {
+    "Azimut": "azimuth",
+    "Profiletype": "profile_type",
+    "Depth": "depth",
+}
+
+
+class TestKeyMapping(unittest.TestCase):
+    def setUp(self):
+        self.mapping = KeyMapping(ARIANE_TO_INTERNAL)
+
+    def test_forward_and_inverse(self):
+        data = {
+            "Data": {
+                "SurveyData": [
+                    {"Azimut": "12.0", "Depth": "3.5", "Profiletype": "VERTICAL"},
+                    {"Azimut": "90.0", "Depth": "4.0", "Explorer": "Ariane"},
+                ]
+            }
+        }
+        expected_output = {
+            "Data": {
+                "SurveyData": [
+                    {"azimuth": "12.0", "depth": "3.5", "profile_type": "VERTICAL"},
+                    {"azimuth": "90.0", "depth": "4.0", "Explorer": "Ariane"},
+                ]
+            }
+        }
+
+        mapped = self.mapping.forward(data)
+        assert mapped == expected_output
+        assert list(mapped["Data"]["SurveyData"][0]) == [
+            "azimuth",
+            "depth",
+            "profile_type",
+        ]
+        assert self.mapping.inverse(mapped) == data
+
+    def test_inverted(self):
+        inverted = self.mapping.inverted()
+        assert inverted.to_dict() == {v: k for k, v in ARIANE_TO_INTERNAL.items()}
+        assert inverted.forward({"azimuth": 1}) == {"Azimut": 1}
+        assert len(inverted) == len(ARIANE_TO_INTERNAL)
+
+    def test_not_invertible(self):
+        with pytest.raises(ValueError, match="not invertible"):
+            KeyMapping({"Azimut": "azimuth", "Azimuth": "azimuth"})
+
+    def test_invalid_mapping_type(self):
+        with pytest.raises(TypeError):
+            KeyMapping({"Azimut": 1})
+
+    def test_collision(self):
+        with pytest.raises(ValueError, match="Key collision"):
+            self.mapping.forward({"Azimut": "12.0", "azimuth": "13.0"})
+
+        with pytest.raises(ValueError, match="Key collision"):
+            self.mapping.inverse([{"Depth": "1.0", "depth": "1.0"}])
+
+    def test_primitive_values(self):
+        assert self.mapping.forward("Azimut") == "Azimut"
+        assert self.mapping.forward([1, "Depth", None]) == [1, "Depth", None]
+
+
+
+class TestScopedKeyMapping(unittest.TestCase):
+    def setUp(self):
+        self.data = {
+            "Data": {
+                "SurveyData": [
+                    {
+                        "Length": "5.0",
+                        "Name": "A1",
+                        "Shape": {
+                            "RadiusCollection": {
+                                "RadiusVector": [
+                                    {"angle": "0.0", "length": "1.5"},
+                                    {"angle": "90.0", "length": "2.0"},
+                                ]
+                            }
+                        },
+                    },
+                    {"Length": "3.0", "Name": "A2"},
+                ]
+            },
+            "Layers": {"layerList": [{"name": "Walls", "visible": "true"}]},
+        }
+
+    def test_scoped_rules(self):
+        mapping = KeyMapping(
+            {
+                "Data.SurveyData.*.Length": "shot_length",
+                "**.RadiusVector.length": "radius",
+                "**.layerList.name": "layer_name",
+                "Name": "name",
+                "angle": "direction",
+            }
+        )
+        mapped = mapping.forward(self.data)
+        shot = mapped["Data"]["SurveyData"][0]
+
+        assert shot["shot_length"] == "5.0"
+        assert shot["name"] == "A1"
+        assert mapped["Data"]["SurveyData"][1] == {"shot_length": "3.0", "name": "A2"}
+        assert shot["Shape"]["RadiusCollection"]["RadiusVector"][1] == {
+            "direction": "90.0",
+            "radius": "2.0",
+        }
+        assert mapped["Layers"]["layerList"] == [
+            {"layer_name": "Walls", "visible": "true"}
+        ]
+        assert mapping.inverse(mapped) == self.data
+        assert apply_key_mapping(self.data, mapping) == mapped
+
+    def test_apply_key_mapping_with_dict_matches_keys_literally(self):
+        # Path patterns need a KeyMapping, a plain dict only renames exact keys
+        data = {"a.b": 1, "a": {"b": 2}}
+        assert apply_key_mapping(data, {"a.b": "x"}) == {"x": 1, "a": {"b": 2}}
+        assert apply_key_mapping(data, KeyMapping({"a.b": "x"})) == {
+            "a.b": 1,
+            "a": {"x": 2},
+        }
+
+    def test_scope_without_list_index(self):
+        mapping = KeyMapping({"Data.SurveyData.Length": "shot_length"})
+        mapped = mapping.forward(self.data)
+        assert [shot["shot_length"] for shot in mapped["Data"]["SurveyData"]] == [
+            "5.0",
+            "3.0",
+        ]
+        assert mapped["Data"]["SurveyData"][0]["Shape"] == self.data["Data"][
+            "SurveyData"
+        ][0]["Shape"]
+
+    def test_scoped_rule_takes_precedence(self):
+        mapping = KeyMapping({"length": "size", "**.RadiusVector.length": "radius"})
+        data = {"length": 1, "RadiusVector": {"length": 2}}
+        assert mapping.forward(data) == {"size": 1, "RadiusVector": {"radius": 2}}
+
+    def test_inverse_matches_renamed_path(self):
+        mapping = KeyMapping(
+            {"SurveyData": "shots", "Data.SurveyData.Length": "length", "Data": "data"}
+        )
+        data = {"Data": {"SurveyData": [{"Length": "5.0"}]}}
+        mapped = mapping.forward(data)
+        assert mapped == {"data": {"shots": [{"length": "5.0"}]}}
+        assert mapping.inverse(mapped) == data
+
+        inverted = mapping.inverted()
+        assert inverted.to_dict() == {
+            "shots": "SurveyData",
+            "data.shots.length": "Length",
+            "data": "Data",
+        }
+        assert inverted.forward(mapped) == data
+
+    def test_scoped_invertibility(self):
+        # Both rules can never apply to the same dict
+        KeyMapping({"Data.SurveyData.Length": "length", "Shape.length": "length"})
+
+        with pytest.raises(ValueError, match="not invertible"):
+            KeyMapping({"**.SurveyData.Length": "length", "Shape.length": "length"})
+
+        with pytest.raises(ValueError, match="not invertible"):
+            KeyMapping({"Length": "length", "Shape.Size": "length"})
+
+    def test_invalid_patterns(self):
+        for pattern in ("Data.*", "Data.**", "Data..Length", "Data."):
+            with pytest.raises(ValueError, match="Invalid path pattern"):
+                KeyMapping({pattern: "x"})
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,166 @@
+import datetime
+import unittest
+
+import pytest
+from openspeleo_core.mapping import Transform
+from openspeleo_core.mapping import transform
+
+FEET_TO_METERS = 0.3048
+
+
+class TestTransform(unittest.TestCase):
+    def setUp(self):
+        self.data = {
+            "Data": {
+                "SurveyData": [
+                    {
+                        "Azimut": "90.0",
+                        "Depth": "10.0",
+                        "Locked": "false",
+                        "Date": "2011-08-03",
+                        "ID": "1",
+                        "Explorer": "Ariane",
+                    },
+                    {
+                        "Azimut": "180.0",
+                        "Depth": "20.0",
+                        "Locked": "true",
+                        "Date": "2011-08-05",
+                        "ID": "2",
+                    },
+                ]
+            },
+            "unit": "ft",
+        }
+
+    def test_transform(self):
+        spec = {
+            "Azimut": {"rename": "azimuth", "cast": "float"},
+            "Depth": {"rename": "depth", "factor": FEET_TO_METERS},
+            "Locked": {"drop": True},
+            "Date": {"cast": "date"},
+            "ID": {"cast": "int"},
+            "Data.SurveyData.Explorer": {"default": "Unknown"},
+            "unit": "units",
+        }
+        assert transform(self.data, spec) == {
+            "Data": {
+                "SurveyData": [
+                    {
+                        "azimuth": 90.0,
+                        "depth": pytest.approx(3.048),
+                        "Date": datetime.date(2011, 8, 3),
+                        "ID": 1,
+                        "Explorer": "Ariane",
+                    },
+                    {
+                        "azimuth": 180.0,
+                        "depth": pytest.approx(6.096),
+                        "Date": datetime.date(2011, 8, 5),
+                        "ID": 2,
+                        "Explorer": "Unknown",
+                    },
+                ]
+            },
+            "units": "ft",
+        }
+
+    def test_compiled_transform(self):
+        compiled = Transform({"Locked": {"cast": "bool"}, "ID": {"cast": "int"}})
+        result = compiled.apply(self.data)
+        shots = result["Data"]["SurveyData"]
+        assert [shot["Locked"] for shot in shots] == [False, True]
+        assert [shot["ID"] for shot in shots] == [1, 2]
+        assert transform(self.data, compiled) == result
+        assert len(compiled) == 2  # noqa: PLR2004
+
+    def test_casts(self):
+        data = {
+            "int": ["12", "12.0", 8.0, 3, None],
+            "float": ["1.5", 2, " 3 "],
+            "bool": ["TRUE", "no", 1, False],
+            "str": [1, 2.5, True],
+        }
+        spec = {key: {"cast": key} for key in data}
+        assert transform(data, spec) == {
+            "int": [12, 12, 8, 3, None],
+            "float": [1.5, 2.0, 3.0],
+            "bool": [True, False, True, False],
+            "str": ["1", "2.5", "True"],
+        }
+
+    def test_default_is_copied(self):
+        data = {"Shots": [{"ID": "1"}, {"ID": "2"}]}
+        default = {"Name": "Unknown"}
+        result = transform(data, {"Shots.Team": {"default": default}})
+        first, second = (shot["Team"] for shot in result["Shots"])
+        assert first == second == default
+
+        # Changing the default of one dict leaves the others and the spec alone
+        first["Name"] = "Ana Lopez"
+        assert second == default == {"Name": "Unknown"}
+
+    def test_factor_with_int_cast(self):
+        data = {"Length": "10"}
+        assert transform(data, {"Length": {"factor": 0.5, "cast": "int"}}) == {
+            "Length": 5
+        }
+
+    def test_scoped_transform(self):
+        data = {
+            "SurveyData": {"Length": "10.0"},
+            "RadiusVector": {"length": "1.0"},
+            "Shape": {"length": "2.0"},
+        }
+        spec = {"**.RadiusVector.length": {"factor": FEET_TO_METERS}}
+        assert transform(data, spec) == {
+            "SurveyData": {"Length": "10.0"},
+            "RadiusVector": {"length": pytest.approx(0.3048)},
+            "Shape": {"length": "2.0"},
+        }
+
+    def test_invalid_cast_value(self):
+        with pytest.raises(
+            ValueError, match="Cannot cast `abc` to float for key `Depth`"
+        ):
+            transform({"Depth": "abc"}, {"Depth": {"cast": "float"}})
+
+        # Integers are never rounded
+        for value in ["12.7", 12.7, "nan", float("inf")]:
+            with pytest.raises(ValueError, match="to int for key `ID`"):
+                transform({"ID": value}, {"ID": {"cast": "int"}})
+        with pytest.raises(ValueError, match="Cannot cast `3` to int"):
+            transform({"ID": "3"}, {"ID": {"cast": "int", "factor": 0.5}})
+
+        # Non-finite numbers are rejected, with or without a factor
+        for value in ["nan", "inf", "-Infinity", float("nan"), float("inf")]:
+            with pytest.raises(ValueError, match="to float for key `Depth`"):
+                transform({"Depth": value}, {"Depth": {"cast": "float"}})
+            with pytest.raises(ValueError, match="to float for key `Depth`"):
+                transform({"Depth": value}, {"Depth": {"factor": 2.0}})
+
+        with pytest.raises(ValueError, match="Cannot cast `2011/08/03` to date"):
+            transform({"Date": "2011/08/03"}, {"Date": {"cast": "date"}})
+
+    def test_invalid_spec(self):
+        invalid_specs = [
+            ({"Depth": {"cast": "double"}}, "Unsupported cast"),
+            ({"Depth": {"scale": 2}}, "Unsupported option"),
+            ({"Depth": {"drop": True, "rename": "x"}}, "cannot be combined"),
+            ({"Depth": {"factor": 2, "cast": "date"}}, "requires a numeric cast"),
+            ({"Depth": {"default": 0}}, "requires a path pattern"),
+        ]
+        for spec, message in invalid_specs:
+            with pytest.raises(ValueError, match=message):
+                Transform(spec)
+
+        with pytest.raises(TypeError):
+            Transform({"Depth": 1})
+
+    def test_rename_collision(self):
+        with pytest.raises(ValueError, match="Key collision"):
+            transform({"Depth": 1, "depth": 2}, {"Depth": "depth"})
+
+
+if __name__ == "__main__":
+    unittest.main()